@@ -0,0 +1,15 @@
+-- SPDX-License-Identifier: MPL-2.0
+CREATE TABLE
+    tags (
+        id INTEGER NOT NULL PRIMARY KEY,
+        name TEXT UNIQUE NOT NULL
+    );
+
+CREATE TABLE
+    records_tags (
+        record_id INTEGER NOT NULL REFERENCES records ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags ON DELETE CASCADE,
+        PRIMARY KEY (record_id, tag_id)
+    );
+
+CREATE INDEX records_tags_tag_id ON records_tags (tag_id);
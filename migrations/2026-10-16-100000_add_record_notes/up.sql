@@ -0,0 +1,3 @@
+-- SPDX-License-Identifier: MPL-2.0
+ALTER TABLE records
+ADD COLUMN notes TEXT;
@@ -0,0 +1,4 @@
+-- SPDX-License-Identifier: MPL-2.0
+CREATE INDEX records_open ON records (started_at)
+WHERE
+    ended_at IS NULL;
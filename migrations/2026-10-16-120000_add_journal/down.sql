@@ -0,0 +1,4 @@
+-- SPDX-License-Identifier: MPL-2.0
+DROP TABLE journal_records;
+
+DROP TABLE journal;
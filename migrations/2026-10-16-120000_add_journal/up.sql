@@ -0,0 +1,20 @@
+-- SPDX-License-Identifier: MPL-2.0
+CREATE TABLE
+    journal (
+        id INTEGER NOT NULL PRIMARY KEY,
+        command TEXT NOT NULL,
+        created_at TIMESTAMP NOT NULL,
+        undone BOOLEAN NOT NULL DEFAULT FALSE
+    );
+
+-- the record states are stored as JSON snapshots, so that records can be
+-- restored even after they have been deleted.  A NULL state means that the
+-- record did not exist at that point.
+CREATE TABLE
+    journal_records (
+        journal_id INTEGER NOT NULL REFERENCES journal ON DELETE CASCADE,
+        record_id INTEGER NOT NULL,
+        before_state TEXT,
+        after_state TEXT,
+        PRIMARY KEY (journal_id, record_id)
+    );
@@ -0,0 +1,13 @@
+-- SPDX-License-Identifier: MPL-2.0
+CREATE TABLE
+    absences (
+        id INTEGER NOT NULL PRIMARY KEY,
+        date DATE NOT NULL,
+        kind TEXT NOT NULL CHECK (
+            kind IN ('holiday', 'vacation', 'sick', 'comp-time')
+        ),
+        half_day BOOLEAN NOT NULL DEFAULT FALSE,
+        description TEXT
+    );
+
+CREATE INDEX absences_date ON absences (date);
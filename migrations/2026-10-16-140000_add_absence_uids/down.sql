@@ -0,0 +1,5 @@
+-- SPDX-License-Identifier: MPL-2.0
+DROP INDEX absences_uid_date;
+
+ALTER TABLE absences
+DROP COLUMN uid;
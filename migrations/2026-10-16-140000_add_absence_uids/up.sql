@@ -0,0 +1,7 @@
+-- SPDX-License-Identifier: MPL-2.0
+-- the UID of the calendar event an absence was imported from, so that
+-- importing the same calendar again updates the existing absences.
+ALTER TABLE absences
+ADD COLUMN uid TEXT;
+
+CREATE UNIQUE INDEX absences_uid_date ON absences (uid, date);
@@ -0,0 +1,8 @@
+-- SPDX-License-Identifier: MPL-2.0
+CREATE TABLE
+    overtime_adjustments (
+        id INTEGER NOT NULL PRIMARY KEY,
+        date DATE NOT NULL,
+        duration_seconds INTEGER NOT NULL,
+        description TEXT
+    );
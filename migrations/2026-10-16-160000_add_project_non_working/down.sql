@@ -0,0 +1,3 @@
+-- SPDX-License-Identifier: MPL-2.0
+ALTER TABLE projects
+DROP COLUMN non_working;
@@ -0,0 +1,5 @@
+-- SPDX-License-Identifier: MPL-2.0
+-- projects such as commuting or volunteering are still tracked, but don't
+-- count towards the time worked.
+ALTER TABLE projects
+ADD COLUMN non_working BOOLEAN NOT NULL DEFAULT FALSE;
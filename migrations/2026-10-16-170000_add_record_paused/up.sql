@@ -0,0 +1,5 @@
+-- SPDX-License-Identifier: MPL-2.0
+-- set when a record was stopped for a break, so that the time until the next
+-- record can be distinguished from time that simply wasn't tracked.
+ALTER TABLE records
+ADD COLUMN paused BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+-- SPDX-License-Identifier: MPL-2.0
+-- archived projects are hidden from the project list, and reusing them
+-- prints a warning.
+ALTER TABLE projects
+ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
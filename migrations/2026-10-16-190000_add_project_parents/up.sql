@@ -0,0 +1,5 @@
+-- SPDX-License-Identifier: MPL-2.0
+-- projects can be nested using paths like "client/project/subproject", where
+-- each project links to the project containing it.
+ALTER TABLE projects
+ADD COLUMN parent_id INTEGER REFERENCES projects;
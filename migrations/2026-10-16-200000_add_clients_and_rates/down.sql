@@ -0,0 +1,13 @@
+-- SPDX-License-Identifier: MPL-2.0
+ALTER TABLE records
+DROP COLUMN billable;
+
+ALTER TABLE projects
+DROP COLUMN billable;
+
+ALTER TABLE projects
+DROP COLUMN client_id;
+
+DROP TABLE rates;
+
+DROP TABLE clients;
@@ -0,0 +1,32 @@
+-- SPDX-License-Identifier: MPL-2.0
+CREATE TABLE
+    clients (
+        id INTEGER NOT NULL PRIMARY KEY,
+        name TEXT UNIQUE NOT NULL,
+        currency TEXT NOT NULL
+    );
+
+-- hourly rates, in hundredths of the client's currency (e.g. cents), that
+-- apply from the given date onwards.  Rates are set either for a client (as
+-- the default for all of its projects) or for a single project.
+CREATE TABLE
+    rates (
+        id INTEGER NOT NULL PRIMARY KEY,
+        client_id INTEGER REFERENCES clients,
+        project_id INTEGER REFERENCES projects,
+        effective_from DATE NOT NULL,
+        hourly_rate INTEGER NOT NULL,
+        CONSTRAINT rate_for_client_or_project CHECK (
+            (client_id IS NULL) != (project_id IS NULL)
+        )
+    );
+
+ALTER TABLE projects
+ADD COLUMN client_id INTEGER REFERENCES clients;
+
+ALTER TABLE projects
+ADD COLUMN billable BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- overrides the project's billable flag for a single record
+ALTER TABLE records
+ADD COLUMN billable BOOLEAN;
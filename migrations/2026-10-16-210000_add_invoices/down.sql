@@ -0,0 +1,7 @@
+-- SPDX-License-Identifier: MPL-2.0
+ALTER TABLE records
+DROP COLUMN invoice_id;
+
+DROP TABLE invoice_lines;
+
+DROP TABLE invoices;
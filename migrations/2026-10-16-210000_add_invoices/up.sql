@@ -0,0 +1,29 @@
+-- SPDX-License-Identifier: MPL-2.0
+CREATE TABLE
+    invoices (
+        id INTEGER NOT NULL PRIMARY KEY,
+        number TEXT UNIQUE NOT NULL,
+        client_id INTEGER NOT NULL REFERENCES clients,
+        currency TEXT NOT NULL,
+        issued_on DATE NOT NULL,
+        period_start DATE NOT NULL,
+        period_end DATE NOT NULL
+    );
+
+-- the line items are stored rather than recalculated, so that the invoice
+-- stays the same even if the rates or projects are changed later.  Amounts
+-- are in hundredths of the invoice's currency (e.g. cents).
+CREATE TABLE
+    invoice_lines (
+        id INTEGER NOT NULL PRIMARY KEY,
+        invoice_id INTEGER NOT NULL REFERENCES invoices,
+        project TEXT NOT NULL,
+        task TEXT NOT NULL,
+        hourly_rate INTEGER NOT NULL,
+        duration_seconds INTEGER NOT NULL,
+        amount INTEGER NOT NULL
+    );
+
+-- records that have been invoiced can no longer be changed
+ALTER TABLE records
+ADD COLUMN invoice_id INTEGER REFERENCES invoices;
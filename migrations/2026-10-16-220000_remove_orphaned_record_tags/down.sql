@@ -0,0 +1,3 @@
+-- SPDX-License-Identifier: MPL-2.0
+-- the removed tags belonged to records that no longer exist, so there is
+-- nothing to restore.
@@ -0,0 +1,18 @@
+-- SPDX-License-Identifier: MPL-2.0
+-- foreign keys weren't enforced on older databases, so deleting a record left
+-- its tags behind.  Those would be picked up by any new record that reuses
+-- the ID, so they are removed here.
+DELETE FROM records_tags
+WHERE
+    record_id NOT IN (
+        SELECT
+            id
+        FROM
+            records
+    )
+    OR tag_id NOT IN (
+        SELECT
+            id
+        FROM
+            tags
+    );
@@ -0,0 +1,16 @@
+-- SPDX-License-Identifier: MPL-2.0
+-- breaks added with `tst break` are stored with their start and end, so that
+-- only the break itself counts as intentional rather than the whole gap after
+-- a record.  Each break belongs to the record that it interrupted.
+CREATE TABLE
+    breaks (
+        id INTEGER NOT NULL PRIMARY KEY,
+        record_id INTEGER NOT NULL REFERENCES records ON DELETE CASCADE,
+        started_at TIMESTAMP NOT NULL,
+        ended_at TIMESTAMP NOT NULL,
+        CONSTRAINT ended_at_gt_started_at CHECK (ended_at > started_at)
+    );
+
+CREATE INDEX breaks_record_id ON breaks (record_id);
+
+CREATE INDEX breaks_started_at ON breaks (started_at);
@@ -0,0 +1,465 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Days, Duration, Months, NaiveDate, TimeZone, Utc};
+
+use crate::{
+    commands::Granularity,
+    records::{parent_projects, Record},
+    rounding::Rounding,
+};
+
+/// A period of time that records can be aggregated over.
+///
+/// Each variant holds the first day of the period: the day itself for
+/// `Day`, the Monday of the ISO week for `Week`, and the first of the month
+/// for `Month`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Period {
+    Day(NaiveDate),
+    Week(NaiveDate),
+    Month(NaiveDate),
+}
+
+impl Period {
+    /// Returns the period of the given granularity that contains `date`, or
+    /// `None` if the granularity does not describe a fixed period.
+    pub fn containing(granularity: &Granularity, date: NaiveDate) -> Option<Self> {
+        match granularity {
+            Granularity::Daily => Some(Period::Day(date)),
+            Granularity::Weekly => Some(Period::Week(
+                date - Days::new(date.weekday().num_days_from_monday().into()),
+            )),
+            Granularity::Monthly => Some(Period::Month(date.with_day(1)?)),
+            Granularity::All | Granularity::Auto => None,
+        }
+    }
+
+    /// The first day of the period
+    pub fn start(&self) -> NaiveDate {
+        match *self {
+            Period::Day(date) | Period::Week(date) | Period::Month(date) => date,
+        }
+    }
+
+    /// The first day after the end of the period
+    pub fn end(&self) -> NaiveDate {
+        match *self {
+            Period::Day(date) => date + Days::new(1),
+            Period::Week(date) => date + Days::new(7),
+            Period::Month(date) => date + Months::new(1),
+        }
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Period::Day(date) => {
+                let weekday = &date.weekday().to_string()[..2];
+                write!(f, "{weekday} {}", date.format("%e %b '%y"))
+            }
+            Period::Week(date) => {
+                // use the ISO week year, which differs from the calendar year
+                // for weeks that span the new year.
+                let week = date.iso_week();
+                write!(f, "{}-W{:02}", week.year(), week.week())
+            }
+            Period::Month(date) => write!(f, "{}", date.format("%b %Y")),
+        }
+    }
+}
+
+/// The total time spent on a single project during a single period
+#[derive(Debug, PartialEq)]
+pub struct Aggregate {
+    pub period: Period,
+    pub project: String,
+    pub non_working: bool,
+    pub tasks: Vec<String>,
+    pub duration: Duration,
+}
+
+/// Sums up the time spent per project for each period of the given granularity.
+///
+/// Durations are rounded per day according to the rounding scope before being
+/// summed together, so the weekly and monthly totals are always the sum of the
+/// daily totals.  Records are attributed to the day that they started on.  The
+/// results are sorted by period, and then by project in reverse order.
+pub fn aggregate<Tz>(
+    records: Vec<Record>,
+    now: DateTime<Utc>,
+    granularity: &Granularity,
+    tz: &Tz,
+    rounding: &Rounding,
+) -> Result<Vec<Aggregate>>
+where
+    Tz: TimeZone,
+{
+    Ok(aggregate_detailed(records, now, granularity, tz, rounding)?.projects)
+}
+
+/// The time spent per project, per tag, and in total for each period
+#[derive(Debug, PartialEq)]
+pub struct Aggregates {
+    pub projects: Vec<Aggregate>,
+    /// The time spent on records with each tag.  Tags are rounded in the same
+    /// way as projects.
+    pub tags: BTreeMap<Period, BTreeMap<String, Duration>>,
+    /// The total time worked in each period, which excludes any time spent on
+    /// non-working projects.
+    pub totals: BTreeMap<Period, Duration>,
+    /// The time worked on each project that has sub-projects, including the
+    /// time spent on all of its sub-projects.
+    pub rollups: BTreeMap<Period, BTreeMap<String, Duration>>,
+}
+
+/// Like [`aggregate`], but additionally returns the time spent per tag and the
+/// total time worked in each period.  If the rounding scope is per day, the
+/// per-project durations will not be rounded, and only the totals will be.
+pub fn aggregate_detailed<Tz>(
+    records: Vec<Record>,
+    now: DateTime<Utc>,
+    granularity: &Granularity,
+    tz: &Tz,
+    rounding: &Rounding,
+) -> Result<Aggregates>
+where
+    Tz: TimeZone,
+{
+    let mut days: BTreeMap<NaiveDate, Vec<Record>> = BTreeMap::new();
+    for record in records {
+        let date = record.started_at.with_timezone(tz).date_naive();
+        days.entry(date).or_default().push(record);
+    }
+
+    let mut periods: BTreeMap<Period, BTreeMap<String, (BTreeSet<String>, Duration)>> =
+        BTreeMap::new();
+    let mut tags: BTreeMap<Period, BTreeMap<String, Duration>> = BTreeMap::new();
+    let mut totals: BTreeMap<Period, Duration> = BTreeMap::new();
+    let mut non_working: BTreeSet<String> = BTreeSet::new();
+    for (date, records) in days {
+        let Some(period) = Period::containing(granularity, date) else {
+            bail!("cannot aggregate records with granularity {granularity:?}");
+        };
+
+        let day = rounding.round_day(
+            records
+                .iter()
+                .map(|record| (record.project.clone(), record.duration(now))),
+        );
+        let working_day = rounding.round_day(
+            records
+                .iter()
+                .filter(|record| !record.non_working)
+                .map(|record| (record.project.clone(), record.duration(now))),
+        );
+        *totals.entry(period).or_default() += working_day.total;
+
+        let day_tags = rounding.round_day(records.iter().flat_map(|record| {
+            let duration = record.duration(now);
+            record.tags.iter().map(move |tag| (tag.clone(), duration))
+        }));
+        let period_tags = tags.entry(period).or_default();
+        for (tag, duration) in day_tags.projects {
+            *period_tags.entry(tag).or_default() += duration;
+        }
+
+        let period_projects = periods.entry(period).or_default();
+        for record in records {
+            if record.non_working {
+                non_working.insert(record.project.clone());
+            }
+            let (tasks, _) = period_projects.entry(record.project).or_default();
+            tasks.insert(record.task);
+        }
+        for (project, duration) in day.projects {
+            let (_, total) = period_projects.entry(project).or_default();
+            *total += duration;
+        }
+    }
+
+    let mut rollups: BTreeMap<Period, BTreeMap<String, Duration>> = BTreeMap::new();
+    for (period, projects) in &periods {
+        let period_rollups = rollups.entry(*period).or_default();
+        for (project, (_, duration)) in projects {
+            if non_working.contains(project) {
+                continue;
+            }
+            for parent in parent_projects(project) {
+                *period_rollups.entry(parent.to_owned()).or_default() += *duration;
+            }
+        }
+        for (project, total) in period_rollups.iter_mut() {
+            if let Some((_, duration)) = projects.get(project) {
+                if !non_working.contains(project) {
+                    *total += *duration;
+                }
+            }
+        }
+    }
+
+    let non_working = &non_working;
+    let projects = periods
+        .into_iter()
+        .flat_map(|(period, projects)| {
+            projects
+                .into_iter()
+                .rev()
+                .map(move |(project, (tasks, duration))| Aggregate {
+                    period,
+                    non_working: non_working.contains(&project),
+                    project,
+                    tasks: tasks.into_iter().collect(),
+                    duration,
+                })
+        })
+        .collect();
+
+    Ok(Aggregates {
+        projects,
+        tags,
+        totals,
+        rollups,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rounding::{RoundingMode, RoundingScope};
+
+    use super::*;
+
+    fn rounding() -> Rounding {
+        Rounding::new(
+            Duration::minutes(15),
+            RoundingMode::Up,
+            RoundingScope::ProjectDay,
+        )
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn record(project: &str, task: &str, start: &str, end: &str) -> Record {
+        Record {
+            id: "hello".into(),
+            task: task.into(),
+            project: project.into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: start.parse().unwrap(),
+            ended_at: Some(end.parse().unwrap()),
+            tags: Vec::new(),
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn week_periods_start_on_monday() {
+        let period = Period::containing(&Granularity::Weekly, date(2024, 5, 12)).unwrap();
+        assert_eq!(period, Period::Week(date(2024, 5, 6)));
+        assert_eq!(period.end(), date(2024, 5, 13));
+    }
+
+    #[test]
+    fn week_periods_are_labelled_with_iso_week_year() {
+        let period = Period::containing(&Granularity::Weekly, date(2024, 12, 31)).unwrap();
+        assert_eq!(period, Period::Week(date(2024, 12, 30)));
+        assert_eq!(period.to_string(), "2025-W01");
+    }
+
+    #[test]
+    fn month_periods_are_labelled_with_month_and_year() {
+        let period = Period::containing(&Granularity::Monthly, date(2024, 2, 29)).unwrap();
+        assert_eq!(period, Period::Month(date(2024, 2, 1)));
+        assert_eq!(period.end(), date(2024, 3, 1));
+        assert_eq!(period.to_string(), "Feb 2024");
+    }
+
+    #[test]
+    fn aggregate_rounds_per_project_per_day_before_summing() {
+        let records = vec![
+            record("proj", "a", "2024-05-06T10:00:00Z", "2024-05-06T10:10:00Z"),
+            record("proj", "b", "2024-05-06T11:00:00Z", "2024-05-06T11:10:00Z"),
+            record("proj", "c", "2024-05-07T10:00:00Z", "2024-05-07T10:10:00Z"),
+        ];
+        let now = "2024-05-12T00:00:00Z".parse().unwrap();
+        let result = aggregate(records, now, &Granularity::Weekly, &Utc, &rounding()).unwrap();
+        assert_eq!(
+            result,
+            vec![Aggregate {
+                period: Period::Week(date(2024, 5, 6)),
+                project: "proj".into(),
+                non_working: false,
+                tasks: vec!["a".into(), "b".into(), "c".into()],
+                duration: Duration::minutes(45),
+            }]
+        );
+    }
+
+    #[test]
+    fn aggregate_excludes_non_working_projects_from_totals() {
+        let mut commute = record(
+            "commute",
+            "drive",
+            "2024-05-06T08:00:00Z",
+            "2024-05-06T09:00:00Z",
+        );
+        commute.non_working = true;
+        let records = vec![
+            commute,
+            record("proj", "a", "2024-05-06T10:00:00Z", "2024-05-06T12:00:00Z"),
+        ];
+        let now = "2024-05-12T00:00:00Z".parse().unwrap();
+        let result =
+            aggregate_detailed(records, now, &Granularity::Weekly, &Utc, &rounding()).unwrap();
+        let period = Period::Week(date(2024, 5, 6));
+        assert_eq!(result.totals[&period], Duration::hours(2));
+        assert_eq!(result.projects.len(), 2);
+        assert_eq!(result.projects[0].project, "proj");
+        assert!(!result.projects[0].non_working);
+        assert_eq!(result.projects[1].project, "commute");
+        assert!(result.projects[1].non_working);
+        assert_eq!(result.projects[1].duration, Duration::hours(1));
+    }
+
+    #[test]
+    fn aggregate_weekly_keeps_weeks_spanning_a_month_boundary_together() {
+        let records = vec![
+            record("proj", "a", "2024-04-30T10:00:00Z", "2024-04-30T11:00:00Z"),
+            record("proj", "b", "2024-05-02T10:00:00Z", "2024-05-02T11:00:00Z"),
+            record("other", "c", "2024-05-06T10:00:00Z", "2024-05-06T11:00:00Z"),
+        ];
+        let now = "2024-05-12T00:00:00Z".parse().unwrap();
+        let result = aggregate(records, now, &Granularity::Weekly, &Utc, &rounding()).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].period, Period::Week(date(2024, 4, 29)));
+        assert_eq!(result[0].duration, Duration::hours(2));
+        assert_eq!(result[1].period, Period::Week(date(2024, 5, 6)));
+        assert_eq!(result[1].project, "other");
+    }
+
+    #[test]
+    fn aggregate_monthly_splits_at_year_boundary() {
+        let records = vec![
+            record("proj", "a", "2024-12-31T10:00:00Z", "2024-12-31T11:00:00Z"),
+            record("proj", "b", "2025-01-01T10:00:00Z", "2025-01-01T12:00:00Z"),
+        ];
+        let now = "2025-01-12T00:00:00Z".parse().unwrap();
+        let result = aggregate(records, now, &Granularity::Monthly, &Utc, &rounding()).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].period, Period::Month(date(2024, 12, 1)));
+        assert_eq!(result[0].duration, Duration::hours(1));
+        assert_eq!(result[1].period, Period::Month(date(2025, 1, 1)));
+        assert_eq!(result[1].duration, Duration::hours(2));
+    }
+
+    #[test]
+    fn aggregate_sorts_projects_in_reverse_order_within_a_period() {
+        let records = vec![
+            record("abc", "a", "2024-05-06T10:00:00Z", "2024-05-06T11:00:00Z"),
+            record("xyz", "b", "2024-05-06T11:00:00Z", "2024-05-06T12:00:00Z"),
+        ];
+        let now = "2024-05-12T00:00:00Z".parse().unwrap();
+        let result = aggregate(records, now, &Granularity::Daily, &Utc, &rounding()).unwrap();
+        let projects = result
+            .iter()
+            .map(|a| a.project.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(projects, vec!["xyz", "abc"]);
+    }
+
+    #[test]
+    fn aggregate_with_day_scope_rounds_only_the_totals() {
+        let records = vec![
+            record("abc", "a", "2024-05-06T10:00:00Z", "2024-05-06T10:10:00Z"),
+            record("xyz", "b", "2024-05-06T11:00:00Z", "2024-05-06T11:10:00Z"),
+            record("xyz", "b", "2024-05-07T11:00:00Z", "2024-05-07T11:10:00Z"),
+        ];
+        let now = "2024-05-12T00:00:00Z".parse().unwrap();
+        let rounding = Rounding::new(Duration::minutes(15), RoundingMode::Up, RoundingScope::Day);
+        let result =
+            aggregate_detailed(records, now, &Granularity::Weekly, &Utc, &rounding).unwrap();
+        assert_eq!(result.projects[0].duration, Duration::minutes(20));
+        assert_eq!(result.projects[1].duration, Duration::minutes(10));
+        assert_eq!(
+            result.totals[&Period::Week(date(2024, 5, 6))],
+            Duration::minutes(45)
+        );
+    }
+
+    #[test]
+    fn aggregate_sums_up_time_per_tag() {
+        let mut meeting = record("abc", "a", "2024-05-06T10:00:00Z", "2024-05-06T10:10:00Z");
+        meeting.tags = vec!["billable".into(), "meeting".into()];
+        let mut billable = record("xyz", "b", "2024-05-06T11:00:00Z", "2024-05-06T11:10:00Z");
+        billable.tags = vec!["billable".into()];
+        let untagged = record("xyz", "c", "2024-05-07T11:00:00Z", "2024-05-07T11:10:00Z");
+
+        let now = "2024-05-12T00:00:00Z".parse().unwrap();
+        let result = aggregate_detailed(
+            vec![meeting, billable, untagged],
+            now,
+            &Granularity::Daily,
+            &Utc,
+            &rounding(),
+        )
+        .unwrap();
+        let tags = &result.tags[&Period::Day(date(2024, 5, 6))];
+        assert_eq!(tags["billable"], Duration::minutes(30));
+        assert_eq!(tags["meeting"], Duration::minutes(15));
+        assert!(result.tags[&Period::Day(date(2024, 5, 7))].is_empty());
+    }
+
+    #[test]
+    fn aggregate_rolls_up_time_into_parent_projects() {
+        let records = vec![
+            record("acme", "a", "2024-05-06T09:00:00Z", "2024-05-06T09:30:00Z"),
+            record(
+                "acme/web",
+                "b",
+                "2024-05-06T10:00:00Z",
+                "2024-05-06T11:00:00Z",
+            ),
+            record(
+                "acme/web/api",
+                "c",
+                "2024-05-06T11:00:00Z",
+                "2024-05-06T11:15:00Z",
+            ),
+            record("other", "d", "2024-05-06T12:00:00Z", "2024-05-06T13:00:00Z"),
+        ];
+        let now = "2024-05-12T00:00:00Z".parse().unwrap();
+        let result =
+            aggregate_detailed(records, now, &Granularity::Daily, &Utc, &rounding()).unwrap();
+        let rollups = &result.rollups[&Period::Day(date(2024, 5, 6))];
+        assert_eq!(
+            rollups.clone().into_iter().collect::<Vec<_>>(),
+            vec![
+                ("acme".to_owned(), Duration::minutes(105)),
+                ("acme/web".to_owned(), Duration::minutes(75)),
+            ]
+        );
+    }
+
+    #[test]
+    fn aggregate_rejects_granularity_all() {
+        let records = vec![record(
+            "abc",
+            "a",
+            "2024-05-06T10:00:00Z",
+            "2024-05-06T11:00:00Z",
+        )];
+        let now = "2024-05-12T00:00:00Z".parse().unwrap();
+        assert!(aggregate(records, now, &Granularity::All, &Utc, &rounding()).is_err());
+    }
+}
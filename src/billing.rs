@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use itertools::Itertools;
+
+use crate::{
+    records::{RateCard, Record},
+    rounding::{Rounding, RoundingScope},
+};
+
+/// The time billed for a single project (or task) at a single rate
+#[derive(Debug, PartialEq, Clone)]
+pub struct BillingLine {
+    pub client: String,
+    pub currency: String,
+    pub project: String,
+    /// The task, if the time was billed per task
+    pub task: Option<String>,
+    /// The hourly rate in hundredths of the currency (e.g. cents)
+    pub hourly_rate: i64,
+    pub duration: Duration,
+    /// The amount in hundredths of the currency (e.g. cents)
+    pub amount: i64,
+}
+
+/// The total billed to a single client
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClientTotal {
+    pub client: String,
+    pub currency: String,
+    pub duration: Duration,
+    pub amount: i64,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Billing {
+    /// The billed time, sorted by client, then project, task, and rate
+    pub lines: Vec<BillingLine>,
+    /// Billable projects that belong to a client but have no rate for some of
+    /// the days worked.  Time spent on these days is not billed.
+    pub missing_rates: BTreeSet<String>,
+}
+
+impl Billing {
+    /// Sums up the lines for each client.
+    pub fn client_totals(&self) -> Vec<ClientTotal> {
+        let mut totals: Vec<ClientTotal> = Vec::new();
+        for line in &self.lines {
+            match totals.last_mut() {
+                Some(total) if total.client == line.client => {
+                    total.duration += line.duration;
+                    total.amount += line.amount;
+                }
+                _ => totals.push(ClientTotal {
+                    client: line.client.clone(),
+                    currency: line.currency.clone(),
+                    duration: line.duration,
+                    amount: line.amount,
+                }),
+            }
+        }
+        totals
+    }
+}
+
+/// Calculates the amount to bill for the given records.  Only billable
+/// records in projects that belong to a client are billed.  Durations are
+/// rounded per day in the same way as for the aggregated views, except that
+/// if the rounding scope is per day, each project is rounded separately (as
+/// the projects may be billed at different rates).
+pub fn bill<Tz: TimeZone>(
+    records: Vec<Record>,
+    rate_card: &RateCard,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    rounding: &Rounding,
+) -> Billing {
+    bill_by(records, rate_card, now, tz, rounding, false)
+}
+
+/// Like [`bill`], but with a separate line for each task in each project, as
+/// used on invoices.  The time spent on each task is rounded separately.
+pub fn bill_tasks<Tz: TimeZone>(
+    records: Vec<Record>,
+    rate_card: &RateCard,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    rounding: &Rounding,
+) -> Billing {
+    bill_by(records, rate_card, now, tz, rounding, true)
+}
+
+fn bill_by<Tz: TimeZone>(
+    records: Vec<Record>,
+    rate_card: &RateCard,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    rounding: &Rounding,
+    by_task: bool,
+) -> Billing {
+    let rounding = match rounding.scope {
+        RoundingScope::Day => {
+            Rounding::new(rounding.unit, rounding.mode, RoundingScope::ProjectDay)
+        }
+        scope => Rounding::new(rounding.unit, rounding.mode, scope),
+    };
+
+    let mut days: BTreeMap<NaiveDate, Vec<Record>> = BTreeMap::new();
+    for record in records {
+        if !record.billable || record.non_working || rate_card.client(&record.project).is_none() {
+            continue;
+        }
+        let date = record.started_at.with_timezone(tz).date_naive();
+        days.entry(date).or_default().push(record);
+    }
+
+    let mut billing = Billing::default();
+    let mut lines: BTreeMap<(String, String, Option<String>, i64), Duration> = BTreeMap::new();
+    for (date, records) in days {
+        let groups = records.into_iter().into_group_map_by(|record| {
+            (record.project.clone(), by_task.then(|| record.task.clone()))
+        });
+        for ((project, task), records) in groups {
+            let Some(client) = rate_card.client(&project) else {
+                continue;
+            };
+            let Some(hourly_rate) = rate_card.hourly_rate(&project, date) else {
+                billing.missing_rates.insert(project);
+                continue;
+            };
+            let duration = rounding
+                .round_day(
+                    records
+                        .iter()
+                        .map(|record| (record.project.clone(), record.duration(now))),
+                )
+                .total;
+            *lines
+                .entry((client.name.clone(), project, task, hourly_rate))
+                .or_default() += duration;
+        }
+    }
+
+    billing.lines = lines
+        .into_iter()
+        .filter_map(|((client, project, task, hourly_rate), duration)| {
+            let client = rate_card.clients.get(&client)?;
+            Some(BillingLine {
+                client: client.name.clone(),
+                currency: client.currency.clone(),
+                project,
+                task,
+                hourly_rate,
+                duration,
+                amount: amount(hourly_rate, duration),
+            })
+        })
+        .collect();
+    billing
+}
+
+/// The amount to bill for the duration at the given hourly rate, rounded to
+/// the nearest hundredth.
+pub fn amount(hourly_rate: i64, duration: Duration) -> i64 {
+    let seconds = i128::from(duration.num_seconds());
+    let amount = (i128::from(hourly_rate) * seconds * 2 + 3600) / (3600 * 2);
+    amount as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        records::{Client, Rate},
+        rounding::RoundingMode,
+    };
+
+    use super::*;
+
+    fn record(project: &str, start: &str, end: &str) -> Record {
+        Record {
+            id: "hello".into(),
+            task: "task".into(),
+            project: project.into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: start.parse().unwrap(),
+            ended_at: Some(end.parse().unwrap()),
+            tags: Vec::new(),
+            notes: None,
+        }
+    }
+
+    fn rate_card() -> RateCard {
+        let rate = |month, hourly_rate| Rate {
+            effective_from: NaiveDate::from_ymd_opt(2024, month, 1).unwrap(),
+            hourly_rate,
+        };
+        RateCard {
+            clients: HashMap::from([(
+                "ACME".into(),
+                Client {
+                    name: "ACME".into(),
+                    currency: "EUR".into(),
+                },
+            )]),
+            project_clients: HashMap::from([
+                ("acme".into(), "ACME".into()),
+                ("unpriced".into(), "ACME".into()),
+            ]),
+            client_rates: HashMap::new(),
+            project_rates: HashMap::from([("acme".into(), vec![rate(1, 8000), rate(6, 9000)])]),
+        }
+    }
+
+    #[test]
+    fn bills_rounded_durations_at_the_effective_rate() {
+        let mut not_billable = record("acme", "2024-05-31T12:00:00Z", "2024-05-31T13:00:00Z");
+        not_billable.billable = false;
+        let records = vec![
+            record("acme/web", "2024-05-31T09:00:00Z", "2024-05-31T09:50:00Z"),
+            record("acme", "2024-06-03T09:00:00Z", "2024-06-03T10:30:00Z"),
+            record("internal", "2024-06-03T11:00:00Z", "2024-06-03T12:00:00Z"),
+            record("unpriced", "2024-06-03T12:00:00Z", "2024-06-03T13:00:00Z"),
+            not_billable,
+        ];
+        let rounding = Rounding::new(Duration::minutes(15), RoundingMode::Up, RoundingScope::Day);
+        let now = "2024-07-01T00:00:00Z".parse().unwrap();
+        let billing = bill(records, &rate_card(), now, &Utc, &rounding);
+
+        let line = |project: &str, hourly_rate, minutes, amount| BillingLine {
+            client: "ACME".into(),
+            currency: "EUR".into(),
+            project: project.into(),
+            task: None,
+            hourly_rate,
+            duration: Duration::minutes(minutes),
+            amount,
+        };
+        assert_eq!(
+            billing.lines,
+            vec![
+                line("acme", 9000, 90, 13500),
+                line("acme/web", 8000, 60, 8000),
+            ]
+        );
+        assert_eq!(billing.missing_rates, BTreeSet::from(["unpriced".into()]));
+        assert_eq!(
+            billing.client_totals(),
+            vec![ClientTotal {
+                client: "ACME".into(),
+                currency: "EUR".into(),
+                duration: Duration::minutes(150),
+                amount: 21500,
+            }]
+        );
+    }
+
+    #[test]
+    fn bills_each_task_separately() {
+        let mut review = record("acme", "2024-06-03T11:00:00Z", "2024-06-03T11:10:00Z");
+        review.task = "review".into();
+        let records = vec![
+            record("acme", "2024-06-03T09:00:00Z", "2024-06-03T09:20:00Z"),
+            record("acme", "2024-06-03T10:00:00Z", "2024-06-03T10:20:00Z"),
+            review,
+        ];
+        let rounding = Rounding::new(
+            Duration::minutes(15),
+            RoundingMode::Up,
+            RoundingScope::ProjectDay,
+        );
+        let now = "2024-07-01T00:00:00Z".parse().unwrap();
+        let billing = bill_tasks(records, &rate_card(), now, &Utc, &rounding);
+
+        let tasks = billing
+            .lines
+            .iter()
+            .map(|line| (line.task.as_deref(), line.duration.num_minutes()))
+            .collect::<Vec<_>>();
+        assert_eq!(tasks, vec![(Some("review"), 15), (Some("task"), 45)]);
+    }
+
+    #[test]
+    fn amounts_are_rounded_to_the_nearest_hundredth() {
+        assert_eq!(amount(10000, Duration::minutes(1)), 167);
+        assert_eq!(amount(9000, Duration::minutes(20)), 3000);
+    }
+}
@@ -1,19 +1,40 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::{collections::HashMap, io::Write, iter::Peekable};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    process::{Command, ExitCode},
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context as _, Result};
 use chrono::{
-    DateTime, Datelike, Duration, DurationRound, Local, NaiveDate, SubsecRound as _, TimeDelta,
-    Utc, Weekday,
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, SubsecRound as _, TimeDelta, Utc,
 };
 use itertools::Itertools;
 use timesheettool::{
-    commands::{Go, Granularity, ListRecords, Stop},
+    aggregate::aggregate,
+    billing::{bill, bill_tasks},
+    breaks::{find_gaps, ViolationKind},
+    check::{self, Problem},
+    commands::{
+        AbsenceArgs, AbsenceCommands, AddAbsence, AdjustOvertime, BillingReport, Break, Check,
+        ClientArgs, ClientCommands, Export, Go, Granularity, HolidaysArgs, HolidaysCommands,
+        ImportHolidays, InvoiceArgs, InvoiceCommands, InvoiceOutput, ListAbsences, ListProjects,
+        ListRecords, NoteArgs, OutputFormat, OvertimeCommands, Pause, ProjectArgs, ProjectCommands,
+        RemoveAbsence, ReportArgs, ReportCommands, Resume, Stop, Unpause, VacationRemaining,
+    },
     config::Config,
-    parse::{parse_date, parse_relative_date},
-    print::print,
-    records::{self, Record},
+    invoice::render_invoice,
+    overtime::{self, balance, EntryKind},
+    parse::{parse_date, parse_duration, parse_ics_events, parse_relative_date},
+    print::{
+        duration_to_string, format_amount, format_hours, print, write_rows, AbsenceRow,
+        AggregateRow, BillingRow, BreakRow, ClientRow, InvoiceRow, OvertimePeriodRow, OvertimeRow,
+        ProblemRow, ProjectRow, RecordRow, StatusRow, TimesRow, VacationRow, ViolationRow,
+    },
+    records::{self, Absence, AbsenceKind, Record},
+    rounding::{Rounding, RoundingMode, RoundingScope},
+    schedule::{Schedule, WeekHours},
 };
 
 pub fn go(config: Config, go: Go) -> Result<()> {
@@ -29,7 +50,86 @@ pub fn go(config: Config, go: Go) -> Result<()> {
         .map(|dt| parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse end time {dt}")))
         .transpose()?;
 
-    if !go.allow_overlap {
+    check_new_project(
+        &mut recs,
+        &go.project,
+        go.new_project || config.auto_create_projects,
+    )?;
+    let notes = read_notes(&go.note, None)?;
+    recs.journalled(&format!("go {}", go.name), |recs| {
+        let record = start_record(
+            recs,
+            &go.name,
+            &go.project,
+            &go.tags,
+            start_date,
+            end_date,
+            go.allow_overlap,
+        )?;
+        if let Some(notes) = notes {
+            recs.set_notes(&record.id, Some(notes.as_str()).filter(|n| !n.is_empty()))?;
+        }
+        Ok(())
+    })
+}
+
+pub fn resume(config: Config, resume: Resume) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+    let today = Local::now().naive_local().date();
+    let start_date = resume
+        .start
+        .map(|dt| parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse start time {dt}")))
+        .unwrap_or_else(|| Ok(Utc::now().round_subsecs(0)))?;
+    let end_date = resume
+        .end
+        .map(|dt| parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse end time {dt}")))
+        .transpose()?;
+
+    let previous = match &resume.record_id {
+        Some(record_id) => recs.get_record(record_id)?,
+        None => {
+            let previous = recs
+                .most_recent_record(start_date)?
+                .ok_or(anyhow!("no previous record found to resume"))?;
+            if previous.ended_at.is_none() {
+                bail!(
+                    "record {} for {} is still running",
+                    previous.id,
+                    previous.task
+                );
+            }
+            previous
+        }
+    };
+
+    recs.journalled(&format!("resume {}", previous.task), |recs| {
+        start_record(
+            recs,
+            &previous.task,
+            &previous.project,
+            &previous.tags,
+            start_date,
+            end_date,
+            resume.allow_overlap,
+        )
+    })?;
+
+    Ok(())
+}
+
+/// Adds a new record, closing (or splitting) the previous record if it would
+/// overlap with the new one, unless overlaps are explicitly allowed.
+fn start_record(
+    recs: &mut records::Records,
+    task: &str,
+    project: &str,
+    tags: &[String],
+    start_date: DateTime<Utc>,
+    end_date: Option<DateTime<Utc>>,
+    allow_overlap: bool,
+) -> Result<Record> {
+    if !allow_overlap {
         let updated = recs.complete_last_record(start_date, end_date)?;
         if updated.len() == 2 {
             log::info!(
@@ -47,18 +147,17 @@ pub fn go(config: Config, go: Go) -> Result<()> {
         }
     }
 
-    recs.add_record(&go.name, &go.project, start_date, end_date)?;
+    warn_if_archived(recs, project)?;
+    let record = recs.add_record(task, project, start_date, end_date)?;
+    recs.add_tags(&record.id, &tags.iter().map(String::as_str).collect_vec())?;
     match end_date {
-        None => log::info!("Added record for {} starting at {start_date}", go.name),
+        None => log::info!("Added record for {task} starting at {start_date}"),
         Some(end_date) => {
-            log::info!(
-                "Added record for {} starting at {start_date} and ending at {end_date}",
-                go.name
-            )
+            log::info!("Added record for {task} starting at {start_date} and ending at {end_date}")
         }
     }
 
-    Ok(())
+    Ok(record)
 }
 
 pub fn stop(config: Config, stop: Stop) -> Result<()> {
@@ -70,21 +169,195 @@ pub fn stop(config: Config, stop: Stop) -> Result<()> {
         .map(|dt| parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse end time {dt}")))
         .unwrap_or_else(|| Ok(Utc::now().round_subsecs(0)))?;
 
-    let updated = recs.complete_last_record(end_date, None)?;
-    if updated.len() == 1 {
+    // the notes are read before making any changes so that the database
+    // isn't left locked while the editor is open.
+    let previous = recs
+        .most_recent_record(end_date)?
+        .filter(|record| record.ended_at.is_none_or(|ended_at| ended_at > end_date));
+    let notes = match previous {
+        None => None,
+        Some(previous) => {
+            let existing = previous.notes.as_deref();
+            match (&stop.note.note, existing) {
+                // notes given on the command line are appended to any existing notes
+                (Some(note), Some(existing)) if !note.is_empty() => {
+                    Some(format!("{existing}\n{note}"))
+                }
+                _ => read_notes(&stop.note, existing)?,
+            }
+        }
+    };
+
+    recs.journalled("stop", |recs| {
+        let updated = recs.complete_last_record(end_date, None)?;
+        if updated.len() == 1 {
+            log::info!(
+                "Updated previous record for {} to end at {}",
+                updated[0].task,
+                end_date
+            );
+            if let Some(notes) = notes {
+                recs.set_notes(
+                    &updated[0].id,
+                    Some(notes.as_str()).filter(|n| !n.is_empty()),
+                )?;
+            }
+        } else {
+            log::warn!("No previous record found to be ended at {}", end_date);
+        }
+        Ok(())
+    })
+}
+
+pub fn pause(config: Config, pause: Pause) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+    let today = Local::now().naive_local().date();
+    let start_date = pause
+        .start
+        .map(|dt| parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse start time {dt}")))
+        .unwrap_or_else(|| Ok(Utc::now().round_subsecs(0)))?;
+
+    recs.journalled("pause", |recs| {
+        let updated = recs.complete_last_record(start_date, None)?;
+        let Some(record) = updated.first() else {
+            bail!("no record is running at {start_date}");
+        };
+        recs.set_paused(&record.id, true)?;
+        log::info!("Paused record for {} at {}", record.task, start_date);
+        Ok(())
+    })
+}
+
+pub fn unpause(config: Config, unpause: Unpause) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+    let today = Local::now().naive_local().date();
+    let end_date = unpause
+        .end
+        .map(|dt| parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse end time {dt}")))
+        .unwrap_or_else(|| Ok(Utc::now().round_subsecs(0)))?;
+
+    let paused = recs
+        .most_recent_record(end_date)?
+        .filter(|record| record.paused && record.ended_at.is_some())
+        .ok_or(anyhow!("no paused record found to continue"))?;
+
+    recs.journalled(&format!("unpause {}", paused.task), |recs| {
+        start_record(
+            recs,
+            &paused.task,
+            &paused.project,
+            &paused.tags,
+            end_date,
+            None,
+            false,
+        )
+    })?;
+
+    Ok(())
+}
+
+pub fn add_break(config: Config, pause: Break) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+    let today = Local::now().naive_local().date();
+    let start_date = parse_date(&pause.start, &Local, today)
+        .ok_or(anyhow!("could not parse start time {}", pause.start))?;
+    let end_date = parse_date(&pause.end, &Local, today)
+        .ok_or(anyhow!("could not parse end time {}", pause.end))?;
+    if end_date <= start_date {
+        bail!("the break must end after it starts");
+    }
+
+    recs.journalled("break", |recs| {
+        let record = recs.add_break(start_date, end_date)?;
         log::info!(
-            "Updated previous record for {} to end at {}",
-            updated[0].task,
+            "Added break after record for {} from {} to {}",
+            record.task,
+            start_date,
             end_date
         );
-    } else {
-        log::warn!("No previous record found to be ended at {}", end_date);
+        Ok(())
+    })
+}
+
+pub fn status(config: Config, format: OutputFormat) -> Result<ExitCode> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+
+    let Some(current) = recs.current_record()? else {
+        log::info!("No record is currently running");
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let now = Utc::now();
+    let today = Local::now().naive_local().date();
+    let start_of_day = parse_relative_date("1 day", &Local, today)
+        .ok_or(anyhow!("could not determine the start of the current day"))?;
+    // time on non-working projects isn't counted as worked, like in `times`
+    let today_total = recs
+        .list_records(start_of_day, now, &[])?
+        .iter()
+        .filter(|record| !record.non_working)
+        .map(|record| record.ended_at.unwrap_or(now).min(now) - record.started_at.max(start_of_day))
+        .sum::<TimeDelta>();
+
+    let mut stdout = std::io::stdout().lock();
+    if format != OutputFormat::Text {
+        write_rows(
+            &mut stdout,
+            format,
+            &[],
+            [StatusRow {
+                elapsed_seconds: current.duration(now).num_seconds(),
+                today_seconds: today_total.num_seconds(),
+                id: current.id,
+                project: current.project,
+                task: current.task,
+                started_at: current.started_at,
+                tags: current.tags,
+            }],
+        )?;
+        return Ok(ExitCode::SUCCESS);
     }
 
-    Ok(())
+    write!(stdout, "{}: {}", current.project, current.task)?;
+    for tag in &current.tags {
+        write!(stdout, " +{tag}")?;
+    }
+    writeln!(
+        stdout,
+        " (since {}, {}; today: {})",
+        current.started_at.with_timezone(&Local).format("%H:%M"),
+        duration_to_string(current.duration(now)),
+        duration_to_string(today_total),
+    )?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Applies the rounding given on the command line on top of the configured
+/// rounding.
+fn override_rounding(
+    rounding: &Rounding,
+    unit: Option<String>,
+    mode: Option<RoundingMode>,
+    scope: Option<RoundingScope>,
+) -> Result<Rounding> {
+    Ok(Rounding {
+        unit: unit
+            .map(|unit| {
+                parse_duration(&unit).ok_or(anyhow!("could not parse rounding unit {unit}"))
+            })
+            .transpose()?
+            .unwrap_or(rounding.unit),
+        mode: mode.unwrap_or(rounding.mode),
+        scope: scope.unwrap_or(rounding.scope),
+    })
 }
 
-pub fn ls(config: Config, list_records: ListRecords) -> Result<()> {
+pub fn ls(config: Config, format: OutputFormat, list_records: ListRecords) -> Result<()> {
     let mut conn = records::establish_connection(&config.database_path)?;
     let mut recs = records::Records::new(&mut conn);
 
@@ -97,11 +370,92 @@ pub fn ls(config: Config, list_records: ListRecords) -> Result<()> {
     let end = parse_relative_date(&list_records.until, &Local, today)
         .ok_or(anyhow!("could not parse end time {}", &list_records.until))?;
 
+    let granularity = resolve_granularity(list_records.granularity, start, end);
+    let rounding = override_rounding(
+        &config.rounding,
+        list_records.rounding,
+        list_records.rounding_mode,
+        list_records.rounding_scope,
+    )?;
+
+    let records = recs.list_records(
+        start,
+        end,
+        &list_records.tags.iter().map(String::as_str).collect_vec(),
+    )?;
+
+    let mut stdout = std::io::stdout().lock();
+    print(
+        &mut stdout,
+        now,
+        granularity,
+        format,
+        filter_projects(records, &list_records.projects),
+        &Local,
+        &rounding,
+        list_records.notes,
+    )?;
+    Ok(())
+}
+
+/// Keeps only the records in one of the given projects (or their
+/// sub-projects).  If no projects are given, all records are kept.
+fn filter_projects(records: Vec<Record>, projects: &[String]) -> Vec<Record> {
+    if projects.is_empty() {
+        return records;
+    }
+    records
+        .into_iter()
+        .filter(|record| projects.iter().any(|project| record.is_in_project(project)))
+        .collect()
+}
+
+/// Reads the notes given on the command line, or from the user's editor.
+/// Returns `None` if the notes should be left unchanged, and an empty string
+/// if the notes should be removed.
+fn read_notes(args: &NoteArgs, existing: Option<&str>) -> Result<Option<String>> {
+    if let Some(note) = &args.note {
+        return Ok(Some(note.trim().into()));
+    }
+    if !args.edit_note {
+        return Ok(None);
+    }
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    let path = std::env::temp_dir().join(format!("tst-notes-{}.txt", std::process::id()));
+    std::fs::write(
+        &path,
+        existing
+            .map(|notes| format!("{notes}\n"))
+            .unwrap_or_default(),
+    )?;
+
+    // the editor may include arguments (e.g. "code --wait"), so split it up
+    // into the program and its arguments before running it.
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or(anyhow!("no editor set in $EDITOR"))?;
+    let status = Command::new(program).args(parts).arg(&path).status();
+    let contents = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    if !status?.success() {
+        bail!("editor {editor} exited unsuccessfully, notes were not updated");
+    }
+    Ok(Some(contents?.trim().into()))
+}
+
+fn resolve_granularity(
+    granularity: Granularity,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Granularity {
     // TODO: this logic is a bit flimsy.  I think it needs to be based on the unit used by the user in parse_relative_date,
     // i.e. if I write a request in weeks, then I want to see daily granularity, and if I write a request in months then I
     // want to see monthly granularity?
-    let granularity = if list_records.granularity != Granularity::Auto {
-        list_records.granularity
+    if granularity != Granularity::Auto {
+        granularity
     } else if end - start <= Duration::days(6) {
         Granularity::All
     } else if end - start <= Duration::weeks(4) {
@@ -110,18 +464,120 @@ pub fn ls(config: Config, list_records: ListRecords) -> Result<()> {
         Granularity::Weekly
     } else {
         Granularity::Monthly
+    }
+}
+
+pub(crate) fn export(config: Config, format: OutputFormat, export: Export) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+
+    let now = Utc::now();
+    let today = Local::now().naive_local().date();
+    let start = parse_relative_date(&export.since, &Local, today)
+        .ok_or(anyhow!("could not parse start time {}", &export.since))?;
+    let end = parse_relative_date(&export.until, &Local, today)
+        .ok_or(anyhow!("could not parse end time {}", &export.until))?;
+
+    // exporting as text doesn't make much sense, so fall back to CSV as the
+    // most spreadsheet-friendly format.
+    let format = match format {
+        OutputFormat::Text => OutputFormat::Csv,
+        format => format,
     };
+    let granularity = resolve_granularity(export.granularity, start, end);
+    let rounding = override_rounding(
+        &config.rounding,
+        export.rounding,
+        export.rounding_mode,
+        export.rounding_scope,
+    )?;
+    let records = recs.list_records(
+        start,
+        end,
+        &export.tags.iter().map(String::as_str).collect_vec(),
+    )?;
+    let records = filter_projects(records, &export.projects);
 
     let mut stdout = std::io::stdout().lock();
-    print(
-        &mut stdout,
-        now,
-        granularity,
-        recs.list_records(start, end)?,
-        &Local,
-        config.time_round_minutes,
-    )?;
-    Ok(())
+    match granularity {
+        Granularity::All => write_rows(
+            &mut stdout,
+            format,
+            &export.columns,
+            records
+                .into_iter()
+                .map(|record| RecordRow::new(record, now)),
+        ),
+        _ => write_rows(
+            &mut stdout,
+            format,
+            &export.columns,
+            aggregate(records, now, &granularity, &Local, &rounding)?
+                .into_iter()
+                .map(AggregateRow::from),
+        ),
+    }
+}
+
+pub(crate) fn check(config: Config, format: OutputFormat, check: Check) -> Result<ExitCode> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+    let now = Utc::now();
+
+    let mut fixed = Vec::new();
+    if let Some(strategy) = check.fix {
+        recs.journalled("check --fix", |recs| {
+            // fixing one problem can change or resolve the others, so the
+            // records are checked again after every fix.
+            loop {
+                let records = recs.all_records()?.collect::<Result<Vec<_>>>()?;
+                let Some(problem) = check::find_problems(records, now)
+                    .into_iter()
+                    .find(Problem::is_fixable)
+                else {
+                    return Ok(());
+                };
+                if !check::fix(recs, &problem, strategy)? {
+                    bail!("could not fix problem: {problem}");
+                }
+                fixed.push(problem);
+            }
+        })?;
+    }
+
+    let records = recs.all_records()?.collect::<Result<Vec<_>>>()?;
+    let remaining = check::find_problems(records, now);
+    let exit_code = match remaining.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    if format != OutputFormat::Text {
+        let fixed = fixed.iter().map(|problem| ProblemRow::new(problem, true));
+        let remaining = remaining
+            .iter()
+            .map(|problem| ProblemRow::new(problem, false));
+        write_rows(&mut stdout, format, &[], fixed.chain(remaining))?;
+        return Ok(exit_code);
+    }
+
+    for problem in &fixed {
+        writeln!(stdout, "fixed: {problem}")?;
+    }
+    for problem in &remaining {
+        writeln!(stdout, "{problem}")?;
+    }
+    match (fixed.len(), remaining.len()) {
+        (0, 0) => log::info!("No problems found"),
+        (_, 0) => log::info!("Fixed {} problem(s)", fixed.len()),
+        (_, count) if check.fix.is_none() => {
+            log::info!("Found {count} problem(s), use --fix to repair them")
+        }
+        (_, count) => log::info!("{count} problem(s) could not be fixed"),
+    }
+
+    Ok(exit_code)
 }
 
 pub(crate) fn edit(config: Config, edit: timesheettool::commands::Edit) -> Result<()> {
@@ -133,19 +589,93 @@ pub(crate) fn edit(config: Config, edit: timesheettool::commands::Edit) -> Resul
         .start
         .map(|dt| parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse start time {dt}")))
         .transpose()?;
-    let end_date = edit
-        .end
-        .map(|dt| parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse end time {dt}")))
-        .transpose()?;
+    let end_date = match edit.reopen {
+        true => Some(None),
+        false => edit
+            .end
+            .map(|dt| {
+                parse_date(&dt, &Local, today).ok_or(anyhow!("could not parse end time {dt}"))
+            })
+            .transpose()?
+            .map(Some),
+    };
     let task_name = edit.task;
+    if let Some(project) = &edit.project {
+        check_new_project(
+            &mut recs,
+            project,
+            edit.new_project || config.auto_create_projects,
+        )?;
+    }
 
-    let record = recs.update_record(
-        &edit.record_id,
-        start_date,
-        end_date,
-        task_name.as_deref(),
-        None,
-    )?;
+    let notes = match edit.note.note.is_some() || edit.note.edit_note {
+        true => {
+            let existing = recs.get_record(&edit.record_id)?.notes;
+            read_notes(&edit.note, existing.as_deref())?
+        }
+        false => None,
+    };
+
+    let record = recs.journalled(&format!("edit {}", edit.record_id), |recs| {
+        recs.add_tags(
+            &edit.record_id,
+            &edit.add_tags.iter().map(String::as_str).collect_vec(),
+        )?;
+        recs.remove_tags(
+            &edit.record_id,
+            &edit.remove_tags.iter().map(String::as_str).collect_vec(),
+        )?;
+        if let Some(notes) = notes {
+            recs.set_notes(
+                &edit.record_id,
+                Some(notes.as_str()).filter(|n| !n.is_empty()),
+            )?;
+        }
+        if let Some(project) = &edit.project {
+            warn_if_archived(recs, project)?;
+        }
+        if start_date.is_some() || end_date.is_some() {
+            let record = recs.get_record(&edit.record_id)?;
+            let start_date = start_date.unwrap_or(record.started_at);
+            let end_date = end_date.unwrap_or(record.ended_at);
+            match edit.shift_neighbours {
+                true => {
+                    for neighbour in recs.shift_neighbours(&record.id, start_date, end_date)? {
+                        log::info!(
+                            "Updated neighbouring record for {} to {}",
+                            neighbour.task,
+                            format_times(neighbour.started_at, neighbour.ended_at)
+                        );
+                    }
+                }
+                false => {
+                    if let Some(neighbour) = recs
+                        .overlapping_records(&record.id, start_date, end_date)?
+                        .first()
+                    {
+                        bail!(
+                            "record {} would overlap with record {} ({}, {}), \
+                            use --shift-neighbours to trim the neighbouring records",
+                            record.id,
+                            neighbour.id,
+                            neighbour.task,
+                            format_times(neighbour.started_at, neighbour.ended_at)
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(billable) = edit.billable {
+            recs.set_billable(&edit.record_id, billable.as_override())?;
+        }
+        recs.update_record(
+            &edit.record_id,
+            start_date,
+            end_date,
+            task_name.as_deref(),
+            edit.project.as_deref(),
+        )
+    })?;
 
     log::info!("Record updated: {record:?}");
 
@@ -156,17 +686,60 @@ pub(crate) fn delete(config: Config, delete: timesheettool::commands::Delete) ->
     let mut conn = records::establish_connection(&config.database_path)?;
     let mut recs = records::Records::new(&mut conn);
 
-    recs.delete_record(&delete.record_id)?;
+    recs.journalled(&format!("delete {}", delete.record_id), |recs| {
+        recs.delete_record(&delete.record_id)
+    })?;
 
     log::info!("Record deleted: {}", delete.record_id);
 
     Ok(())
 }
 
-pub(crate) fn times(config: Config, times: timesheettool::commands::Times) -> Result<()> {
+pub(crate) fn undo(config: Config, undo: timesheettool::commands::Undo) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+
+    let operations = recs.undo(undo.count)?;
+    if operations.is_empty() {
+        log::warn!("Nothing to undo");
+    }
+    for operation in operations {
+        log::info!(
+            "Undid \"{}\" from {}",
+            operation.command,
+            operation.created_at.with_timezone(&Local).format("%F %T")
+        );
+    }
+
+    Ok(())
+}
+
+pub(crate) fn redo(config: Config, redo: timesheettool::commands::Redo) -> Result<()> {
     let mut conn = records::establish_connection(&config.database_path)?;
     let mut recs = records::Records::new(&mut conn);
 
+    let operations = recs.redo(redo.count)?;
+    if operations.is_empty() {
+        log::warn!("Nothing to redo");
+    }
+    for operation in operations {
+        log::info!(
+            "Redid \"{}\" from {}",
+            operation.command,
+            operation.created_at.with_timezone(&Local).format("%F %T")
+        );
+    }
+
+    Ok(())
+}
+
+pub(crate) fn times(
+    config: Config,
+    format: OutputFormat,
+    times: timesheettool::commands::Times,
+) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+
     let now = Utc::now();
     let today = Local::now().naive_local().date();
     let start = parse_relative_date(&times.since, &Local, today)
@@ -174,238 +747,973 @@ pub(crate) fn times(config: Config, times: timesheettool::commands::Times) -> Re
     let end = parse_relative_date(&times.until, &Local, today)
         .ok_or(anyhow!("could not parse end time {}", &times.until))?;
 
-    let mut stdout = std::io::stdout().lock();
-    let days = recs
-        .list_records(start, end)?
+    let mut absences = records::Absences::new(&mut conn)
+        .list_absences(
+            start.with_timezone(&Local).date_naive(),
+            (end - TimeDelta::seconds(1))
+                .with_timezone(&Local)
+                .date_naive(),
+        )?
+        .into_iter()
+        .into_group_map_by(|absence| absence.date);
+
+    let mut recs = records::Records::new(&mut conn);
+
+    // non-working records aren't counted as worked, but they still account
+    // for the time between working records.
+    let records = recs.list_records(start, end, &[])?;
+    let breaks = recs.list_breaks(start, end)?;
+
+    // the end of the previous working day is needed to check the rest period
+    // before the first day shown.
+    let mut previous_end = match records.iter().find(|record| !record.non_working) {
+        Some(first) => recs
+            .most_recent_record(first.started_at)?
+            .filter(|record| !record.non_working)
+            .and_then(|record| record.ended_at),
+        None => None,
+    };
+
+    let days = records
         .into_iter()
         .chunk_by(|r| r.started_at.with_timezone(&Local).date_naive());
 
+    let mut rows = Vec::new();
     for (day, records) in &days {
-        let mut records = records.peekable();
-
-        let start = records
-            .peek()
-            .unwrap()
-            .started_at
-            .duration_trunc(TimeDelta::minutes(15))
-            .unwrap();
-        let start_local = start.with_timezone(&Local);
-
-        let start_text = start_local.format("%H:%M");
-
-        let (end, pauses) = breaks(records);
-        let end = end.map(|last| {
-            // There is no `duration_ceil` or similar, but this *should* do the right
-            // thing, right?
-            (last + TimeDelta::seconds(((60 * 15) / 2) - 1))
-                .duration_round(TimeDelta::minutes(15))
-                .unwrap()
+        let records = records.collect_vec();
+        let Some(start) = records
+            .iter()
+            .find(|record| !record.non_working)
+            .map(|record| record.started_at)
+        else {
+            continue;
+        };
+        let (end, gaps) = find_gaps(&records, &breaks);
+        // a gap split up by non-working records is still a single pause
+        let pauses = gaps
+            .iter()
+            .map(|gap| (gap.started_at, gap.ended_at))
+            .coalesce(|a, b| match a.1 == b.0 {
+                true => Ok((a.0, b.1)),
+                false => Err((a, b)),
+            })
+            .collect_vec();
+        let work_day = config
+            .breaks
+            .evaluate(start, end.unwrap_or(now), &pauses, previous_end);
+        previous_end = end;
+
+        rows.push(TimesRow {
+            date: day,
+            started_at: Some(work_day.started_at),
+            ended_at: end.map(|_| work_day.ended_at),
+            duration_seconds: work_day.worked.num_seconds(),
+            breaks: gaps.into_iter().map(BreakRow::from).collect(),
+            absences: absences
+                .remove(&day)
+                .unwrap_or_default()
+                .into_iter()
+                .map(AbsenceRow::from)
+                .collect(),
+            violations: work_day
+                .violations
+                .into_iter()
+                .map(ViolationRow::from)
+                .collect(),
         });
-        let mut hours = end.unwrap_or(now) - start;
+    }
+
+    // days where nothing was worked still get listed if there was an absence
+    for (day, absences) in absences {
+        rows.push(TimesRow {
+            date: day,
+            started_at: None,
+            ended_at: None,
+            duration_seconds: 0,
+            breaks: Vec::new(),
+            absences: absences.into_iter().map(AbsenceRow::from).collect(),
+            violations: Vec::new(),
+        });
+    }
+    rows.sort_by_key(|row| row.date);
+
+    let mut stdout = std::io::stdout().lock();
+    if format != OutputFormat::Text {
+        return write_rows(&mut stdout, format, &[], rows);
+    }
 
-        let end = end
+    for row in rows {
+        let absences = row
+            .absences
+            .iter()
+            .map(|absence| match absence.half_day {
+                true => format!("half-day {}", absence.kind),
+                false => absence.kind.clone(),
+            })
+            .join(", ");
+        let Some(started_at) = row.started_at else {
+            writeln!(stdout, "{}: {absences}", row.date)?;
+            continue;
+        };
+
+        let start_text = started_at.with_timezone(&Local).format("%H:%M");
+        let end = row
+            .ended_at
             .map(|last| last.with_timezone(&Local).format("%H:%M").to_string())
             .unwrap_or("     ".into());
-
-        let mut pause_sum = TimeDelta::zero();
-        let pauses = pauses
-            .into_iter()
-            .map(|(start, end)| {
-                pause_sum += end - start;
-                let start = start.with_timezone(&Local).format("%H:%M");
-                let end = end.with_timezone(&Local).format("%H:%M");
-                format!("{start} - {end}")
+        let pauses = row
+            .breaks
+            .iter()
+            .map(|pause| {
+                let start = pause.started_at.with_timezone(&Local).format("%H:%M");
+                let end = pause.ended_at.with_timezone(&Local).format("%H:%M");
+                match (pause.untracked, pause.non_working) {
+                    (true, _) => format!("{start} - {end} untracked"),
+                    (_, true) => format!("{start} - {end} non-working"),
+                    _ => format!("{start} - {end}"),
+                }
             })
             .join(", ");
 
-        hours -= (pause_sum).max(TimeDelta::minutes(30));
+        let absences = match absences.is_empty() {
+            true => absences,
+            false => format!(", absences: {absences}"),
+        };
 
         writeln!(
             stdout,
-            "{day}: {start_text} - {end}  (hours: {}, breaks: {pauses})",
-            format_duration(hours),
+            "{}: {start_text} - {end}  (hours: {}, breaks: {pauses}{absences})",
+            row.date,
+            format_duration(TimeDelta::seconds(row.duration_seconds)),
         )?;
+
+        for violation in &row.violations {
+            let actual = format_duration(TimeDelta::seconds(violation.actual_seconds));
+            let limit = format_duration(TimeDelta::seconds(violation.limit_seconds));
+            match violation.kind {
+                ViolationKind::InsufficientBreak => {
+                    writeln!(stdout, "    ! breaks of {actual}, {limit} required")?
+                }
+                ViolationKind::MaxDailyExceeded => writeln!(
+                    stdout,
+                    "    ! worked {actual}, more than the maximum {limit}"
+                )?,
+                ViolationKind::InsufficientRest => writeln!(
+                    stdout,
+                    "    ! rested {actual} since the previous day, {limit} required"
+                )?,
+            }
+        }
     }
 
     Ok(())
 }
 
+fn format_times(started_at: DateTime<Utc>, ended_at: Option<DateTime<Utc>>) -> String {
+    let started_at = started_at.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+    match ended_at {
+        Some(ended_at) => format!(
+            "{started_at}-{}",
+            ended_at.with_timezone(&Local).format("%H:%M")
+        ),
+        None => format!("{started_at} onwards"),
+    }
+}
+
 fn format_duration(delta: TimeDelta) -> String {
     let minutes = delta.num_minutes() % 60;
     let hours = delta.num_minutes() / 60;
-    return format!("{hours:0>2}:{minutes:0>2}");
-}
-
-fn breaks(
-    records: impl Iterator<Item = Record>,
-) -> (Option<DateTime<Utc>>, Vec<(DateTime<Utc>, DateTime<Utc>)>) {
-    let mut end: Option<DateTime<Utc>> = None;
-    let mut pauses = Vec::new();
-    for record in records {
-        if let Some(end) = end {
-            let gap = record.started_at - end;
-            if gap > TimeDelta::seconds(60) {
-                let gap_start = end.duration_round(TimeDelta::minutes(5)).unwrap();
-                let gap_end = record
-                    .started_at
-                    .duration_round(TimeDelta::minutes(5))
-                    .unwrap();
-
-                pauses.push((gap_start, gap_end));
+    format!("{hours:0>2}:{minutes:0>2}")
+}
+
+pub(crate) fn overtime(
+    config: Config,
+    format: OutputFormat,
+    overtime: timesheettool::commands::Overtime,
+) -> Result<()> {
+    if let Some(OvertimeCommands::Adjust(adjust)) = overtime.command {
+        return adjust_overtime(config, adjust);
+    }
+
+    let mut conn = records::establish_connection(&config.database_path)?;
+
+    let now = Utc::now();
+    let today = Local::now().naive_local().date();
+
+    // future absences are ignored, as they would otherwise affect the balance
+    // before the day has actually happened.
+    let mut days_off = BTreeMap::<NaiveDate, f64>::new();
+    for absence in records::Absences::new(&mut conn).list_absences(NaiveDate::MIN, today)? {
+        let credited = match absence.kind.is_credited() {
+            true => absence.days(),
+            false => 0.0,
+        };
+        *days_off.entry(absence.date).or_default() += credited;
+    }
+
+    let adjustments = records::Adjustments::new(&mut conn).list_adjustments()?;
+
+    let mut recs = records::Records::new(&mut conn);
+    let start = parse_relative_date(&overtime.since, &Local, today)
+        .ok_or(anyhow!("could not parse start time {}", &overtime.since))?;
+    let end = parse_relative_date(&overtime.until, &Local, today)
+        .ok_or(anyhow!("could not parse end time {}", &overtime.until))?;
+    let granularity = resolve_granularity(overtime.granularity, start, end);
+    let start_date = start.with_timezone(&Local).date_naive();
+    let end = end.with_timezone(&Local).naive_local();
+
+    let schedule = match overtime.hours {
+        Some(hours) => Schedule::new(WeekHours::weekdays(hours), Vec::new()),
+        None => config.schedule,
+    };
+    let records = recs
+        .all_records()?
+        .filter_ok(|record| !record.non_working)
+        .collect::<Result<Vec<_>>>()?;
+    let hours_worked = records
+        .into_iter()
+        .chunk_by(|record| record.started_at.with_timezone(&Local).date_naive())
+        .into_iter()
+        .map(|(day, records)| {
+            let durations = records
+                .map(|record| (record.project.clone(), record.duration(now)))
+                .collect_vec();
+            let worked = config.rounding.round_day(durations).total;
+            (day, worked.num_seconds() as f64 / (60.0 * 60.0))
+        })
+        .collect::<BTreeMap<_, _>>();
+    let days = overtime::overtime_days(&hours_worked, &days_off, &schedule, today);
+    let entries = balance(days, adjustments, &config.overtime, today)
+        .into_iter()
+        .filter(|entry| entry.date >= start_date && entry.date.and_time(NaiveTime::MIN) < end);
+
+    let mut stdout = std::io::stdout().lock();
+    if granularity != Granularity::All {
+        let summaries = overtime::summarise(entries, &granularity)?;
+        if format != OutputFormat::Text {
+            return write_rows(
+                &mut stdout,
+                format,
+                &[],
+                summaries.into_iter().map(OvertimePeriodRow::from),
+            );
+        }
+
+        for summary in summaries {
+            write!(
+                stdout,
+                "{}: worked {:.2} of {:.2} ({:+.2})",
+                summary.period,
+                summary.hours_worked,
+                summary.hours_expected,
+                summary.hours_worked - summary.hours_expected
+            )?;
+            if summary.hours_adjusted != 0.0 {
+                write!(stdout, ", adjustments {:+.2}", summary.hours_adjusted)?;
             }
+            writeln!(stdout, "   (balance: {:+.2})", summary.hours_balance)?;
         }
-        end = record.ended_at;
+        return Ok(());
+    }
+
+    if format != OutputFormat::Text {
+        return write_rows(&mut stdout, format, &[], entries.map(OvertimeRow::from));
     }
 
-    (end, pauses)
+    for entry in entries {
+        match entry.kind {
+            EntryKind::Initial => write!(
+                stdout,
+                "Starting balance on {}: {:+.2}",
+                entry.date, entry.hours_difference
+            )?,
+            EntryKind::Day => write!(
+                stdout,
+                "Hours worked for day {}: {:.2} ({:+.2})",
+                entry.date, entry.hours_worked, entry.hours_difference
+            )?,
+            EntryKind::Adjustment => write!(
+                stdout,
+                "Adjustment on {}: {:+.2} ({})",
+                entry.date,
+                entry.hours_difference,
+                entry.description.as_deref().unwrap_or("no description")
+            )?,
+            EntryKind::Reset => write!(
+                stdout,
+                "Balance reset on {}: {:+.2}",
+                entry.date, entry.hours_difference
+            )?,
+        }
+        writeln!(stdout, "   (balance: {:+.2})", entry.hours_balance)?;
+    }
+
+    Ok(())
 }
 
-pub(crate) fn overtime(config: Config, overtime: timesheettool::commands::Overtime) -> Result<()> {
+fn adjust_overtime(config: Config, adjust: AdjustOvertime) -> Result<()> {
     let mut conn = records::establish_connection(&config.database_path)?;
-    let mut recs = records::Records::new(&mut conn);
+    let mut adjustments = records::Adjustments::new(&mut conn);
 
-    let now = Utc::now();
-    let today = Local::now().naive_local().date();
-    let start = parse_relative_date(&overtime.since, &Local, today)
-        .ok_or(anyhow!("could not parse start time {}", &overtime.since))?
-        .with_timezone(&Local)
-        .date_naive();
-
-    for record in OvertimeIter::new(
-        recs.all_records()?,
-        overtime.hours,
-        config.time_round_minutes,
-        now,
-    ) {
-        let record = record?;
-        if record.date < start {
+    let date = adjust
+        .date
+        .unwrap_or_else(|| Local::now().naive_local().date());
+    let adjustment =
+        adjustments.add_adjustment(date, adjust.amount, adjust.description.as_deref())?;
+    log::info!(
+        "Adjusted overtime balance by {:+.2} hours on {}",
+        adjustment.duration.num_seconds() as f64 / (60.0 * 60.0),
+        adjustment.date
+    );
+
+    Ok(())
+}
+
+pub(crate) fn absence(config: Config, format: OutputFormat, absence: AbsenceArgs) -> Result<()> {
+    match absence.command {
+        AbsenceCommands::Add(add) => add_absence(config, add),
+        AbsenceCommands::Ls(list) => list_absences(config, format, list),
+        AbsenceCommands::Rm(remove) => remove_absence(config, remove),
+        AbsenceCommands::Remaining(remaining) => vacation_remaining(config, format, remaining),
+    }
+}
+
+fn add_absence(config: Config, add: AddAbsence) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut absences = records::Absences::new(&mut conn);
+
+    let until = add.until.unwrap_or(add.date);
+    if until < add.date {
+        bail!("the end date {until} is before the start date {}", add.date);
+    }
+
+    for date in add.date.iter_days().take_while(|date| *date <= until) {
+        if add.until.is_some() && config.schedule.expected_hours(date) == 0.0 {
+            log::debug!("Skipping {date} as it is not a working day");
             continue;
         }
-        println!(
-            "Hours worked for day {}: {:.2} ({:+.2})   (balance: {:+.2})",
-            record.date, record.hours_day, record.hours_difference, record.hours_total
+
+        let absence =
+            absences.add_absence(date, add.kind, add.half_day, add.description.as_deref())?;
+        log::info!(
+            "Added {} on {} ({})",
+            absence.kind,
+            absence.date,
+            absence.id
         );
     }
 
     Ok(())
 }
 
-struct OvertimeIter<T>
-where
-    T: Iterator<Item = Result<Record>>,
-{
-    now: DateTime<Utc>,
-    day: Option<NaiveDate>,
-    seconds_day: HashMap<String, i64>,
-    hours_total: f64,
-    hours_for_day: f64,
-    rounding_minutes: u32,
-    records: Peekable<T>,
-    finished: bool,
-}
-
-#[derive(Debug)]
-struct OvertimeRecord {
-    date: NaiveDate,
-    hours_day: f64,
-    hours_difference: f64,
-    hours_total: f64,
-}
-
-impl<T> OvertimeIter<T>
-where
-    T: Iterator<Item = Result<Record>>,
-{
-    pub fn new(records: T, hours_for_day: f64, rounding_minutes: u32, now: DateTime<Utc>) -> Self {
-        Self {
-            now,
-            hours_for_day,
-            rounding_minutes,
-            records: records.peekable(),
-            day: None,
-            seconds_day: HashMap::new(),
-            hours_total: 0.0,
-            finished: false,
-        }
-    }
-}
-
-impl<T> Iterator for OvertimeIter<T>
-where
-    T: Iterator<Item = Result<Record>>,
-{
-    type Item = Result<OvertimeRecord>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.finished {
-            return None;
-        }
-
-        let today = self.day;
-        loop {
-            match self.records.peek() {
-                None => {
-                    self.finished = true;
-                    break;
-                }
-                Some(Err(_)) => {
-                    return Some(Err(self.records.next().unwrap().unwrap_err()));
-                }
-                Some(Ok(record)) => {
-                    let day = record.started_at.with_timezone(&Local).date_naive();
-                    if Some(day) != self.day {
-                        self.day = Some(day);
-                        break;
-                    }
+fn list_absences(config: Config, format: OutputFormat, list: ListAbsences) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut absences = records::Absences::new(&mut conn);
 
-                    let seconds = record.duration(self.now).num_seconds();
+    let year = list.year.unwrap_or_else(|| Local::now().year());
+    let absences = absences_in_year(&mut absences, year)?;
 
-                    self.seconds_day
-                        .entry(record.project.clone())
-                        .and_modify(|e| *e += seconds)
-                        .or_insert(seconds);
-                }
+    let mut stdout = std::io::stdout().lock();
+    if format != OutputFormat::Text {
+        return write_rows(
+            &mut stdout,
+            format,
+            &[],
+            absences.into_iter().map(AbsenceRow::from),
+        );
+    }
+
+    writeln!(
+        stdout,
+        "Date           ( id  )  Kind              Description"
+    )?;
+    for absence in absences {
+        let kind = match absence.half_day {
+            true => format!("{} (half)", absence.kind),
+            false => absence.kind.to_string(),
+        };
+        writeln!(
+            stdout,
+            "{} {}  ({})  {:16}  {}",
+            &absence.date.weekday().to_string()[..2],
+            absence.date.format("%e %b '%y"),
+            absence.id,
+            kind,
+            absence.description.as_deref().unwrap_or_default()
+        )?;
+    }
+
+    Ok(())
+}
+
+fn remove_absence(config: Config, remove: RemoveAbsence) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut absences = records::Absences::new(&mut conn);
+
+    let absence = absences.delete_absence(&remove.absence_id)?;
+    log::info!(
+        "Absence deleted: {} on {} ({})",
+        absence.kind,
+        absence.date,
+        absence.id
+    );
+
+    Ok(())
+}
+
+fn vacation_remaining(
+    config: Config,
+    format: OutputFormat,
+    remaining: VacationRemaining,
+) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut absences = records::Absences::new(&mut conn);
+
+    let allowance = config.vacation_days_per_year.ok_or(anyhow!(
+        "no vacation allowance set, use vacation_days_per_year in the config file to set one"
+    ))?;
+    let year = remaining.year.unwrap_or_else(|| Local::now().year());
+    let taken = absences_in_year(&mut absences, year)?
+        .iter()
+        .filter(|absence| absence.kind == AbsenceKind::Vacation)
+        .map(Absence::days)
+        .sum::<f64>();
+    let row = VacationRow {
+        year,
+        allowance_days: allowance,
+        taken_days: taken,
+        remaining_days: allowance - taken,
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    if format != OutputFormat::Text {
+        return write_rows(&mut stdout, format, &[], [row]);
+    }
+
+    writeln!(
+        stdout,
+        "Vacation days for {}: {} of {} taken ({} remaining)",
+        row.year, row.taken_days, row.allowance_days, row.remaining_days
+    )?;
+
+    Ok(())
+}
+
+fn absences_in_year(absences: &mut records::Absences, year: i32) -> Result<Vec<Absence>> {
+    let start = NaiveDate::from_ymd_opt(year, 1, 1).ok_or(anyhow!("invalid year {year}"))?;
+    let end = NaiveDate::from_ymd_opt(year, 12, 31).ok_or(anyhow!("invalid year {year}"))?;
+    absences.list_absences(start, end)
+}
+
+pub(crate) fn holidays(config: Config, holidays: HolidaysArgs) -> Result<()> {
+    match holidays.command {
+        HolidaysCommands::Import(import) => import_holidays(config, import),
+    }
+}
+
+fn import_holidays(config: Config, import: ImportHolidays) -> Result<()> {
+    let contents = std::fs::read_to_string(&import.file)
+        .with_context(|| format!("could not read calendar file {:?}", import.file))?;
+    let events = parse_ics_events(&contents);
+    if events.is_empty() {
+        log::warn!("No all-day events found in {:?}", import.file);
+        return Ok(());
+    }
+
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut absences = records::Absences::new(&mut conn);
+    let summary = absences.import_holidays(&events)?;
+    log::info!(
+        "Imported {} events: {} holidays added, {} removed, {} unchanged",
+        events.len(),
+        summary.added,
+        summary.removed,
+        summary.unchanged
+    );
+
+    Ok(())
+}
+
+pub(crate) fn project(config: Config, format: OutputFormat, project: ProjectArgs) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+
+    let (name, non_working) = match project.command {
+        ProjectCommands::NonWorking(name) => (name, true),
+        ProjectCommands::Working(name) => (name, false),
+        ProjectCommands::Ls(list) => return list_projects(&mut conn, format, list),
+        ProjectCommands::Rename(rename) => {
+            let project =
+                records::Projects::new(&mut conn).rename(&rename.project, &rename.new_name)?;
+            log::info!("Renamed project {} to {}", rename.project, project.name);
+            return Ok(());
+        }
+        ProjectCommands::Merge(merge) => {
+            let mut recs = records::Records::new(&mut conn);
+            let moved = recs.journalled(&format!("project merge {}", merge.from), |recs| {
+                recs.merge_projects(&merge.from, &merge.into)
+            })?;
+            log::info!(
+                "Moved {moved} record(s) from {} into {}",
+                merge.from,
+                merge.into
+            );
+            return Ok(());
+        }
+        ProjectCommands::Archive(name) => {
+            records::Projects::new(&mut conn).set_archived(&name.project, true)?;
+            log::info!("Archived project {}", name.project);
+            return Ok(());
+        }
+        ProjectCommands::Unarchive(name) => {
+            records::Projects::new(&mut conn).set_archived(&name.project, false)?;
+            log::info!("Restored project {}", name.project);
+            return Ok(());
+        }
+        ProjectCommands::Client(assign) => {
+            records::Projects::new(&mut conn)
+                .set_client(&assign.project, assign.client.as_deref())?;
+            match assign.client {
+                Some(client) => log::info!("Project {} is now billed to {client}", assign.project),
+                None => log::info!("Project {} is no longer billed to a client", assign.project),
             }
-            self.records.next();
+            return Ok(());
+        }
+        ProjectCommands::Rate(rate) => {
+            let from = rate.from.unwrap_or_else(|| Local::now().date_naive());
+            records::Projects::new(&mut conn).set_rate(&rate.name, rate.rate, from)?;
+            match records::Clients::new(&mut conn).rate_card()?.client(&rate.name) {
+                Some(client) => log::info!(
+                    "Set the hourly rate for {} to {} from {from}",
+                    rate.name,
+                    format_amount(rate.rate, &client.currency)
+                ),
+                None => log::warn!(
+                    "Project {} is not billed to a client yet, use \"tst project client\" to bill it",
+                    rate.name
+                ),
+            }
+            return Ok(());
+        }
+        ProjectCommands::Billable(name) => {
+            records::Projects::new(&mut conn).set_billable(&name.project, true)?;
+            log::info!("Marked project {} as billable", name.project);
+            return Ok(());
         }
+        ProjectCommands::NonBillable(name) => {
+            records::Projects::new(&mut conn).set_billable(&name.project, false)?;
+            log::info!("Marked project {} as not billable", name.project);
+            return Ok(());
+        }
+    };
+    let project = records::Projects::new(&mut conn).set_non_working(&name.project, non_working)?;
+    match project.non_working {
+        true => log::info!("Marked project {} as non-working time", project.name),
+        false => log::info!("Marked project {} as working time", project.name),
+    }
 
-        match today {
-            None => self.next(),
-            Some(day) => {
-                let hours_for_day = if matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
-                    0.0
-                } else {
-                    self.hours_for_day
-                };
+    Ok(())
+}
+
+fn list_projects(conn: &mut records::Conn, format: OutputFormat, list: ListProjects) -> Result<()> {
+    let projects = records::Projects::new(conn)
+        .list(Utc::now())?
+        .into_iter()
+        .filter(|usage| list.all || !usage.project.archived)
+        .collect_vec();
+
+    let mut stdout = std::io::stdout().lock();
+    if format != OutputFormat::Text {
+        return write_rows(
+            &mut stdout,
+            format,
+            &[],
+            projects.into_iter().map(ProjectRow::from),
+        );
+    }
 
-                let hours = self
-                    .seconds_day
-                    .drain()
-                    .map(|(_, value)| {
-                        round_to_next(value, self.rounding_minutes as i64 * 60) as f64
-                    })
-                    .sum::<f64>()
-                    / (60.0 * 60.0);
-
-                let difference = hours - hours_for_day;
-                self.hours_total += difference;
-
-                Some(Ok(OvertimeRecord {
-                    date: day,
-                    hours_day: hours,
-                    hours_difference: difference,
-                    hours_total: self.hours_total,
-                }))
+    // sub-projects can have long names, so size the column to fit them
+    let width = projects
+        .iter()
+        .map(|usage| usage.project.name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(16);
+    writeln!(stdout, "{:width$}      Total  Last used", "Project")?;
+    for usage in projects {
+        let last_used = match usage.last_used {
+            Some(last_used) => last_used
+                .with_timezone(&Local)
+                .format("%a %e %b '%y")
+                .to_string(),
+            None => "never".into(),
+        };
+        let mut flags = Vec::new();
+        if usage.project.non_working {
+            flags.push("non-working");
+        }
+        if usage.project.archived {
+            flags.push("archived");
+        }
+        if !usage.project.billable {
+            flags.push("non-billable");
+        }
+        let flags = match flags.is_empty() {
+            true => String::new(),
+            false => format!("  ({})", flags.join(", ")),
+        };
+        writeln!(
+            stdout,
+            "{:width$}  {:>9}  {last_used}{flags}",
+            usage.project.name,
+            duration_to_string(usage.total),
+        )?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn client(config: Config, format: OutputFormat, client: ClientArgs) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut clients = records::Clients::new(&mut conn);
+    let today = Local::now().date_naive();
+
+    match client.command {
+        ClientCommands::Add(add) => {
+            let client = clients.add(&add.name, &add.currency)?;
+            if let Some(rate) = add.rate {
+                clients.set_rate(&client.name, rate, today)?;
+            }
+            log::info!("Added client {} ({})", client.name, client.currency);
+        }
+        ClientCommands::Ls => {
+            let rate_card = clients.rate_card()?;
+            let rows = clients.list()?.into_iter().map(|client| ClientRow {
+                hourly_rate: rate_card.client_rate(&client.name, today),
+                name: client.name,
+                currency: client.currency,
+            });
+
+            let mut stdout = std::io::stdout().lock();
+            if format != OutputFormat::Text {
+                return write_rows(&mut stdout, format, &[], rows);
+            }
+            let rows = rows.collect_vec();
+            let width = rows
+                .iter()
+                .map(|row| row.name.chars().count())
+                .max()
+                .unwrap_or_default()
+                .max(16);
+            writeln!(stdout, "{:width$}  {:>14}", "Client", "Rate")?;
+            for row in rows {
+                let rate = match row.hourly_rate {
+                    Some(rate) => format_amount(rate, &row.currency),
+                    None => format!("- {}", row.currency),
+                };
+                writeln!(stdout, "{:width$}  {rate:>14}", row.name)?;
             }
         }
+        ClientCommands::Rate(rate) => {
+            let from = rate.from.unwrap_or(today);
+            clients.set_rate(&rate.name, rate.rate, from)?;
+            let currency = clients
+                .rate_card()?
+                .clients
+                .get(&rate.name)
+                .map(|client| client.currency.clone())
+                .unwrap_or_default();
+            log::info!(
+                "Set the default hourly rate for {} to {} from {from}",
+                rate.name,
+                format_amount(rate.rate, &currency)
+            );
+        }
     }
+
+    Ok(())
 }
 
-fn round_to_next(value: i64, unit: i64) -> i64 {
-    let remainder = value % unit;
-    if remainder == 0 {
-        value
-    } else {
-        value + unit - remainder
+pub(crate) fn report(config: Config, format: OutputFormat, report: ReportArgs) -> Result<()> {
+    match report.command {
+        ReportCommands::Billing(billing) => billing_report(config, format, billing),
+    }
+}
+
+fn billing_report(config: Config, format: OutputFormat, report: BillingReport) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+    let mut recs = records::Records::new(&mut conn);
+
+    let now = Utc::now();
+    let today = Local::now().naive_local().date();
+    let start = parse_relative_date(&report.since, &Local, today)
+        .ok_or(anyhow!("could not parse start time {}", &report.since))?;
+    let end = parse_relative_date(&report.until, &Local, today)
+        .ok_or(anyhow!("could not parse end time {}", &report.until))?;
+
+    let records = recs.list_records(start, end, &[])?;
+    let rate_card = recs.clients().rate_card()?;
+    for client in &report.clients {
+        if !rate_card.clients.contains_key(client) {
+            bail!("No client found with name {client}");
+        }
+    }
+
+    let mut billing = bill(records, &rate_card, now, &Local, &config.rounding);
+    if !report.clients.is_empty() {
+        billing
+            .lines
+            .retain(|line| report.clients.contains(&line.client));
+    }
+    for project in &billing.missing_rates {
+        log::warn!(
+            "No hourly rate found for project {project}, so some of its time was not billed \
+            (use \"tst project rate\" or \"tst client rate\" to set one)"
+        );
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    if format != OutputFormat::Text {
+        return write_rows(
+            &mut stdout,
+            format,
+            &[],
+            billing.lines.into_iter().map(BillingRow::from),
+        );
+    }
+
+    if billing.lines.is_empty() {
+        log::info!("No billable time found");
+        return Ok(());
+    }
+    let width = billing
+        .lines
+        .iter()
+        .map(|line| line.project.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(16);
+    for (index, total) in billing.client_totals().into_iter().enumerate() {
+        if index > 0 {
+            writeln!(stdout)?;
+        }
+        writeln!(stdout, "{} ({})", total.client, total.currency)?;
+        for line in billing
+            .lines
+            .iter()
+            .filter(|line| line.client == total.client)
+        {
+            writeln!(
+                stdout,
+                "  {:width$}  {:>8}  at {:>12}/h  {:>14}",
+                line.project,
+                format_hours(line.duration),
+                format_amount(line.hourly_rate, &line.currency),
+                format_amount(line.amount, &line.currency),
+            )?;
+        }
+        writeln!(
+            stdout,
+            "  {:width$}  {:>8}  {:>17}  {:>14}",
+            "Subtotal",
+            format_hours(total.duration),
+            "",
+            format_amount(total.amount, &total.currency),
+        )?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn invoice(config: Config, format: OutputFormat, invoice: InvoiceArgs) -> Result<()> {
+    let mut conn = records::establish_connection(&config.database_path)?;
+
+    match invoice.command {
+        Some(InvoiceCommands::Ls) => list_invoices(&mut conn, format),
+        Some(InvoiceCommands::Show(show)) => {
+            let invoice = records::Invoices::new(&mut conn).get(&show.number)?;
+            write_invoice(&invoice, &show.output)
+        }
+        Some(InvoiceCommands::Cancel(cancel)) => {
+            let released = records::Invoices::new(&mut conn).cancel(&cancel.number)?;
+            log::info!(
+                "Cancelled invoice {}, {released} record(s) can be changed again",
+                cancel.number
+            );
+            Ok(())
+        }
+        None => create_invoice(&config, &mut conn, invoice),
+    }
+}
+
+fn create_invoice(config: &Config, conn: &mut records::Conn, args: InvoiceArgs) -> Result<()> {
+    let client = args.client.ok_or(anyhow!("no client given"))?;
+    let now = Utc::now();
+    let today = Local::now().naive_local().date();
+    let start = parse_relative_date(&args.since, &Local, today)
+        .ok_or(anyhow!("could not parse start time {}", &args.since))?;
+    let end = parse_relative_date(&args.until, &Local, today)
+        .ok_or(anyhow!("could not parse end time {}", &args.until))?;
+
+    let mut recs = records::Records::new(conn);
+    let rate_card = recs.clients().rate_card()?;
+    if !rate_card.clients.contains_key(&client) {
+        bail!("No client found with name {client}");
+    }
+    let records = recs
+        .list_records(start, end, &[])?
+        .into_iter()
+        .filter(|record| {
+            !record.invoiced
+                && record.billable
+                && !record.non_working
+                && rate_card
+                    .client(&record.project)
+                    .is_some_and(|record_client| record_client.name == client)
+        })
+        .collect_vec();
+    if let Some(record) = records.iter().find(|record| record.ended_at.is_none()) {
+        bail!(
+            "record {} ({}) is still running, stop it before creating the invoice",
+            record.id,
+            record.task
+        );
+    }
+
+    let billing = bill_tasks(records.clone(), &rate_card, now, &Local, &config.rounding);
+    if !billing.missing_rates.is_empty() {
+        bail!(
+            "No hourly rate found for {}, use \"tst project rate\" or \"tst client rate\" to set one",
+            billing.missing_rates.iter().join(", ")
+        );
+    }
+    let lines = billing
+        .lines
+        .into_iter()
+        .map(|line| records::InvoiceLine {
+            project: line.project,
+            task: line.task.unwrap_or_default(),
+            hourly_rate: line.hourly_rate,
+            duration: line.duration,
+            amount: line.amount,
+        })
+        .collect_vec();
+    // the end of the range is exclusive, so the period ends the day before
+    let period = (
+        start.with_timezone(&Local).date_naive(),
+        (end - Duration::seconds(1))
+            .with_timezone(&Local)
+            .date_naive(),
+    );
+
+    if args.dry_run {
+        if lines.is_empty() {
+            bail!("Nothing to invoice for {client}");
+        }
+        let invoice = records::Invoice {
+            number: "DRAFT".into(),
+            currency: rate_card.clients[&client].currency.clone(),
+            client,
+            issued_on: today,
+            period_start: period.0,
+            period_end: period.1,
+            lines,
+        };
+        return write_invoice(&invoice, &args.output);
+    }
+
+    let record_ids = records.into_iter().map(|record| record.id).collect_vec();
+    let invoice =
+        records::Invoices::new(conn).create(&client, today, period, lines, &record_ids)?;
+    write_invoice(&invoice, &args.output)?;
+    log::info!(
+        "Created invoice {} for {client}, and marked {} record(s) as invoiced",
+        invoice.number,
+        record_ids.len()
+    );
+
+    Ok(())
+}
+
+fn write_invoice(invoice: &records::Invoice, output: &InvoiceOutput) -> Result<()> {
+    match &output.output {
+        Some(path) => {
+            let mut file = std::io::BufWriter::new(
+                std::fs::File::create(path)
+                    .with_context(|| format!("could not create invoice file {path:?}"))?,
+            );
+            render_invoice(&mut file, invoice, output.template)?;
+            file.flush()?;
+            log::info!("Wrote invoice {} to {path:?}", invoice.number);
+            Ok(())
+        }
+        None => render_invoice(&mut std::io::stdout().lock(), invoice, output.template),
+    }
+}
+
+fn list_invoices(conn: &mut records::Conn, format: OutputFormat) -> Result<()> {
+    let invoices = records::Invoices::new(conn).list()?;
+
+    let mut stdout = std::io::stdout().lock();
+    if format != OutputFormat::Text {
+        return write_rows(
+            &mut stdout,
+            format,
+            &[],
+            invoices.into_iter().map(InvoiceRow::from),
+        );
+    }
+
+    let width = invoices
+        .iter()
+        .map(|invoice| invoice.client.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(16);
+    writeln!(
+        stdout,
+        "Number     Issued      {:width$}  {:24}  {:>14}",
+        "Client", "Period", "Total"
+    )?;
+    for invoice in invoices {
+        writeln!(
+            stdout,
+            "{:9}  {}  {:width$}  {} to {}  {:>14}",
+            invoice.number,
+            invoice.issued_on,
+            invoice.client,
+            invoice.period_start,
+            invoice.period_end,
+            format_amount(invoice.total(), &invoice.currency),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Logs a warning if the project exists but has been archived.
+fn warn_if_archived(recs: &mut records::Records, project: &str) -> Result<()> {
+    if recs.projects().get(project)?.is_some_and(|p| p.archived) {
+        log::warn!(
+            "Project {project} is archived, use `tst project unarchive {project}` to restore it"
+        );
+    }
+    Ok(())
+}
+
+/// Fails if the project doesn't exist yet and new projects aren't allowed,
+/// suggesting existing projects with similar names.
+fn check_new_project(recs: &mut records::Records, project: &str, allow_new: bool) -> Result<()> {
+    if allow_new || recs.projects().get(project)?.is_some() {
+        return Ok(());
+    }
+
+    let similar = recs.projects().similar_names(project)?;
+    match similar.is_empty() {
+        true => bail!("project {project} does not exist yet, use --new-project to create it"),
+        false => bail!(
+            "project {project} does not exist yet (did you mean {}?), \
+            use --new-project to create it",
+            similar.join(" or ")
+        ),
     }
 }
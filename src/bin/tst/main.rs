@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use std::process::ExitCode;
+
 use anyhow::Result;
 use clap::Parser;
 use dotenvy::dotenv;
@@ -10,7 +12,7 @@ use timesheettool::{
 
 mod commands;
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     dotenv().ok();
     let args = Arguments::parse();
 
@@ -23,12 +25,27 @@ fn main() -> Result<()> {
 
     match args.command {
         Commands::Go(go) => commands::go(config, go)?,
+        Commands::Resume(resume) => commands::resume(config, resume)?,
         Commands::Stop(stop) => commands::stop(config, stop)?,
-        Commands::Ls(list_records) => commands::ls(config, list_records)?,
+        Commands::Pause(pause) => commands::pause(config, pause)?,
+        Commands::Unpause(unpause) => commands::unpause(config, unpause)?,
+        Commands::Break(pause) => commands::add_break(config, pause)?,
+        Commands::Status => return commands::status(config, args.format),
+        Commands::Ls(list_records) => commands::ls(config, args.format, list_records)?,
         Commands::Edit(edit) => commands::edit(config, edit)?,
         Commands::Delete(delete) => commands::delete(config, delete)?,
-        Commands::Overtime(overtime) => commands::overtime(config, overtime)?,
-        Commands::Times(times) => commands::times(config, times)?,
+        Commands::Undo(undo) => commands::undo(config, undo)?,
+        Commands::Redo(redo) => commands::redo(config, redo)?,
+        Commands::Absence(absence) => commands::absence(config, args.format, absence)?,
+        Commands::Holidays(holidays) => commands::holidays(config, holidays)?,
+        Commands::Project(project) => commands::project(config, args.format, project)?,
+        Commands::Client(client) => commands::client(config, args.format, client)?,
+        Commands::Overtime(overtime) => commands::overtime(config, args.format, overtime)?,
+        Commands::Times(times) => commands::times(config, args.format, times)?,
+        Commands::Export(export) => commands::export(config, args.format, export)?,
+        Commands::Check(check) => return commands::check(config, args.format, check),
+        Commands::Report(report) => commands::report(config, args.format, report)?,
+        Commands::Invoice(invoice) => commands::invoice(config, args.format, invoice)?,
     }
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,414 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::Serialize;
+
+use crate::records::{Break, Record};
+
+/// A minimum break that must be taken once a certain time has been worked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakRule {
+    pub after: Duration,
+    pub minimum: Duration,
+}
+
+/// The rules for breaks and working times that a working day is checked
+/// against.  The default follows the German Arbeitszeitgesetz (ArbZG).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakPolicy {
+    pub rules: Vec<BreakRule>,
+    /// Pauses shorter than this are counted as working time
+    pub min_break: Duration,
+    /// The start of the day is rounded down and the end of the day rounded up
+    /// to a multiple of this duration.  Zero disables rounding.
+    pub rounding: Duration,
+    pub max_daily: Duration,
+    /// The minimum time between the end of one working day and the start of
+    /// the next.
+    pub min_rest: Duration,
+}
+
+impl Default for BreakPolicy {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                BreakRule {
+                    after: Duration::hours(6),
+                    minimum: Duration::minutes(30),
+                },
+                BreakRule {
+                    after: Duration::hours(9),
+                    minimum: Duration::minutes(45),
+                },
+            ],
+            min_break: Duration::minutes(15),
+            rounding: Duration::minutes(15),
+            max_daily: Duration::hours(10),
+            min_rest: Duration::hours(11),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ViolationKind {
+    InsufficientBreak,
+    MaxDailyExceeded,
+    InsufficientRest,
+}
+
+impl ViolationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ViolationKind::InsufficientBreak => "insufficient-break",
+            ViolationKind::MaxDailyExceeded => "max-daily-exceeded",
+            ViolationKind::InsufficientRest => "insufficient-rest",
+        }
+    }
+}
+
+/// A breach of the break policy, along with the actual duration (e.g. the
+/// break that was taken) and the limit that it was checked against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub actual: Duration,
+    pub limit: Duration,
+}
+
+/// A single working day, after applying the break policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkDay {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub worked: Duration,
+    /// The time deducted for breaks.  If too few breaks were taken, the
+    /// required break is deducted instead.
+    pub breaks: Duration,
+    pub violations: Vec<Violation>,
+}
+
+/// Why the time between two working records wasn't worked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapKind {
+    /// A break that was taken intentionally, with `tst pause` or `tst break`
+    Break,
+    /// Time spent on a project that isn't working time
+    NonWorking,
+    /// Time that wasn't tracked at all
+    Untracked,
+}
+
+/// A period between two working records on the same day, with the start and
+/// end rounded to five minutes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gap {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub kind: GapKind,
+}
+
+/// Finds the gaps between the working records of a single day, which must be
+/// sorted by their start time.  Time spent on non-working records and the
+/// given breaks is accounted for, so only the rest of each gap can be
+/// untracked.  Returns the end of the last working record (if it has ended)
+/// along with the gaps.
+pub fn find_gaps(records: &[Record], breaks: &[Break]) -> (Option<DateTime<Utc>>, Vec<Gap>) {
+    let mut accounted = records
+        .iter()
+        .filter(|record| record.non_working)
+        .map(|record| Gap {
+            started_at: round_gap(record.started_at),
+            ended_at: record.ended_at.map_or(DateTime::<Utc>::MAX_UTC, round_gap),
+            kind: GapKind::NonWorking,
+        })
+        .chain(breaks.iter().map(|pause| Gap {
+            started_at: round_gap(pause.started_at),
+            ended_at: round_gap(pause.ended_at),
+            kind: GapKind::Break,
+        }))
+        .collect::<Vec<_>>();
+    accounted.sort_by_key(|gap| gap.started_at);
+
+    let mut previous: Option<(DateTime<Utc>, bool)> = None;
+    let mut gaps = Vec::new();
+    for record in records.iter().filter(|record| !record.non_working) {
+        if let Some((end, paused)) = previous {
+            if record.started_at - end > Duration::seconds(60) {
+                let kind = match paused {
+                    true => GapKind::Break,
+                    false => GapKind::Untracked,
+                };
+                split_gap(
+                    &mut gaps,
+                    round_gap(end),
+                    round_gap(record.started_at),
+                    &accounted,
+                    kind,
+                );
+            }
+        }
+        previous = record.ended_at.map(|end| (end, record.paused));
+    }
+
+    (previous.map(|(end, _)| end), gaps)
+}
+
+fn round_gap(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.duration_round(Duration::minutes(5)).unwrap_or(time)
+}
+
+/// Adds the gap between the two times, split up into the parts covered by
+/// the accounted periods (which must be rounded and sorted by their start)
+/// and the rest, which is of the given kind.
+fn split_gap(
+    gaps: &mut Vec<Gap>,
+    started_at: DateTime<Utc>,
+    ended_at: DateTime<Utc>,
+    accounted: &[Gap],
+    kind: GapKind,
+) {
+    let mut push = |started_at: DateTime<Utc>, ended_at: DateTime<Utc>, kind: GapKind| {
+        if ended_at <= started_at {
+            return;
+        }
+        match gaps.last_mut() {
+            Some(last) if last.kind == kind && last.ended_at == started_at => {
+                last.ended_at = ended_at;
+            }
+            _ => gaps.push(Gap {
+                started_at,
+                ended_at,
+                kind,
+            }),
+        }
+    };
+
+    let mut cursor = started_at;
+    for period in accounted {
+        let from = period.started_at.max(cursor);
+        let until = period.ended_at.min(ended_at);
+        if until <= from {
+            continue;
+        }
+        push(cursor, from, kind);
+        push(from, until, period.kind);
+        cursor = until;
+    }
+    push(cursor, ended_at, kind);
+}
+
+impl BreakPolicy {
+    /// Returns the total break required after working for the given time.
+    pub fn required_break(&self, worked: Duration) -> Duration {
+        self.rules
+            .iter()
+            .filter(|rule| worked > rule.after)
+            .map(|rule| rule.minimum)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Applies the policy to a single working day.  The pauses are the gaps
+    /// between records during the day, and `previous_end` is the end of the
+    /// last working day, used for checking the rest period.
+    pub fn evaluate(
+        &self,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        pauses: &[(DateTime<Utc>, DateTime<Utc>)],
+        previous_end: Option<DateTime<Utc>>,
+    ) -> WorkDay {
+        let mut violations = Vec::new();
+        if let Some(rest) = previous_end.map(|previous_end| started_at - previous_end) {
+            if rest < self.min_rest {
+                violations.push(Violation {
+                    kind: ViolationKind::InsufficientRest,
+                    actual: rest,
+                    limit: self.min_rest,
+                });
+            }
+        }
+
+        let started_at = self.round_down(started_at);
+        let ended_at = self.round_up(ended_at);
+        let taken = pauses
+            .iter()
+            .map(|(start, end)| *end - *start)
+            .filter(|pause| *pause >= self.min_break)
+            .sum::<Duration>();
+        let required = self.required_break(ended_at - started_at - taken);
+        if taken < required {
+            violations.push(Violation {
+                kind: ViolationKind::InsufficientBreak,
+                actual: taken,
+                limit: required,
+            });
+        }
+
+        let breaks = taken.max(required);
+        let worked = ended_at - started_at - breaks;
+        if worked > self.max_daily {
+            violations.push(Violation {
+                kind: ViolationKind::MaxDailyExceeded,
+                actual: worked,
+                limit: self.max_daily,
+            });
+        }
+
+        WorkDay {
+            started_at,
+            ended_at,
+            worked,
+            breaks,
+            violations,
+        }
+    }
+
+    fn round_down(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        if self.rounding <= Duration::zero() {
+            return time;
+        }
+        time.duration_trunc(self.rounding).unwrap_or(time)
+    }
+
+    fn round_up(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        match self.round_down(time) {
+            rounded if rounded == time => time,
+            rounded => rounded + self.rounding,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(time: &str) -> DateTime<Utc> {
+        format!("2024-05-13T{time}Z").parse().unwrap()
+    }
+
+    fn record(project: &str, started_at: &str, ended_at: &str) -> Record {
+        Record {
+            id: "hello".into(),
+            task: "blub".into(),
+            project: project.into(),
+            non_working: project == "commute",
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: dt(started_at),
+            ended_at: Some(dt(ended_at)),
+            tags: Vec::new(),
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn non_working_records_are_not_untracked_gaps() {
+        let records = [
+            record("commute", "07:30:00", "08:00:00"),
+            record("work", "08:00:00", "12:00:00"),
+            record("commute", "12:10:00", "12:40:00"),
+            record("work", "13:00:00", "17:00:00"),
+        ];
+        let (end, gaps) = find_gaps(&records, &[]);
+        assert_eq!(end, Some(dt("17:00:00")));
+        assert_eq!(
+            gaps,
+            [
+                Gap {
+                    started_at: dt("12:00:00"),
+                    ended_at: dt("12:10:00"),
+                    kind: GapKind::Untracked,
+                },
+                Gap {
+                    started_at: dt("12:10:00"),
+                    ended_at: dt("12:40:00"),
+                    kind: GapKind::NonWorking,
+                },
+                Gap {
+                    started_at: dt("12:40:00"),
+                    ended_at: dt("13:00:00"),
+                    kind: GapKind::Untracked,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn required_break_uses_the_highest_threshold_exceeded() {
+        let policy = BreakPolicy::default();
+        assert_eq!(policy.required_break(Duration::hours(6)), Duration::zero());
+        assert_eq!(
+            policy.required_break(Duration::minutes(6 * 60 + 1)),
+            Duration::minutes(30)
+        );
+        assert_eq!(
+            policy.required_break(Duration::hours(10)),
+            Duration::minutes(45)
+        );
+    }
+
+    #[test]
+    fn short_pauses_do_not_count_as_breaks() {
+        let policy = BreakPolicy::default();
+        let day = policy.evaluate(
+            dt("08:05:00"),
+            dt("15:50:00"),
+            &[(dt("12:00:00"), dt("12:10:00"))],
+            None,
+        );
+        assert_eq!(day.started_at, dt("08:00:00"));
+        assert_eq!(day.ended_at, dt("16:00:00"));
+        assert_eq!(day.breaks, Duration::minutes(30));
+        assert_eq!(day.worked, Duration::minutes(7 * 60 + 30));
+        assert_eq!(
+            day.violations,
+            vec![Violation {
+                kind: ViolationKind::InsufficientBreak,
+                actual: Duration::zero(),
+                limit: Duration::minutes(30),
+            }]
+        );
+    }
+
+    #[test]
+    fn long_days_and_short_rests_are_violations() {
+        let policy = BreakPolicy::default();
+        let day = policy.evaluate(
+            dt("07:00:00"),
+            dt("18:45:00"),
+            &[(dt("12:00:00"), dt("12:45:00"))],
+            Some("2024-05-12T21:00:00Z".parse().unwrap()),
+        );
+        assert_eq!(day.worked, Duration::hours(11));
+        assert_eq!(
+            day.violations,
+            vec![
+                Violation {
+                    kind: ViolationKind::InsufficientRest,
+                    actual: Duration::hours(10),
+                    limit: Duration::hours(11),
+                },
+                Violation {
+                    kind: ViolationKind::MaxDailyExceeded,
+                    actual: Duration::hours(11),
+                    limit: Duration::hours(10),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_rounding_keeps_exact_times() {
+        let policy = BreakPolicy {
+            rounding: Duration::zero(),
+            ..BreakPolicy::default()
+        };
+        let day = policy.evaluate(dt("08:05:00"), dt("12:10:00"), &[], None);
+        assert_eq!(day.started_at, dt("08:05:00"));
+        assert_eq!(day.worked, Duration::minutes(4 * 60 + 5));
+        assert!(day.violations.is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fmt::Display;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    commands::FixStrategy,
+    records::{Record, Records},
+};
+
+/// A record (or pair of records) that breaks the assumptions made when
+/// calculating durations, e.g. by being counted twice.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The earlier record ends after the later record has started
+    Overlap { earlier: Record, later: Record },
+    /// The earlier record has no end, but another record was started later
+    StillOpen { earlier: Record, later: Record },
+    /// The record ends at or before the time it started
+    ZeroLength(Record),
+    /// The record starts or ends after the current time
+    InFuture(Record),
+}
+
+impl Problem {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Problem::Overlap { .. } => "overlap",
+            Problem::StillOpen { .. } => "still-open",
+            Problem::ZeroLength(_) => "zero-length",
+            Problem::InFuture(_) => "in-future",
+        }
+    }
+
+    pub fn record(&self) -> &Record {
+        match self {
+            Problem::Overlap { earlier, .. } | Problem::StillOpen { earlier, .. } => earlier,
+            Problem::ZeroLength(record) | Problem::InFuture(record) => record,
+        }
+    }
+
+    pub fn other_record(&self) -> Option<&Record> {
+        match self {
+            Problem::Overlap { later, .. } | Problem::StillOpen { later, .. } => Some(later),
+            Problem::ZeroLength(_) | Problem::InFuture(_) => None,
+        }
+    }
+
+    /// Whether `fix` is able to repair this problem.  Records in the future
+    /// might be intentional, so they are only reported.
+    pub fn is_fixable(&self) -> bool {
+        !matches!(self, Problem::InFuture(_))
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Overlap { earlier, later } => write!(
+                f,
+                "record {} ({}) overlaps with record {} ({})",
+                earlier.id, earlier.task, later.id, later.task
+            ),
+            Problem::StillOpen { earlier, later } => write!(
+                f,
+                "record {} ({}) is still open, but record {} ({}) started after it",
+                earlier.id, earlier.task, later.id, later.task
+            ),
+            Problem::ZeroLength(record) => write!(
+                f,
+                "record {} ({}) does not end after it starts",
+                record.id, record.task
+            ),
+            Problem::InFuture(record) => {
+                write!(f, "record {} ({}) is in the future", record.id, record.task)
+            }
+        }
+    }
+}
+
+/// Finds problems in the given records, which must be sorted by start time.
+/// Each record is only checked for overlaps against the earlier record that
+/// ends last, so if many records overlap, then fixing the reported problems
+/// may reveal more.
+pub fn find_problems(
+    records: impl IntoIterator<Item = Record>,
+    now: DateTime<Utc>,
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut latest: Option<Record> = None;
+    for record in records {
+        if record.started_at > now || record.ended_at.is_some_and(|end| end > now) {
+            problems.push(Problem::InFuture(record.clone()));
+        }
+        if record.ended_at.is_some_and(|end| end <= record.started_at) {
+            problems.push(Problem::ZeroLength(record));
+            continue;
+        }
+
+        let Some(earlier) = latest.take() else {
+            latest = Some(record);
+            continue;
+        };
+        let (overlaps, ends_later) = match (earlier.ended_at, record.ended_at) {
+            // if both are open, the earlier record will be ended when the
+            // later one starts, so only the later one needs checking further
+            (None, None) => (true, false),
+            (None, Some(_)) => (true, true),
+            (Some(earlier_end), None) => (earlier_end > record.started_at, false),
+            (Some(earlier_end), Some(end)) => (earlier_end > record.started_at, earlier_end > end),
+        };
+        if overlaps {
+            problems.push(match earlier.ended_at {
+                None => Problem::StillOpen {
+                    earlier: earlier.clone(),
+                    later: record.clone(),
+                },
+                Some(_) => Problem::Overlap {
+                    earlier: earlier.clone(),
+                    later: record.clone(),
+                },
+            });
+        }
+        latest = Some(if ends_later { earlier } else { record });
+    }
+
+    problems
+}
+
+/// Repairs a single problem.  Overlapping records are fixed by ending the
+/// earlier record when the later record starts.  With the split strategy, if
+/// the earlier record continued after the later record ended, it is also
+/// started again at that point.  Zero-length records are deleted.
+///
+/// Returns whether anything was changed.
+pub fn fix(records: &mut Records, problem: &Problem, strategy: FixStrategy) -> Result<bool> {
+    match problem {
+        Problem::Overlap { earlier, later } | Problem::StillOpen { earlier, later } => {
+            let restart = match strategy {
+                FixStrategy::Truncate => None,
+                FixStrategy::Split => later.ended_at,
+            };
+            let updated = records.split_record(&earlier.id, later.started_at, restart)?;
+            Ok(!updated.is_empty())
+        }
+        Problem::ZeroLength(record) => {
+            records.delete_record(&record.id)?;
+            Ok(true)
+        }
+        Problem::InFuture(_) => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::establish_connection;
+
+    fn dt(time: &str) -> DateTime<Utc> {
+        format!("2024-05-12T{time}Z").parse().unwrap()
+    }
+
+    fn record(id: &str, start: &str, end: Option<&str>) -> Record {
+        Record {
+            id: id.into(),
+            task: "task".into(),
+            project: "proj".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: dt(start),
+            ended_at: end.map(dt),
+            tags: Vec::new(),
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn finds_overlapping_open_empty_and_future_records() {
+        let records = vec![
+            record("aaaaa", "08:00:00", Some("10:00:00")),
+            record("bbbbb", "09:00:00", Some("09:30:00")),
+            record("ccccc", "11:00:00", None),
+            record("ddddd", "11:30:00", Some("11:30:00")),
+            record("eeeee", "12:00:00", None),
+            record("fffff", "20:00:00", Some("21:00:00")),
+        ];
+        let problems = find_problems(records.clone(), dt("18:00:00"));
+        assert_eq!(
+            problems,
+            vec![
+                Problem::Overlap {
+                    earlier: records[0].clone(),
+                    later: records[1].clone(),
+                },
+                Problem::ZeroLength(records[3].clone()),
+                Problem::StillOpen {
+                    earlier: records[2].clone(),
+                    later: records[4].clone(),
+                },
+                Problem::InFuture(records[5].clone()),
+                Problem::StillOpen {
+                    earlier: records[4].clone(),
+                    later: records[5].clone(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn fixing_overlaps_can_split_the_earlier_record() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("long", "proj", dt("08:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+        records
+            .add_record("short", "proj", dt("09:00:00"), Some(dt("10:00:00")))
+            .unwrap();
+
+        let listed = records.all_records().unwrap().collect::<Result<Vec<_>>>();
+        let problems = find_problems(listed.unwrap(), dt("23:00:00"));
+        assert_eq!(problems.len(), 1);
+        assert!(fix(&mut records, &problems[0], FixStrategy::Split).unwrap());
+
+        let listed = records
+            .all_records()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let times = listed
+            .iter()
+            .map(|record| (record.task.as_str(), record.started_at, record.ended_at))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            times,
+            vec![
+                ("long", dt("08:00:00"), Some(dt("09:00:00"))),
+                ("short", dt("09:00:00"), Some(dt("10:00:00"))),
+                ("long", dt("10:00:00"), Some(dt("12:00:00"))),
+            ]
+        );
+        assert!(find_problems(listed, dt("23:00:00")).is_empty());
+    }
+}
@@ -4,6 +4,14 @@ use std::path::PathBuf;
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
 
+use chrono::NaiveDate;
+
+use crate::{
+    parse::{parse_amount, parse_signed_duration},
+    records::AbsenceKind,
+    rounding::{RoundingMode, RoundingScope},
+};
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Arguments {
@@ -29,6 +37,21 @@ pub struct Arguments {
     #[arg(global = true, long, help_heading = "Global Options")]
     pub config_file: Option<PathBuf>,
 
+    /// set output format
+    ///
+    /// "text" prints human-readable tables.  "json" prints a single JSON
+    /// array, and "ndjson" prints one JSON object per line, which is useful
+    /// for streaming into other tools.  "csv" and "tsv" print spreadsheet-style
+    /// tables with a header row.  Timestamps are formatted as RFC 3339
+    /// strings, and durations are given in seconds.
+    #[arg(
+        global = true,
+        long,
+        default_value = "text",
+        help_heading = "Global Options"
+    )]
+    pub format: OutputFormat,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -51,13 +74,55 @@ pub enum Commands {
     #[clap(aliases = &["start", "record"])]
     Go(Go),
 
+    /// Restart a previous task
+    ///
+    /// Creates a new record with the same project, task, and tags as the most
+    /// recent record, or as the record with the given ID.  Like "go", any
+    /// open record that would overlap with the new record will be ended.
+    ///
+    /// Aliases: continue
+    #[clap(aliases = &["continue"])]
+    Resume(Resume),
+
     /// Stop the current record
     ///
     /// If any task is open at the given time, stop that task.  By default,
     /// the time used is the current time, but this can optionally be set
-    /// using a flag.
+    /// using a flag.  Any notes given are appended to the record's existing
+    /// notes.
     Stop(Stop),
 
+    /// Stop the current record for a break
+    ///
+    /// Like "stop", but the time until the next record is started is marked
+    /// as a break.  Use "unpause" to end the break and continue with the same
+    /// project, task, and tags.
+    Pause(Pause),
+
+    /// End a break started with "pause"
+    ///
+    /// Starts a new record with the same project, task, and tags as the
+    /// record that was paused.
+    Unpause(Unpause),
+
+    /// Add a break that has already happened
+    ///
+    /// The record that was running at the start of the break is stopped at
+    /// the start time, and continued again at the end time.  If the previous
+    /// record ended when the break started, only the time between the two
+    /// dates is marked as a break, and the rest of the gap stays untracked.
+    /// Otherwise, nothing was running and the break is refused, as the gap
+    /// before it wasn't tracked.
+    Break(Break),
+
+    /// Show the currently running record
+    ///
+    /// Shows the project and task of the record that is currently running,
+    /// how long it has been running, and the total time worked today.  Exits
+    /// with a non-zero status if no record is running, so that this can be
+    /// used in scripts and shell prompts.
+    Status,
+
     /// List all records
     ///
     /// By default, shows all records for the last week.  This can be changed
@@ -79,11 +144,70 @@ pub enum Commands {
     /// assumptions made by timesheettool, so use this with care.
     Delete(Delete),
 
+    /// Undo the most recent changes
+    ///
+    /// Reverts the changes made by the most recent go, resume, stop, pause,
+    /// edit, or delete commands.  By default, only the last change is undone,
+    /// but more changes can be undone at once by passing a count.
+    Undo(Undo),
+
+    /// Redo changes that were undone
+    ///
+    /// Reapplies changes that were reverted using the undo command.  Once a
+    /// new change has been made, the undone changes can no longer be redone.
+    Redo(Redo),
+
+    /// Manage holidays, vacation, and sick days
+    ///
+    /// Absences reduce the hours that are expected to be worked on that day
+    /// when calculating overtime.  The exception is comp-time, which is time
+    /// taken off using existing overtime, so the usual hours still apply.
+    Absence(AbsenceArgs),
+
+    /// Manage public holidays
+    Holidays(HolidaysArgs),
+
+    /// Manage projects
+    Project(ProjectArgs),
+
+    /// Manage clients and their hourly rates
+    ///
+    /// Projects are billed to a client using "project client", and are billed
+    /// at the client's rate unless the project has its own rate.
+    Client(ClientArgs),
+
     /// View overtime statistics
     Overtime(Overtime),
 
     /// View day start/end times and breaks
     Times(Times),
+
+    /// Export records for use in spreadsheets
+    ///
+    /// Writes one row per record, or one row per project and period if a
+    /// granularity is given.  The output is CSV by default, use the --format
+    /// flag to choose between "csv" and "tsv" (or one of the JSON formats).
+    Export(Export),
+
+    /// Check records for overlaps and other problems
+    ///
+    /// Finds records that overlap each other, records that are still running
+    /// even though a later record was started, records that end before they
+    /// start, and records in the future.  Exits with an error if any problems
+    /// remain, so it can be used in scripts.
+    Check(Check),
+
+    /// Show reports for a range of records
+    Report(ReportArgs),
+
+    /// Create an invoice for a client
+    ///
+    /// Bills all of the client's billable records in the given time range
+    /// that haven't been invoiced yet, with a line for each task in each
+    /// project.  The records are marked as invoiced, so that they can't be
+    /// billed twice, and can no longer be changed unless the invoice is
+    /// cancelled.
+    Invoice(InvoiceArgs),
 }
 
 #[derive(Args, Debug)]
@@ -91,7 +215,8 @@ pub struct Go {
     /// project
     ///
     /// Provides the project name that this record should be logged under.
-    /// If the project doesn't exist, it will be created automatically.
+    /// New projects are only created if --new-project is given (or if
+    /// auto_create_projects is set in the config file).
     pub project: String,
 
     /// task name
@@ -99,6 +224,49 @@ pub struct Go {
     /// Provides the task name that this record should be logged under.
     pub name: String,
 
+    /// tags
+    ///
+    /// Tags to attach to this record, each prefixed with a "+" (e.g. "+meeting").
+    /// If a tag doesn't exist yet, it will be created automatically.
+    #[arg(value_parser = parse_prefixed_tag)]
+    pub tags: Vec<String>,
+
+    /// record start time
+    ///
+    /// Defaults to the current time.  Can be specified as a ISO-8601-style
+    /// string, or as a relative string.  (See documentation for the exact
+    /// format of this string.)
+    #[arg(short = 's', long)]
+    pub start: Option<String>,
+
+    /// record end time
+    ///
+    /// Defaults to no end time if not set (i.e. the task is marked as still in progress).
+    /// Can be specified as a ISO-8601-style string, or as a relative string.  (See
+    /// documentation for the exact format of this string.)
+    #[arg(short = 'e', long)]
+    pub end: Option<String>,
+
+    /// allow this record to overlap other records in the database
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub allow_overlap: bool,
+
+    /// create the project if it doesn't exist yet
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub new_project: bool,
+
+    #[command(flatten)]
+    pub note: NoteArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct Resume {
+    /// record id
+    ///
+    /// The five-letter ID of the record to resume.  Defaults to the most
+    /// recent record.
+    pub record_id: Option<String>,
+
     /// record start time
     ///
     /// Defaults to the current time.  Can be specified as a ISO-8601-style
@@ -129,6 +297,60 @@ pub struct Stop {
     /// format of this string.)
     #[arg(short = 'e', long)]
     pub end: Option<String>,
+
+    #[command(flatten)]
+    pub note: NoteArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct Pause {
+    /// break start time
+    ///
+    /// Defaults to the current time.  Can be specified as a ISO-8601-style
+    /// string, or as a relative string.  (See documentation for the exact
+    /// format of this string.)
+    #[arg(short = 's', long)]
+    pub start: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct Unpause {
+    /// break end time
+    ///
+    /// Defaults to the current time.  Can be specified as a ISO-8601-style
+    /// string, or as a relative string.  (See documentation for the exact
+    /// format of this string.)
+    #[arg(short = 'e', long)]
+    pub end: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct Break {
+    /// break start time
+    ///
+    /// Can be specified as a ISO-8601-style string, or as a relative string.
+    /// (See documentation for the exact format of this string.)
+    pub start: String,
+
+    /// break end time
+    pub end: String,
+}
+
+#[derive(Args, Debug)]
+pub struct NoteArgs {
+    /// notes for the record
+    ///
+    /// Free-form notes describing what was done during this record.  When
+    /// editing a record, an empty string removes the existing notes.
+    #[arg(short = 'n', long, conflicts_with = "edit_note")]
+    pub note: Option<String>,
+
+    /// write notes for the record using $EDITOR
+    ///
+    /// Opens the editor given by the $VISUAL or $EDITOR environment
+    /// variables, pre-filled with the record's existing notes.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub edit_note: bool,
 }
 
 #[derive(Args, Debug)]
@@ -163,11 +385,49 @@ pub struct ListRecords {
 
     /// rounding unit
     ///
-    /// Rounds hours worked in a project per day to the next largest multiple
-    /// of this value.  Useful for having a concept of minimum billable time
-    /// units.  Rounding occurs per project, and per day.
-    #[arg(short = 'r', long, default_value = "30m")]
-    pub rounding: String,
+    /// Rounds hours worked to a multiple of this duration (e.g. "30m" or "1h").
+    /// Useful for having a concept of minimum billable time units.  Defaults
+    /// to the time_round_minutes value in the config file.
+    #[arg(short = 'r', long)]
+    pub rounding: Option<String>,
+
+    /// rounding direction
+    ///
+    /// Whether durations should be rounded up, down, to the nearest multiple
+    /// of the rounding unit, or not at all.  Defaults to the rounding_mode
+    /// value in the config file, or "up" if that is not set.
+    #[arg(long)]
+    pub rounding_mode: Option<RoundingMode>,
+
+    /// what to round
+    ///
+    /// Whether to round each individual record, the total time spent on each
+    /// project per day, or the total time worked per day.  Defaults to the
+    /// rounding_scope value in the config file, or "project-day" if that is
+    /// not set.
+    #[arg(long)]
+    pub rounding_scope: Option<RoundingScope>,
+
+    /// only show records with this tag
+    ///
+    /// Can be used multiple times, in which case only records that have all
+    /// of the given tags will be shown.
+    #[arg(long = "tag", value_parser = parse_tag)]
+    pub tags: Vec<String>,
+
+    /// only show records in this project or its sub-projects
+    ///
+    /// Can be used multiple times, in which case records in any of the given
+    /// projects will be shown.
+    #[arg(short = 'p', long = "project")]
+    pub projects: Vec<String>,
+
+    /// show notes
+    ///
+    /// Shows each record's notes underneath the record.  Only used when
+    /// showing individual records (i.e. with the "all" granularity).
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub notes: bool,
 }
 
 #[derive(ValueEnum, Debug, Clone, PartialEq, Eq)]
@@ -177,13 +437,30 @@ pub enum Granularity {
     /// show all records
     All,
     /// show time spent on tasks per day
+    #[value(alias = "day")]
     Daily,
     /// Show time spent on tasks per week
+    #[value(alias = "week")]
     Weekly,
     /// Show time spent on tasks per month
+    #[value(alias = "month")]
     Monthly,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// human-readable tables
+    Text,
+    /// a single JSON array
+    Json,
+    /// newline-delimited JSON, one object per line
+    Ndjson,
+    /// comma-separated values
+    Csv,
+    /// tab-separated values
+    Tsv,
+}
+
 #[derive(Args, Debug)]
 pub struct Edit {
     /// record id
@@ -199,12 +476,84 @@ pub struct Edit {
     #[arg(short = 'e', long)]
     pub end: Option<String>,
 
+    /// remove the record's end time
+    ///
+    /// Marks the record as still in progress, as if it had never been stopped.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "end")]
+    pub reopen: bool,
+
+    /// trim neighbouring records instead of refusing to overlap them
+    ///
+    /// By default, changing the start or end time of a record so that it
+    /// overlaps other records fails.  With this flag, earlier records are
+    /// ended when this record starts, and later records are started when this
+    /// record ends.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub shift_neighbours: bool,
+
     /// task name
     ///
     /// Provides the task name that this record should be logged under.  If
     /// the tag name doesn't exist yet in the database, it will be created.
     #[arg(short = 't', long)]
     pub task: Option<String>,
+
+    /// project
+    ///
+    /// Moves the record to the given project.  New projects are only created
+    /// if --new-project is given (or if auto_create_projects is set in the
+    /// config file).
+    #[arg(short = 'p', long)]
+    pub project: Option<String>,
+
+    /// create the project if it doesn't exist yet
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "project")]
+    pub new_project: bool,
+
+    /// whether the record should be billed
+    ///
+    /// By default, records are billable if their project is billable.  Use
+    /// "yes" or "no" to override this for a single record, or "project" to
+    /// remove the override again.
+    #[arg(long)]
+    pub billable: Option<Billable>,
+
+    /// add a tag to the record
+    ///
+    /// Can be used multiple times.  If the tag doesn't exist yet, it will be
+    /// created automatically.
+    #[arg(long = "add-tag", value_parser = parse_tag)]
+    pub add_tags: Vec<String>,
+
+    /// remove a tag from the record
+    ///
+    /// Can be used multiple times.
+    #[arg(long = "remove-tag", value_parser = parse_tag)]
+    pub remove_tags: Vec<String>,
+
+    #[command(flatten)]
+    pub note: NoteArgs,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Billable {
+    /// always bill the record
+    Yes,
+    /// never bill the record
+    No,
+    /// bill the record if its project is billable
+    Project,
+}
+
+impl Billable {
+    /// The override to store for the record, if any
+    pub fn as_override(self) -> Option<bool> {
+        match self {
+            Billable::Yes => Some(true),
+            Billable::No => Some(false),
+            Billable::Project => None,
+        }
+    }
 }
 
 #[derive(Args, Debug)]
@@ -216,12 +565,291 @@ pub struct Delete {
 }
 
 #[derive(Args, Debug)]
+pub struct Undo {
+    /// number of changes to undo
+    #[arg(default_value_t = 1)]
+    pub count: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct Redo {
+    /// number of changes to redo
+    #[arg(default_value_t = 1)]
+    pub count: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct AbsenceArgs {
+    #[command(subcommand)]
+    pub command: AbsenceCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AbsenceCommands {
+    /// Add an absence for a single day or a range of days
+    ///
+    /// When a range of days is given, days where no hours are expected
+    /// according to the schedule (e.g. weekends) are skipped.
+    Add(AddAbsence),
+
+    /// List the absences in a given year
+    Ls(ListAbsences),
+
+    /// Remove an existing absence by its ID
+    Rm(RemoveAbsence),
+
+    /// Show how many vacation days are left in a given year
+    ///
+    /// The yearly allowance is set using vacation_days_per_year in the
+    /// config file.
+    Remaining(VacationRemaining),
+}
+
+#[derive(Args, Debug)]
+pub struct AddAbsence {
+    /// the reason for the absence
+    pub kind: AbsenceKind,
+
+    /// the date of the absence (yyyy-mm-dd)
+    pub date: NaiveDate,
+
+    /// the last date of the absence (yyyy-mm-dd)
+    ///
+    /// If set, an absence is added for every working day from the first date
+    /// until this date (inclusive).
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+
+    /// only take half of each day off
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub half_day: bool,
+
+    /// a short description of the absence
+    #[arg(short = 'd', long)]
+    pub description: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ListAbsences {
+    /// the year to list absences for
+    ///
+    /// Defaults to the current year.
+    #[arg(long)]
+    pub year: Option<i32>,
+}
+
+#[derive(Args, Debug)]
+pub struct RemoveAbsence {
+    /// absence id
+    ///
+    /// The five-letter absence ID, as shown by "absence ls"
+    pub absence_id: String,
+}
+
+#[derive(Args, Debug)]
+pub struct VacationRemaining {
+    /// the year to show vacation days for
+    ///
+    /// Defaults to the current year.
+    #[arg(long)]
+    pub year: Option<i32>,
+}
+
+#[derive(Args, Debug)]
+pub struct HolidaysArgs {
+    #[command(subcommand)]
+    pub command: HolidaysCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HolidaysCommands {
+    /// Import public holidays from an iCalendar (.ics) file
+    ///
+    /// Every all-day event in the file is added as a holiday.  Events are
+    /// identified by their UID, so importing the same file again will update
+    /// the existing holidays rather than adding duplicates.
+    Import(ImportHolidays),
+}
+
+#[derive(Args, Debug)]
+pub struct ImportHolidays {
+    /// path to the .ics file
+    pub file: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct ProjectArgs {
+    #[command(subcommand)]
+    pub command: ProjectCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProjectCommands {
+    /// Mark a project as non-working time
+    ///
+    /// Time spent on non-working projects (e.g. commuting or volunteering) is
+    /// still recorded, but does not count towards overtime, the hours shown
+    /// by the times command, or the totals in reports.
+    NonWorking(ProjectName),
+
+    /// Mark a non-working project as working time again
+    Working(ProjectName),
+
+    /// List projects, with the total time spent and when they were last used
+    Ls(ListProjects),
+
+    /// Rename a project
+    ///
+    /// Fails if a project with the new name already exists, use merge instead.
+    Rename(RenameProject),
+
+    /// Move all records from one project into another
+    ///
+    /// The first project is deleted afterwards.  This can be reverted with undo.
+    Merge(MergeProjects),
+
+    /// Archive a project that is no longer used
+    ///
+    /// Archived projects are hidden from the project list, and a warning is
+    /// shown if new records are added to them.
+    Archive(ProjectName),
+
+    /// Restore an archived project
+    Unarchive(ProjectName),
+
+    /// Set the client that a project is billed to
+    ///
+    /// Sub-projects are billed to the same client, unless they have been
+    /// given a client of their own.
+    Client(ProjectClient),
+
+    /// Set the hourly rate for a project
+    ///
+    /// This overrides the client's default rate for the project and its
+    /// sub-projects.  The rate applies from the given date until the next
+    /// rate starts, so earlier records are still billed at the old rate.
+    Rate(SetRate),
+
+    /// Mark a project as billable (the default)
+    Billable(ProjectName),
+
+    /// Mark a project as not billable
+    ///
+    /// Records in non-billable projects are left out of the billing report,
+    /// unless the record itself has been marked as billable using
+    /// "edit --billable yes".
+    NonBillable(ProjectName),
+}
+
+#[derive(Args, Debug)]
+pub struct ListProjects {
+    /// include archived projects
+    #[arg(short = 'a', long, action = clap::ArgAction::SetTrue)]
+    pub all: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RenameProject {
+    /// the current name of the project
+    pub project: String,
+
+    /// the new name of the project
+    pub new_name: String,
+}
+
+#[derive(Args, Debug)]
+pub struct MergeProjects {
+    /// the project to move records out of
+    pub from: String,
+
+    /// the project to move records into
+    pub into: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ProjectName {
+    /// the name of the project
+    pub project: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ProjectClient {
+    /// the name of the project
+    pub project: String,
+
+    /// the name of the client
+    ///
+    /// If not given, the project is no longer billed to a client directly.
+    pub client: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct SetRate {
+    /// the name of the project or client
+    pub name: String,
+
+    /// the hourly rate (e.g. "85" or "92.50")
+    #[arg(value_parser = parse_rate)]
+    pub rate: i64,
+
+    /// the date that the rate applies from (yyyy-mm-dd)
+    ///
+    /// Defaults to the current date.  Setting a rate on the same date as an
+    /// existing rate replaces it.
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+}
+
+#[derive(Args, Debug)]
+pub struct ClientArgs {
+    #[command(subcommand)]
+    pub command: ClientCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClientCommands {
+    /// Add a new client
+    Add(AddClient),
+
+    /// List all clients, with their current rates
+    Ls,
+
+    /// Set the default hourly rate for a client's projects
+    ///
+    /// The rate applies from the given date until the next rate starts, so
+    /// earlier records are still billed at the old rate.
+    Rate(SetRate),
+}
+
+#[derive(Args, Debug)]
+pub struct AddClient {
+    /// the name of the client
+    pub name: String,
+
+    /// the currency that the client is billed in
+    #[arg(short = 'c', long, default_value = "EUR")]
+    pub currency: String,
+
+    /// the default hourly rate (e.g. "85" or "92.50")
+    ///
+    /// The rate applies from the current date onwards.
+    #[arg(short = 'r', long, value_parser = parse_rate)]
+    pub rate: Option<i64>,
+}
+
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Overtime {
+    #[command(subcommand)]
+    pub command: Option<OvertimeCommands>,
+
     /// hours worked per day
     ///
-    /// The number of hours in a conventional work day.  Defaults to 8.
-    #[arg(long, default_value = "8.0")]
-    pub hours: f64,
+    /// The number of hours in a conventional work day, from Monday to Friday.
+    /// If not set, the schedule from the config file is used, which defaults
+    /// to 8 hours per weekday.
+    #[arg(long)]
+    pub hours: Option<f64>,
 
     /// how long back to show overtime records
     ///
@@ -231,6 +859,51 @@ pub struct Overtime {
     /// "2 months" will show all records from the current and previous months.
     #[arg(short = 's', long, default_value = "1 week")]
     pub since: String,
+
+    /// when to show overtime records until
+    ///
+    /// Results will be rounded to the beginning of the relevant period.
+    /// For example, if until is "1 week", then records will be shown until
+    /// the start of the current week.  The keyword "now" will show results
+    /// until the current time.
+    #[arg(short = 'u', long, default_value = "now")]
+    pub until: String,
+
+    /// how to summarise overtime
+    ///
+    /// By default, every day and every change to the balance is shown on a
+    /// separate line.  If "daily", "weekly", or "monthly" is given, then the
+    /// hours worked, the hours expected, and the balance at the end of each
+    /// period are shown instead.  "auto" picks a granularity based on the
+    /// number of days shown.
+    #[arg(short = 'g', long, default_value = "all")]
+    pub granularity: Granularity,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OvertimeCommands {
+    /// Manually adjust the overtime balance
+    ///
+    /// Use this for overtime that was paid out or otherwise changed outside of
+    /// timesheettool.  Negative amounts need to be passed after "--" so that
+    /// they aren't treated as flags, e.g.: tst overtime adjust -- -10h "paid out"
+    Adjust(AdjustOvertime),
+}
+
+#[derive(Args, Debug)]
+pub struct AdjustOvertime {
+    /// the amount to add to the balance (e.g. "2h 30m" or "-10h")
+    #[arg(value_parser = parse_adjustment, allow_hyphen_values = true)]
+    pub amount: chrono::Duration,
+
+    /// the reason for the adjustment
+    pub description: Option<String>,
+
+    /// the date of the adjustment (yyyy-mm-dd)
+    ///
+    /// Defaults to the current date.
+    #[arg(long)]
+    pub date: Option<NaiveDate>,
 }
 
 #[derive(Args, Debug)]
@@ -254,3 +927,257 @@ pub struct Times {
     #[arg(short = 'u', long, default_value = "now")]
     pub until: String,
 }
+
+#[derive(Args, Debug)]
+pub struct Check {
+    /// repair problems that were found
+    ///
+    /// Overlapping records are repaired by ending the earlier record when the
+    /// later record starts.  With the "split" strategy, if the earlier record
+    /// continued after the later record ended, it is started again at that
+    /// point.  Empty records are deleted, and records in the future are only
+    /// reported.  All fixes can be reverted with a single undo.
+    #[arg(long, value_enum)]
+    pub fix: Option<FixStrategy>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixStrategy {
+    /// end the earlier record when the later record starts
+    Truncate,
+    /// end the earlier record, then restart it after the later record
+    Split,
+}
+
+#[derive(Args, Debug)]
+pub struct Export {
+    /// how long back to export records
+    ///
+    /// Results will be rounded to the beginning of the relevant period.
+    /// For example, if since is "1 week", then all records from the start
+    /// of the current week will be exported.  Similarly, an argument of
+    /// "2 months" will export all records from the current and previous months.
+    #[arg(short = 's', long, default_value = "1 week")]
+    pub since: String,
+
+    /// when to export records until
+    ///
+    /// Results will be rounded to the beginning of the relevant period.
+    /// For example, if until is "1 week", then records will be exported until
+    /// the start of the current week.  Similarly, an argument of "2 months"
+    /// will export all records up until the beginning of the previous month.
+    /// The keyword "now" will export results until the current time.
+    #[arg(short = 'u', long, default_value = "now")]
+    pub until: String,
+
+    /// how to aggregate records
+    ///
+    /// By default, each individual record is exported as a separate row.  If
+    /// "daily", "weekly", or "monthly" is given, then one row will be exported
+    /// per project and period instead.
+    #[arg(short = 'g', long, default_value = "all")]
+    pub granularity: Granularity,
+
+    /// columns to export
+    ///
+    /// A comma-separated list of column names, in the order that they should
+    /// be written.  Defaults to all available columns.  Records have the
+    /// columns "id", "project", "task", "started_at", "ended_at",
+    /// "duration_seconds", "tags", "notes", and "non_working".  Aggregated
+    /// rows have the columns "period", "label", "start", "end", "project",
+    /// "tasks", "duration_seconds", and "non_working".
+    #[arg(short = 'c', long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// rounding unit for aggregated rows
+    ///
+    /// Rounds the durations of aggregated rows to a multiple of this duration
+    /// (e.g. "30m" or "1h").  Individual records are never rounded.  Defaults
+    /// to the time_round_minutes value in the config file.
+    #[arg(short = 'r', long)]
+    pub rounding: Option<String>,
+
+    /// rounding direction for aggregated rows
+    ///
+    /// Defaults to the rounding_mode value in the config file, or "up" if
+    /// that is not set.
+    #[arg(long)]
+    pub rounding_mode: Option<RoundingMode>,
+
+    /// what to round in aggregated rows
+    ///
+    /// Defaults to the rounding_scope value in the config file, or
+    /// "project-day" if that is not set.
+    #[arg(long)]
+    pub rounding_scope: Option<RoundingScope>,
+
+    /// only export records with this tag
+    ///
+    /// Can be used multiple times, in which case only records that have all
+    /// of the given tags will be exported.
+    #[arg(long = "tag", value_parser = parse_tag)]
+    pub tags: Vec<String>,
+
+    /// only export records in this project or its sub-projects
+    ///
+    /// Can be used multiple times, in which case records in any of the given
+    /// projects will be exported.
+    #[arg(short = 'p', long = "project")]
+    pub projects: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    #[command(subcommand)]
+    pub command: ReportCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReportCommands {
+    /// Show how much to bill each client
+    ///
+    /// The time spent on each billable project is rounded in the same way as
+    /// in "ls", and multiplied by the project's hourly rate.  Projects that
+    /// don't belong to a client are left out.
+    Billing(BillingReport),
+}
+
+#[derive(Args, Debug)]
+pub struct BillingReport {
+    /// how long back to bill records
+    ///
+    /// Results will be rounded to the beginning of the relevant period.
+    /// For example, if since is "1 month", then all records from the start
+    /// of the current month will be billed.
+    #[arg(short = 's', long, default_value = "1 month")]
+    pub since: String,
+
+    /// when to bill records until
+    ///
+    /// Results will be rounded to the beginning of the relevant period.
+    /// The keyword "now" will bill records until the current time.
+    #[arg(short = 'u', long, default_value = "now")]
+    pub until: String,
+
+    /// only bill this client
+    ///
+    /// Can be used multiple times.
+    #[arg(short = 'c', long = "client")]
+    pub clients: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct InvoiceArgs {
+    #[command(subcommand)]
+    pub command: Option<InvoiceCommands>,
+
+    /// the client to invoice
+    #[arg(required = true)]
+    pub client: Option<String>,
+
+    /// how long back to invoice records
+    ///
+    /// Results will be rounded to the beginning of the relevant period.
+    /// For example, if since is "2 months", then all records from the start
+    /// of the previous month will be invoiced.
+    #[arg(short = 's', long, default_value = "1 month")]
+    pub since: String,
+
+    /// when to invoice records until
+    ///
+    /// Results will be rounded to the beginning of the relevant period.
+    /// For example, if until is "1 month", then records will be invoiced
+    /// until the start of the current month.  The keyword "now" will invoice
+    /// records until the current time.
+    #[arg(short = 'u', long, default_value = "now")]
+    pub until: String,
+
+    /// show the invoice without saving it or marking any records as invoiced
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub output: InvoiceOutput,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum InvoiceCommands {
+    /// List all invoices
+    Ls,
+
+    /// Show an existing invoice again
+    Show(ShowInvoice),
+
+    /// Cancel the most recent invoice
+    ///
+    /// The invoice is deleted, and its records can be changed and invoiced
+    /// again.  Only the most recent invoice can be cancelled, so that there
+    /// are no gaps in the invoice numbers.
+    Cancel(InvoiceNumber),
+}
+
+#[derive(Args, Debug)]
+pub struct ShowInvoice {
+    /// the invoice number
+    pub number: String,
+
+    #[command(flatten)]
+    pub output: InvoiceOutput,
+}
+
+#[derive(Args, Debug)]
+pub struct InvoiceNumber {
+    /// the invoice number
+    pub number: String,
+}
+
+#[derive(Args, Debug)]
+pub struct InvoiceOutput {
+    /// the template used to write the invoice
+    #[arg(short = 't', long, default_value = "markdown")]
+    pub template: InvoiceTemplate,
+
+    /// write the invoice to this file instead of to stdout
+    #[arg(short = 'o', long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceTemplate {
+    /// a Markdown document with a table of line items
+    #[value(alias = "md")]
+    Markdown,
+    /// a standalone HTML page
+    Html,
+    /// plain text, e.g. for emails
+    #[value(alias = "txt")]
+    Text,
+}
+
+/// Parses an hourly rate, returning the rate in hundredths (e.g. cents)
+fn parse_rate(rate: &str) -> Result<i64, String> {
+    parse_amount(rate).ok_or_else(|| format!("invalid rate {rate:?}"))
+}
+
+/// Parses an overtime adjustment, which may be negative
+fn parse_adjustment(amount: &str) -> Result<chrono::Duration, String> {
+    parse_signed_duration(amount).ok_or_else(|| format!("invalid duration {amount:?}"))
+}
+
+/// Parses a tag, which may optionally be prefixed with a "+"
+fn parse_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.strip_prefix('+').unwrap_or(tag);
+    if tag.is_empty() || tag.contains(char::is_whitespace) {
+        return Err(format!("invalid tag {tag:?}"));
+    }
+    Ok(tag.into())
+}
+
+/// Parses a tag that must be prefixed with a "+"
+fn parse_prefixed_tag(tag: &str) -> Result<String, String> {
+    if !tag.starts_with('+') {
+        return Err(format!("tags must start with a \"+\" (e.g. \"+{tag}\")"));
+    }
+    parse_tag(tag)
+}
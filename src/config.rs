@@ -2,6 +2,15 @@
 
 use std::{fs::read_to_string, path::PathBuf};
 
+use chrono::{Duration, NaiveDate};
+
+use crate::{
+    breaks::{BreakPolicy, BreakRule},
+    overtime::{OvertimePolicy, ResetPeriod},
+    rounding::{Rounding, RoundingMode, RoundingScope},
+    schedule::{Schedule, ScheduleChange, WeekHours},
+};
+
 const APP_NAME: &str = "timesheettool";
 
 pub fn load_config(config_path: Option<PathBuf>) -> Config {
@@ -36,19 +45,251 @@ pub fn load_config(config_path: Option<PathBuf>) -> Config {
     log::trace!("Config: database_path is {:?}", &database_path);
 
     let time_round_minutes = config_toml.time_round_minutes.unwrap_or(15);
+    let rounding = Rounding::new(
+        Duration::minutes(time_round_minutes.into()),
+        config_toml.rounding_mode.unwrap_or_default(),
+        config_toml.rounding_scope.unwrap_or_default(),
+    );
+    log::trace!("Config: rounding is {rounding:?}");
+
+    let schedule = config_toml.schedule.unwrap_or_default().into_schedule();
+    log::trace!("Config: schedule is {schedule:?}");
+
+    let vacation_days_per_year = config_toml.vacation_days_per_year;
+    log::trace!("Config: vacation_days_per_year is {vacation_days_per_year:?}");
+
+    let overtime = config_toml.overtime.unwrap_or_default().into_policy();
+    log::trace!("Config: overtime is {overtime:?}");
+
+    let breaks = config_toml.breaks.unwrap_or_default().into_policy();
+    log::trace!("Config: breaks is {breaks:?}");
+
+    let auto_create_projects = config_toml.auto_create_projects.unwrap_or(false);
+    log::trace!("Config: auto_create_projects is {auto_create_projects:?}");
+
     Config {
         database_path,
-        time_round_minutes,
+        rounding,
+        schedule,
+        vacation_days_per_year,
+        overtime,
+        breaks,
+        auto_create_projects,
     }
 }
 
 pub struct Config {
     pub database_path: PathBuf,
-    pub time_round_minutes: u32,
+    pub rounding: Rounding,
+    pub schedule: Schedule,
+    pub vacation_days_per_year: Option<f64>,
+    pub overtime: OvertimePolicy,
+    pub breaks: BreakPolicy,
+    /// Whether new projects can be created without passing --new-project
+    pub auto_create_projects: bool,
 }
 
 #[derive(Default, serde::Deserialize)]
 struct PartialConfig {
     database_path: Option<PathBuf>,
     time_round_minutes: Option<u32>,
+    rounding_mode: Option<RoundingMode>,
+    rounding_scope: Option<RoundingScope>,
+    schedule: Option<PartialSchedule>,
+    vacation_days_per_year: Option<f64>,
+    overtime: Option<PartialOvertime>,
+    breaks: Option<PartialBreaks>,
+    auto_create_projects: Option<bool>,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct PartialBreaks {
+    rules: Option<Vec<PartialBreakRule>>,
+    min_break_minutes: Option<u32>,
+    round_minutes: Option<u32>,
+    max_daily_hours: Option<f64>,
+    min_rest_hours: Option<f64>,
+}
+
+#[derive(serde::Deserialize)]
+struct PartialBreakRule {
+    after_hours: f64,
+    break_minutes: u32,
+}
+
+impl PartialBreaks {
+    fn into_policy(self) -> BreakPolicy {
+        let default = BreakPolicy::default();
+        let hours = |hours: f64| Duration::seconds((hours * 60.0 * 60.0).round() as i64);
+        BreakPolicy {
+            rules: self
+                .rules
+                .map(|rules| {
+                    rules
+                        .into_iter()
+                        .map(|rule| BreakRule {
+                            after: hours(rule.after_hours),
+                            minimum: Duration::minutes(rule.break_minutes.into()),
+                        })
+                        .collect()
+                })
+                .unwrap_or(default.rules),
+            min_break: self
+                .min_break_minutes
+                .map(|minutes| Duration::minutes(minutes.into()))
+                .unwrap_or(default.min_break),
+            rounding: self
+                .round_minutes
+                .map(|minutes| Duration::minutes(minutes.into()))
+                .unwrap_or(default.rounding),
+            max_daily: self.max_daily_hours.map(hours).unwrap_or(default.max_daily),
+            min_rest: self.min_rest_hours.map(hours).unwrap_or(default.min_rest),
+        }
+    }
+}
+
+#[derive(Default, serde::Deserialize)]
+struct PartialOvertime {
+    initial_balance: Option<f64>,
+    start_date: Option<NaiveDate>,
+    reset: Option<ResetPeriod>,
+    max_carry_over: Option<f64>,
+}
+
+impl PartialOvertime {
+    fn into_policy(self) -> OvertimePolicy {
+        OvertimePolicy {
+            initial_balance: self.initial_balance.unwrap_or(0.0),
+            start_date: self.start_date,
+            // a maximum carry-over implies that the balance gets reset at some
+            // point, so assume the most common case of a yearly reset.
+            reset: self
+                .reset
+                .or(self.max_carry_over.map(|_| ResetPeriod::Yearly)),
+            max_carry_over: self.max_carry_over.unwrap_or(0.0),
+        }
+    }
+}
+
+#[derive(Default, serde::Deserialize)]
+struct PartialSchedule {
+    #[serde(flatten)]
+    days: PartialWeek,
+    #[serde(default)]
+    changes: Vec<PartialScheduleChange>,
+}
+
+#[derive(serde::Deserialize)]
+struct PartialScheduleChange {
+    from: NaiveDate,
+    until: Option<NaiveDate>,
+    #[serde(flatten)]
+    days: PartialWeek,
+}
+
+/// Hours per weekday, where any missing days are taken from another schedule
+#[derive(Default, serde::Deserialize)]
+struct PartialWeek {
+    monday: Option<f64>,
+    tuesday: Option<f64>,
+    wednesday: Option<f64>,
+    thursday: Option<f64>,
+    friday: Option<f64>,
+    saturday: Option<f64>,
+    sunday: Option<f64>,
+}
+
+impl PartialWeek {
+    fn or(&self, fallback: WeekHours) -> WeekHours {
+        let [mon, tue, wed, thu, fri, sat, sun] = fallback.0;
+        WeekHours([
+            self.monday.unwrap_or(mon),
+            self.tuesday.unwrap_or(tue),
+            self.wednesday.unwrap_or(wed),
+            self.thursday.unwrap_or(thu),
+            self.friday.unwrap_or(fri),
+            self.saturday.unwrap_or(sat),
+            self.sunday.unwrap_or(sun),
+        ])
+    }
+}
+
+impl PartialSchedule {
+    fn into_schedule(self) -> Schedule {
+        let usual = self.days.or(WeekHours::weekdays(8.0));
+        let changes = self
+            .changes
+            .into_iter()
+            .map(|change| ScheduleChange {
+                from: change.from,
+                until: change.until,
+                // days missing from a change are taken from the usual schedule
+                hours: change.days.or(usual),
+            })
+            .collect();
+        Schedule::new(usual, changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn break_rules_replace_the_default_rules() {
+        let config: PartialConfig = toml::from_str(
+            r#"
+            [breaks]
+            round_minutes = 0
+            min_rest_hours = 12.5
+
+            [[breaks.rules]]
+            after_hours = 5.5
+            break_minutes = 20
+            "#,
+        )
+        .unwrap();
+        let policy = config.breaks.unwrap().into_policy();
+        assert_eq!(
+            policy,
+            BreakPolicy {
+                rules: vec![BreakRule {
+                    after: Duration::minutes(5 * 60 + 30),
+                    minimum: Duration::minutes(20),
+                }],
+                rounding: Duration::zero(),
+                min_rest: Duration::minutes(12 * 60 + 30),
+                ..BreakPolicy::default()
+            }
+        );
+    }
+
+    #[test]
+    fn schedule_days_default_to_the_usual_schedule() {
+        let config: PartialConfig = toml::from_str(
+            r#"
+            [schedule]
+            friday = 6
+            sunday = 4.5
+
+            [[schedule.changes]]
+            from = "2024-05-01"
+            until = "2024-05-31"
+            monday = 0
+            "#,
+        )
+        .unwrap();
+        let schedule = config.schedule.unwrap().into_schedule();
+        assert_eq!(
+            schedule,
+            Schedule::new(
+                WeekHours([8.0, 8.0, 8.0, 8.0, 6.0, 0.0, 4.5]),
+                vec![ScheduleChange {
+                    from: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+                    until: NaiveDate::from_ymd_opt(2024, 5, 31),
+                    hours: WeekHours([0.0, 8.0, 8.0, 8.0, 6.0, 0.0, 4.5]),
+                }]
+            )
+        );
+    }
 }
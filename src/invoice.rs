@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::{
+    commands::InvoiceTemplate,
+    print::{format_amount, format_hours},
+    records::Invoice,
+};
+
+/// Writes the invoice as a document using one of the built-in templates.
+pub fn render_invoice(
+    writer: &mut impl Write,
+    invoice: &Invoice,
+    template: InvoiceTemplate,
+) -> Result<()> {
+    match template {
+        InvoiceTemplate::Markdown => render_markdown(writer, invoice),
+        InvoiceTemplate::Html => render_html(writer, invoice),
+        InvoiceTemplate::Text => render_text(writer, invoice),
+    }
+}
+
+fn render_markdown(writer: &mut impl Write, invoice: &Invoice) -> Result<()> {
+    // table cells can't contain pipes, and a trailing double space is a
+    // line break in Markdown.
+    let cell = |text: &str| text.replace('|', "\\|");
+    writeln!(writer, "# Invoice {}", invoice.number)?;
+    writeln!(writer)?;
+    writeln!(writer, "**Client:** {}  ", invoice.client)?;
+    writeln!(writer, "**Date:** {}  ", invoice.issued_on)?;
+    writeln!(
+        writer,
+        "**Period:** {} to {}",
+        invoice.period_start, invoice.period_end
+    )?;
+    writeln!(writer)?;
+    writeln!(writer, "| Project | Task | Hours | Rate | Amount |")?;
+    writeln!(writer, "| --- | --- | ---: | ---: | ---: |")?;
+    for line in &invoice.lines {
+        writeln!(
+            writer,
+            "| {} | {} | {} | {}/h | {} |",
+            cell(&line.project),
+            cell(&line.task),
+            format_hours(line.duration),
+            format_amount(line.hourly_rate, &invoice.currency),
+            format_amount(line.amount, &invoice.currency),
+        )?;
+    }
+    writeln!(
+        writer,
+        "| **Total** | | **{}** | | **{}** |",
+        format_hours(invoice.duration()),
+        format_amount(invoice.total(), &invoice.currency),
+    )?;
+    Ok(())
+}
+
+fn render_html(writer: &mut impl Write, invoice: &Invoice) -> Result<()> {
+    let number = escape_html(&invoice.number);
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html>")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>Invoice {number}</title>")?;
+    writeln!(
+        writer,
+        "<style>td.number, th.number {{ text-align: right; }}</style>"
+    )?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<h1>Invoice {number}</h1>")?;
+    writeln!(writer, "<dl>")?;
+    writeln!(
+        writer,
+        "<dt>Client</dt><dd>{}</dd>",
+        escape_html(&invoice.client)
+    )?;
+    writeln!(writer, "<dt>Date</dt><dd>{}</dd>", invoice.issued_on)?;
+    writeln!(
+        writer,
+        "<dt>Period</dt><dd>{} to {}</dd>",
+        invoice.period_start, invoice.period_end
+    )?;
+    writeln!(writer, "</dl>")?;
+    writeln!(writer, "<table>")?;
+    writeln!(
+        writer,
+        "<thead><tr><th>Project</th><th>Task</th><th class=\"number\">Hours</th>\
+        <th class=\"number\">Rate</th><th class=\"number\">Amount</th></tr></thead>"
+    )?;
+    writeln!(writer, "<tbody>")?;
+    for line in &invoice.lines {
+        writeln!(
+            writer,
+            "<tr><td>{}</td><td>{}</td><td class=\"number\">{}</td>\
+            <td class=\"number\">{}/h</td><td class=\"number\">{}</td></tr>",
+            escape_html(&line.project),
+            escape_html(&line.task),
+            format_hours(line.duration),
+            escape_html(&format_amount(line.hourly_rate, &invoice.currency)),
+            escape_html(&format_amount(line.amount, &invoice.currency)),
+        )?;
+    }
+    writeln!(writer, "</tbody>")?;
+    writeln!(
+        writer,
+        "<tfoot><tr><th colspan=\"2\">Total</th><th class=\"number\">{}</th><th></th>\
+        <th class=\"number\">{}</th></tr></tfoot>",
+        format_hours(invoice.duration()),
+        escape_html(&format_amount(invoice.total(), &invoice.currency)),
+    )?;
+    writeln!(writer, "</table>")?;
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")?;
+    Ok(())
+}
+
+fn render_text(writer: &mut impl Write, invoice: &Invoice) -> Result<()> {
+    let rows = invoice
+        .lines
+        .iter()
+        .map(|line| {
+            [
+                line.project.clone(),
+                line.task.clone(),
+                format_hours(line.duration),
+                format!("{}/h", format_amount(line.hourly_rate, &invoice.currency)),
+                format_amount(line.amount, &invoice.currency),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let total = [
+        "Total".into(),
+        String::new(),
+        format_hours(invoice.duration()),
+        String::new(),
+        format_amount(invoice.total(), &invoice.currency),
+    ];
+    let header = ["Project", "Task", "Hours", "Rate", "Amount"].map(String::from);
+    let mut widths = [0; 5];
+    for row in [&header, &total].into_iter().chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let write_row = |writer: &mut dyn Write, row: &[String; 5]| {
+        writeln!(
+            writer,
+            "{:w0$}  {:w1$}  {:>w2$}  {:>w3$}  {:>w4$}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+        )
+    };
+
+    writeln!(writer, "INVOICE {}", invoice.number)?;
+    writeln!(writer)?;
+    writeln!(writer, "Client:  {}", invoice.client)?;
+    writeln!(writer, "Date:    {}", invoice.issued_on)?;
+    writeln!(
+        writer,
+        "Period:  {} to {}",
+        invoice.period_start, invoice.period_end
+    )?;
+    writeln!(writer)?;
+    write_row(writer, &header)?;
+    writeln!(writer, "{}", "-".repeat(widths.iter().sum::<usize>() + 8))?;
+    for row in &rows {
+        write_row(writer, row)?;
+    }
+    writeln!(writer, "{}", "-".repeat(widths.iter().sum::<usize>() + 8))?;
+    write_row(writer, &total)?;
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::*;
+    use crate::records::InvoiceLine;
+
+    fn invoice() -> Invoice {
+        let line = |project: &str, task: &str, minutes, amount| InvoiceLine {
+            project: project.into(),
+            task: task.into(),
+            hourly_rate: 9000,
+            duration: Duration::minutes(minutes),
+            amount,
+        };
+        Invoice {
+            number: "2024-0007".into(),
+            client: "Smith & Sons".into(),
+            currency: "EUR".into(),
+            issued_on: NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            period_start: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            period_end: NaiveDate::from_ymd_opt(2024, 5, 31).unwrap(),
+            lines: vec![
+                line("acme/web", "fix <form> | layout", 90, 13500),
+                line("acme/web", "review", 15, 2250),
+            ],
+        }
+    }
+
+    fn render(template: InvoiceTemplate) -> String {
+        let mut buffer = Vec::new();
+        render_invoice(&mut buffer, &invoice(), template).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn renders_markdown_invoices() {
+        let markdown = render(InvoiceTemplate::Markdown);
+        assert_eq!(
+            markdown,
+            "# Invoice 2024-0007\n\
+            \n\
+            **Client:** Smith & Sons  \n\
+            **Date:** 2024-06-03  \n\
+            **Period:** 2024-05-01 to 2024-05-31\n\
+            \n\
+            | Project | Task | Hours | Rate | Amount |\n\
+            | --- | --- | ---: | ---: | ---: |\n\
+            | acme/web | fix <form> \\| layout | 1.50h | 90.00 EUR/h | 135.00 EUR |\n\
+            | acme/web | review | 0.25h | 90.00 EUR/h | 22.50 EUR |\n\
+            | **Total** | | **1.75h** | | **157.50 EUR** |\n"
+        );
+    }
+
+    #[test]
+    fn escapes_html_and_aligns_text_invoices() {
+        let html = render(InvoiceTemplate::Html);
+        assert!(html.contains("<dt>Client</dt><dd>Smith &amp; Sons</dd>"));
+        assert!(html.contains("<td>fix &lt;form&gt; | layout</td>"));
+
+        let text = render(InvoiceTemplate::Text);
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[6..],
+            [
+                "Project   Task                 Hours         Rate      Amount",
+                "-------------------------------------------------------------",
+                "acme/web  fix <form> | layout  1.50h  90.00 EUR/h  135.00 EUR",
+                "acme/web  review               0.25h  90.00 EUR/h   22.50 EUR",
+                "-------------------------------------------------------------",
+                "Total                          1.75h               157.50 EUR",
+            ]
+        );
+    }
+}
@@ -1,7 +1,15 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod aggregate;
+pub mod billing;
+pub mod breaks;
+pub mod check;
 pub mod commands;
 pub mod config;
+pub mod invoice;
+pub mod overtime;
 pub mod parse;
 pub mod print;
 pub mod records;
+pub mod rounding;
+pub mod schedule;
@@ -0,0 +1,482 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::{aggregate::Period, commands::Granularity, records::Adjustment, schedule::Schedule};
+
+/// How often the overtime balance is reset
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResetPeriod {
+    Quarterly,
+    Yearly,
+}
+
+impl ResetPeriod {
+    /// Returns the first reset date after the given date.
+    fn next_reset(&self, date: NaiveDate) -> NaiveDate {
+        let start_of_year = NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap();
+        match self {
+            ResetPeriod::Yearly => start_of_year + Months::new(12),
+            ResetPeriod::Quarterly => start_of_year + Months::new((date.month0() / 3 + 1) * 3),
+        }
+    }
+}
+
+/// Rules about how the overtime balance is calculated
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OvertimePolicy {
+    /// The balance (in hours) at the start of the overtime account
+    pub initial_balance: f64,
+    /// The date that the overtime account starts.  Any days before this will
+    /// be ignored.
+    pub start_date: Option<NaiveDate>,
+    /// How often the balance is reset
+    pub reset: Option<ResetPeriod>,
+    /// The maximum number of hours that can be carried over when the balance
+    /// is reset.  Negative balances are always carried over in full.
+    pub max_carry_over: f64,
+}
+
+/// The hours worked on a single day, compared to the expected hours
+#[derive(Debug, Clone, PartialEq)]
+pub struct OvertimeDay {
+    pub date: NaiveDate,
+    pub hours_worked: f64,
+    pub hours_difference: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Initial,
+    Day,
+    Adjustment,
+    Reset,
+}
+
+impl EntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryKind::Initial => "initial",
+            EntryKind::Day => "day",
+            EntryKind::Adjustment => "adjustment",
+            EntryKind::Reset => "reset",
+        }
+    }
+}
+
+/// A single change to the overtime balance
+#[derive(Debug, Clone, PartialEq)]
+pub struct OvertimeEntry {
+    pub date: NaiveDate,
+    pub kind: EntryKind,
+    pub hours_worked: f64,
+    pub hours_difference: f64,
+    pub hours_balance: f64,
+    pub description: Option<String>,
+}
+
+/// The overtime for a longer period, e.g. a week or a month
+#[derive(Debug, Clone, PartialEq)]
+pub struct OvertimeSummary {
+    pub period: Period,
+    pub hours_worked: f64,
+    pub hours_expected: f64,
+    /// Changes to the balance that weren't caused by working, i.e. manual
+    /// adjustments, resets, and the initial balance.
+    pub hours_adjusted: f64,
+    /// The balance at the end of the period
+    pub hours_balance: f64,
+}
+
+impl OvertimeSummary {
+    pub fn hours_difference(&self) -> f64 {
+        self.hours_worked - self.hours_expected + self.hours_adjusted
+    }
+}
+
+/// Compares the hours worked to the hours expected by the schedule, for every
+/// day from the first day that was worked or taken off until the `until`
+/// date.  Days where nothing was tracked count as not having worked at all,
+/// unless no hours were expected on them either, in which case they are left
+/// out.  `days_off` gives the fraction of each day that was taken off and
+/// should be credited.
+pub fn overtime_days(
+    hours_worked: &BTreeMap<NaiveDate, f64>,
+    days_off: &BTreeMap<NaiveDate, f64>,
+    schedule: &Schedule,
+    until: NaiveDate,
+) -> Vec<OvertimeDay> {
+    let Some(first) = hours_worked.keys().chain(days_off.keys()).min().copied() else {
+        return Vec::new();
+    };
+
+    // days worked after the until date (e.g. records in the future) are still
+    // included, but the days in between are not filled in.
+    let later_days = hours_worked.keys().copied().filter(|date| *date > until);
+    first
+        .iter_days()
+        .take_while(|date| *date <= until)
+        .chain(later_days)
+        .filter_map(|date| {
+            let worked = hours_worked.get(&date).copied();
+            let day_off = days_off.get(&date).copied();
+            let expected = schedule.expected_hours(date);
+            if worked.is_none() && day_off.is_none() && expected == 0.0 {
+                return None;
+            }
+
+            let hours_worked = worked.unwrap_or(0.0);
+            let hours_expected = expected * (1.0 - day_off.unwrap_or(0.0).min(1.0));
+            Some(OvertimeDay {
+                date,
+                hours_worked,
+                hours_difference: hours_worked - hours_expected,
+            })
+        })
+        .collect()
+}
+
+/// Groups the balance entries into periods of the given granularity.  The
+/// entries must be sorted by date, as returned by `balance`.
+pub fn summarise(
+    entries: impl IntoIterator<Item = OvertimeEntry>,
+    granularity: &Granularity,
+) -> Result<Vec<OvertimeSummary>> {
+    let mut summaries: Vec<OvertimeSummary> = Vec::new();
+    for entry in entries {
+        let period = Period::containing(granularity, entry.date).ok_or(anyhow!(
+            "cannot summarise overtime with granularity {granularity:?}"
+        ))?;
+        let summary = match summaries.last_mut() {
+            Some(summary) if summary.period == period => summary,
+            _ => {
+                summaries.push(OvertimeSummary {
+                    period,
+                    hours_worked: 0.0,
+                    hours_expected: 0.0,
+                    hours_adjusted: 0.0,
+                    hours_balance: 0.0,
+                });
+                summaries.last_mut().unwrap()
+            }
+        };
+
+        match entry.kind {
+            EntryKind::Day => {
+                summary.hours_worked += entry.hours_worked;
+                summary.hours_expected += entry.hours_worked - entry.hours_difference;
+            }
+            EntryKind::Initial | EntryKind::Adjustment | EntryKind::Reset => {
+                summary.hours_adjusted += entry.hours_difference
+            }
+        }
+        summary.hours_balance = entry.hours_balance;
+    }
+
+    Ok(summaries)
+}
+
+/// Calculates the running overtime balance from the hours worked each day and
+/// any manual adjustments.  Both the days and the adjustments must be sorted
+/// by date.  Resets are applied up to and including the `until` date.
+pub fn balance(
+    days: impl IntoIterator<Item = OvertimeDay>,
+    adjustments: impl IntoIterator<Item = Adjustment>,
+    policy: &OvertimePolicy,
+    until: NaiveDate,
+) -> Vec<OvertimeEntry> {
+    let days = days.into_iter().map(|day| OvertimeEntry {
+        date: day.date,
+        kind: EntryKind::Day,
+        hours_worked: day.hours_worked,
+        hours_difference: day.hours_difference,
+        hours_balance: 0.0,
+        description: None,
+    });
+    let adjustments = adjustments.into_iter().map(|adjustment| OvertimeEntry {
+        date: adjustment.date,
+        kind: EntryKind::Adjustment,
+        hours_worked: 0.0,
+        hours_difference: adjustment.duration.num_seconds() as f64 / (60.0 * 60.0),
+        hours_balance: 0.0,
+        description: adjustment.description,
+    });
+    // adjustments are applied after the hours worked on the same day
+    let mut changes = itertools::merge_join_by(days, adjustments, |day, adjustment| {
+        day.date <= adjustment.date
+    })
+    .map(|either| either.into_inner())
+    .filter(|entry| policy.start_date.is_none_or(|start| entry.date >= start))
+    .peekable();
+
+    let Some(first_date) = policy
+        .start_date
+        .or_else(|| changes.peek().map(|entry| entry.date))
+    else {
+        return Vec::new();
+    };
+
+    let mut entries = Vec::new();
+    let mut hours_balance = policy.initial_balance;
+    if policy.initial_balance != 0.0 || policy.start_date.is_some() {
+        entries.push(OvertimeEntry {
+            date: first_date,
+            kind: EntryKind::Initial,
+            hours_worked: 0.0,
+            hours_difference: policy.initial_balance,
+            hours_balance,
+            description: None,
+        });
+    }
+
+    let mut last_date = first_date;
+    for mut entry in changes {
+        reset(
+            &mut entries,
+            &mut hours_balance,
+            policy,
+            last_date,
+            entry.date,
+        );
+        hours_balance += entry.hours_difference;
+        entry.hours_balance = hours_balance;
+        last_date = entry.date;
+        entries.push(entry);
+    }
+    reset(&mut entries, &mut hours_balance, policy, last_date, until);
+
+    entries
+}
+
+/// Adds an entry for each reset between the two dates that changes the
+/// balance, if there are any.
+fn reset(
+    entries: &mut Vec<OvertimeEntry>,
+    hours_balance: &mut f64,
+    policy: &OvertimePolicy,
+    after: NaiveDate,
+    until: NaiveDate,
+) {
+    let Some(period) = policy.reset else {
+        return;
+    };
+
+    let mut date = period.next_reset(after);
+    while date <= until {
+        let carried_over = hours_balance.min(policy.max_carry_over);
+        if carried_over != *hours_balance {
+            entries.push(OvertimeEntry {
+                date,
+                kind: EntryKind::Reset,
+                hours_worked: 0.0,
+                hours_difference: carried_over - *hours_balance,
+                hours_balance: carried_over,
+                description: None,
+            });
+            *hours_balance = carried_over;
+        }
+        date = period.next_reset(date);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn day(date: NaiveDate, hours_difference: f64) -> OvertimeDay {
+        OvertimeDay {
+            date,
+            hours_worked: 8.0 + hours_difference,
+            hours_difference,
+        }
+    }
+
+    fn balances(entries: &[OvertimeEntry]) -> Vec<(NaiveDate, EntryKind, f64)> {
+        entries
+            .iter()
+            .map(|entry| (entry.date, entry.kind, entry.hours_balance))
+            .collect()
+    }
+
+    #[test]
+    fn overtime_days_count_missed_weekdays() {
+        // Thursday 9th to Tuesday 14th May, with nothing tracked on Monday
+        let hours_worked = BTreeMap::from([
+            (date(2024, 5, 9), 9.0),
+            (date(2024, 5, 11), 2.0),
+            (date(2024, 5, 14), 8.0),
+        ]);
+        let days_off = BTreeMap::from([(date(2024, 5, 10), 1.0)]);
+        let days = overtime_days(
+            &hours_worked,
+            &days_off,
+            &Schedule::default(),
+            date(2024, 5, 14),
+        );
+        assert_eq!(
+            days.iter()
+                .map(|day| (day.date, day.hours_worked, day.hours_difference))
+                .collect::<Vec<_>>(),
+            [
+                (date(2024, 5, 9), 9.0, 1.0),
+                (date(2024, 5, 10), 0.0, 0.0),
+                (date(2024, 5, 11), 2.0, 2.0),
+                (date(2024, 5, 13), 0.0, -8.0),
+                (date(2024, 5, 14), 8.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn summarise_groups_entries_by_period() {
+        let entries = balance(
+            [
+                day(date(2024, 5, 10), 1.0),
+                day(date(2024, 5, 13), -2.0),
+                day(date(2024, 5, 14), 0.5),
+            ],
+            [Adjustment {
+                id: "wuhuo".into(),
+                date: date(2024, 5, 14),
+                duration: Duration::hours(3),
+                description: None,
+            }],
+            &OvertimePolicy::default(),
+            date(2024, 5, 14),
+        );
+
+        let summaries = summarise(entries, &Granularity::Weekly).unwrap();
+        assert_eq!(
+            summaries,
+            vec![
+                OvertimeSummary {
+                    period: Period::Week(date(2024, 5, 6)),
+                    hours_worked: 9.0,
+                    hours_expected: 8.0,
+                    hours_adjusted: 0.0,
+                    hours_balance: 1.0,
+                },
+                OvertimeSummary {
+                    period: Period::Week(date(2024, 5, 13)),
+                    hours_worked: 14.5,
+                    hours_expected: 16.0,
+                    hours_adjusted: 3.0,
+                    hours_balance: 2.5,
+                },
+            ]
+        );
+        assert_eq!(summaries[1].hours_difference(), 1.5);
+        assert!(summarise([], &Granularity::All).unwrap().is_empty());
+    }
+
+    #[test]
+    fn next_reset_is_the_start_of_the_next_period() {
+        assert_eq!(
+            ResetPeriod::Yearly.next_reset(date(2024, 12, 31)),
+            date(2025, 1, 1)
+        );
+        assert_eq!(
+            ResetPeriod::Yearly.next_reset(date(2025, 1, 1)),
+            date(2026, 1, 1)
+        );
+        assert_eq!(
+            ResetPeriod::Quarterly.next_reset(date(2024, 5, 12)),
+            date(2024, 7, 1)
+        );
+        assert_eq!(
+            ResetPeriod::Quarterly.next_reset(date(2024, 11, 30)),
+            date(2025, 1, 1)
+        );
+    }
+
+    #[test]
+    fn balance_sums_days_and_adjustments() {
+        let entries = balance(
+            [day(date(2024, 5, 13), 1.0), day(date(2024, 5, 14), 2.0)],
+            [Adjustment {
+                id: "wuhuo".into(),
+                date: date(2024, 5, 13),
+                duration: Duration::hours(-10),
+                description: Some("paid out".into()),
+            }],
+            &OvertimePolicy::default(),
+            date(2024, 5, 14),
+        );
+
+        assert_eq!(
+            balances(&entries),
+            vec![
+                (date(2024, 5, 13), EntryKind::Day, 1.0),
+                (date(2024, 5, 13), EntryKind::Adjustment, -9.0),
+                (date(2024, 5, 14), EntryKind::Day, -7.0),
+            ]
+        );
+        assert_eq!(entries[1].description.as_deref(), Some("paid out"));
+    }
+
+    #[test]
+    fn balance_starts_from_initial_balance_at_start_date() {
+        let policy = OvertimePolicy {
+            initial_balance: 5.0,
+            start_date: Some(date(2024, 5, 14)),
+            ..Default::default()
+        };
+        let entries = balance(
+            [day(date(2024, 5, 13), 1.0), day(date(2024, 5, 14), 2.0)],
+            [],
+            &policy,
+            date(2024, 5, 14),
+        );
+
+        assert_eq!(
+            balances(&entries),
+            vec![
+                (date(2024, 5, 14), EntryKind::Initial, 5.0),
+                (date(2024, 5, 14), EntryKind::Day, 7.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn balance_is_capped_at_each_reset() {
+        let policy = OvertimePolicy {
+            reset: Some(ResetPeriod::Yearly),
+            max_carry_over: 10.0,
+            ..Default::default()
+        };
+        let entries = balance(
+            [
+                day(date(2023, 12, 1), 25.0),
+                day(date(2024, 12, 1), -15.0),
+                day(date(2026, 2, 1), 16.0),
+            ],
+            [],
+            &policy,
+            date(2027, 1, 1),
+        );
+
+        assert_eq!(
+            balances(&entries),
+            vec![
+                (date(2023, 12, 1), EntryKind::Day, 25.0),
+                (date(2024, 1, 1), EntryKind::Reset, 10.0),
+                (date(2024, 12, 1), EntryKind::Day, -5.0),
+                // negative balances are carried over in full, so there are no
+                // resets in 2025 or 2026
+                (date(2026, 2, 1), EntryKind::Day, 11.0),
+                (date(2027, 1, 1), EntryKind::Reset, 10.0),
+            ]
+        );
+        assert_eq!(entries[1].hours_difference, -15.0);
+    }
+}
@@ -1,7 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod amountparse;
 mod dateparse;
+mod durationparse;
+mod icsparse;
 mod reldateparse;
 
+pub use amountparse::parse_amount;
 pub use dateparse::parse_date;
+pub use durationparse::{parse_duration, parse_signed_duration};
+pub use icsparse::{parse_ics_events, CalendarEvent};
 pub use reldateparse::parse_relative_date;
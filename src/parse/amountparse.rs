@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d+)(?:[.,](\d{1,2}))?$").expect("Could not parse Regex"));
+
+/// Parses an amount of money with up to two decimal places (e.g. "85" or
+/// "92.50"), returning the amount in hundredths (e.g. cents).
+pub fn parse_amount(amount: &str) -> Option<i64> {
+    let captures = REGEX.captures(amount.trim())?;
+    let whole: i64 = captures[1].parse().ok()?;
+    let fraction = match captures.get(2).map(|m| m.as_str()) {
+        None => 0,
+        Some(digits) if digits.len() == 1 => digits.parse::<i64>().ok()? * 10,
+        Some(digits) => digits.parse().ok()?,
+    };
+    whole.checked_mul(100)?.checked_add(fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_whole_and_decimal_amounts() {
+        assert_eq!(parse_amount("85"), Some(8500));
+        assert_eq!(parse_amount("92.5"), Some(9250));
+        assert_eq!(parse_amount(" 92,05 "), Some(9205));
+        assert_eq!(parse_amount("0.99"), Some(99));
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        assert_eq!(parse_amount(""), None);
+        assert_eq!(parse_amount("-10"), None);
+        assert_eq!(parse_amount("10.005"), None);
+        assert_eq!(parse_amount("ten"), None);
+    }
+}
@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::LazyLock;
+
+use chrono::Duration;
+use regex::Regex;
+
+static REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?xi)
+^ # anchor to start of string
+
+(?: # hours
+  (\d+) \s* h(?:r|rs|our|ours)? \s*
+)?
+(?: # minutes
+  (\d+) \s* m(?:in|ins|inute|inutes)? \s*
+)?
+(?: # seconds
+  (\d+) \s* s(?:ec|ecs|econd|econds)?
+)?
+
+$ # anchor to end of string
+",
+    )
+    .expect("Could not parse Regex")
+});
+
+/// Parses a duration written as a combination of hours, minutes, and seconds,
+/// e.g. "1h 30m" or "15 minutes".
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let duration = duration.trim();
+    if duration.is_empty() {
+        return None;
+    }
+    if duration == "0" {
+        return Some(Duration::zero());
+    }
+
+    let captures = REGEX.captures(duration)?;
+    let unit = |index: usize| -> Option<i64> {
+        captures
+            .get(index)
+            .map(|m| m.as_str().parse().ok())
+            .unwrap_or(Some(0))
+    };
+
+    Some(Duration::hours(unit(1)?) + Duration::minutes(unit(2)?) + Duration::seconds(unit(3)?))
+}
+
+/// Parses a duration that may be prefixed with a "+" or "-" sign, e.g. "-10h".
+pub fn parse_signed_duration(duration: &str) -> Option<Duration> {
+    let duration = duration.trim();
+    match duration.strip_prefix('-') {
+        Some(negative) => parse_duration(negative).map(|duration| -duration),
+        None => parse_duration(duration.strip_prefix('+').unwrap_or(duration)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_minutes() {
+        assert_eq!(parse_duration("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_duration("30 minutes"), Some(Duration::minutes(30)));
+    }
+
+    #[test]
+    fn parses_hours() {
+        assert_eq!(parse_duration("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_duration("1 hour"), Some(Duration::hours(1)));
+    }
+
+    #[test]
+    fn parses_combinations_of_units() {
+        assert_eq!(
+            parse_duration("1h 30m 15s"),
+            Some(Duration::seconds(60 * 60 + 30 * 60 + 15))
+        );
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+    }
+
+    #[test]
+    fn parses_zero() {
+        assert_eq!(parse_duration("0"), Some(Duration::zero()));
+        assert_eq!(parse_duration("0m"), Some(Duration::zero()));
+    }
+
+    #[test]
+    fn does_not_parse_units_in_the_wrong_order() {
+        assert_eq!(parse_duration("30m 1h"), None);
+    }
+
+    #[test]
+    fn does_not_parse_empty_strings() {
+        assert_eq!(parse_duration("   "), None);
+        assert_eq!(parse_duration("15"), None);
+    }
+
+    #[test]
+    fn parses_signed_durations() {
+        assert_eq!(parse_signed_duration("-10h"), Some(Duration::hours(-10)));
+        assert_eq!(
+            parse_signed_duration("+1h 30m"),
+            Some(Duration::minutes(90))
+        );
+        assert_eq!(parse_signed_duration("45m"), Some(Duration::minutes(45)));
+        assert_eq!(parse_signed_duration("-"), None);
+        assert_eq!(parse_duration("-10h"), None);
+    }
+}
@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use chrono::NaiveDate;
+
+/// An all-day event from an iCalendar file
+#[derive(Debug, PartialEq, Clone)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: Option<String>,
+    pub start: NaiveDate,
+    /// The day after the last day of the event
+    pub end: NaiveDate,
+}
+
+impl CalendarEvent {
+    /// Returns each day covered by this event.
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.start.iter_days().take_while(|day| *day < self.end)
+    }
+}
+
+/// Parses the all-day events (VEVENT components with a DATE start) from an
+/// iCalendar file.  Events with a time of day, or without a UID, are skipped.
+pub fn parse_ics_events(contents: &str) -> Vec<CalendarEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<PartialEvent> = None;
+    for line in unfold_lines(contents) {
+        let Some((name, params, value)) = split_property(&line) else {
+            continue;
+        };
+
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some(PartialEvent::default())
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some(event) = current.take().and_then(PartialEvent::finish) {
+                    events.push(event);
+                }
+            }
+            ("UID", Some(event)) => event.uid = Some(value.to_string()),
+            ("SUMMARY", Some(event)) => event.summary = Some(unescape_text(value)),
+            ("DTSTART", Some(event)) => event.start = parse_ics_date(params, value),
+            ("DTEND", Some(event)) => event.end = parse_ics_date(params, value),
+            _ => {}
+        }
+    }
+
+    events
+}
+
+#[derive(Default)]
+struct PartialEvent {
+    uid: Option<String>,
+    summary: Option<String>,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+}
+
+impl PartialEvent {
+    fn finish(self) -> Option<CalendarEvent> {
+        let start = self.start?;
+        Some(CalendarEvent {
+            uid: self.uid?,
+            summary: self.summary,
+            start,
+            // an all-day event without an end lasts for a single day
+            end: self.end.filter(|end| *end > start).or(start.succ_opt())?,
+        })
+    }
+}
+
+/// Joins folded lines back together.  Long lines in iCalendar files are split
+/// by a line break followed by a single space or tab.
+/// See: https://datatracker.ietf.org/doc/html/rfc5545#section-3.1
+fn unfold_lines(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Splits a content line into its upper-cased name, its parameters, and its
+/// value.  Parameters are left unparsed, as only VALUE=DATE is relevant here.
+fn split_property(line: &str) -> Option<(String, &str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let (name, params) = key.split_once(';').unwrap_or((key, ""));
+    Some((name.trim().to_ascii_uppercase(), params, value.trim()))
+}
+
+/// Parses a DTSTART or DTEND value, returning `None` for anything that isn't
+/// a plain date (i.e. a date with a time).
+fn parse_ics_date(params: &str, value: &str) -> Option<NaiveDate> {
+    let is_date = params
+        .split(';')
+        .any(|param| param.eq_ignore_ascii_case("VALUE=DATE"))
+        || value.len() == 8;
+    if !is_date {
+        return None;
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d").ok()
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_all_day_events() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:christmas-2024@example.com\r\n\
+            DTSTART;VALUE=DATE:20241225\r\n\
+            DTEND;VALUE=DATE:20241227\r\n\
+            SUMMARY:Christmas Day\\, Boxing Day\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:new-year-2024@example.com\r\n\
+            DTSTART:20240101\r\n\
+            SUMMARY:New Ye\r\n ar's Day\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let events = parse_ics_events(ics);
+        assert_eq!(
+            events,
+            vec![
+                CalendarEvent {
+                    uid: "christmas-2024@example.com".into(),
+                    summary: Some("Christmas Day, Boxing Day".into()),
+                    start: date(12, 25),
+                    end: date(12, 27),
+                },
+                CalendarEvent {
+                    uid: "new-year-2024@example.com".into(),
+                    summary: Some("New Year's Day".into()),
+                    start: date(1, 1),
+                    end: date(1, 2),
+                },
+            ]
+        );
+        assert_eq!(
+            events[0].days().collect::<Vec<_>>(),
+            vec![date(12, 25), date(12, 26)]
+        );
+    }
+
+    #[test]
+    fn skips_events_with_times_or_without_uids() {
+        let ics = "BEGIN:VEVENT\n\
+            UID:meeting@example.com\n\
+            DTSTART:20240512T100000Z\n\
+            END:VEVENT\n\
+            BEGIN:VEVENT\n\
+            DTSTART;VALUE=DATE:20240512\n\
+            END:VEVENT\n";
+
+        assert_eq!(parse_ics_events(ics), vec![]);
+    }
+}
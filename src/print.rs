@@ -1,28 +1,85 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::{collections::HashSet, fmt::Display, io::Write};
+use std::{fmt::Display, io::Write};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use itertools::Itertools;
+
+use crate::{
+    aggregate::{aggregate, aggregate_detailed},
+    commands::{Granularity, OutputFormat},
+    records::Record,
+    rounding::{Rounding, RoundingScope},
+};
+
+mod json;
+mod table;
+
+pub use json::{
+    write_json, AbsenceRow, AggregateRow, BillingRow, BreakRow, ClientRow, InvoiceRow,
+    OvertimePeriodRow, OvertimeRow, ProblemRow, ProjectRow, RecordRow, StatusRow, TimesRow,
+    VacationRow, ViolationRow,
+};
+pub use table::{write_table, Row};
+
+/// Writes the rows in the given machine-readable format.  The columns are
+/// only used for CSV and TSV output; if empty, all columns will be written.
+pub fn write_rows<T: Row>(
+    writer: &mut impl Write,
+    format: OutputFormat,
+    columns: &[String],
+    rows: impl IntoIterator<Item = T>,
+) -> Result<()> {
+    match format {
+        OutputFormat::Text => bail!("cannot write rows as text"),
+        OutputFormat::Json | OutputFormat::Ndjson => write_json(writer, format, rows),
+        OutputFormat::Csv => write_table(writer, b',', columns, rows),
+        OutputFormat::Tsv => write_table(writer, b'\t', columns, rows),
+    }
+}
 
-use crate::{commands::Granularity, records::Record};
-
+#[allow(clippy::too_many_arguments)]
 pub fn print<Tz>(
     writer: &mut impl Write,
     now: DateTime<Utc>,
     granularity: Granularity,
+    format: OutputFormat,
     records: Vec<Record>,
     tz: &Tz,
-    rounding_minutes: u32,
+    rounding: &Rounding,
+    show_notes: bool,
 ) -> Result<()>
 where
     Tz: TimeZone,
     Tz::Offset: Display,
 {
+    if format != OutputFormat::Text {
+        return match granularity {
+            Granularity::All => write_rows(
+                writer,
+                format,
+                &[],
+                records
+                    .into_iter()
+                    .map(|record| RecordRow::new(record, now)),
+            ),
+            Granularity::Auto => bail!("granularity must be resolved before printing"),
+            _ => write_rows(
+                writer,
+                format,
+                &[],
+                aggregate(records, now, &granularity, tz, rounding)?
+                    .into_iter()
+                    .map(AggregateRow::from),
+            ),
+        };
+    }
+
     match granularity {
-        Granularity::All => print_granularity_all(writer, now, records, tz)?,
-        Granularity::Daily => print_granularity_daily(writer, now, records, tz, rounding_minutes)?,
-        _ => unimplemented!("not yet implemented - other granularities like {granularity:?}"),
+        Granularity::All => print_granularity_all(writer, now, records, tz, show_notes)?,
+        Granularity::Auto => bail!("granularity must be resolved before printing"),
+        _ => print_granularity_aggregated(writer, now, granularity, records, tz, rounding)?,
     }
     Ok(())
 }
@@ -32,6 +89,7 @@ fn print_granularity_all<Tz>(
     now: DateTime<Utc>,
     records: Vec<Record>,
     tz: &Tz,
+    show_notes: bool,
 ) -> Result<()>
 where
     Tz: TimeZone,
@@ -55,7 +113,7 @@ where
         let ended_at = record.ended_at.map(|e| e.with_timezone(tz));
         print_times(writer, &started_at, &ended_at)?;
 
-        writeln!(
+        write!(
             writer,
             " {:>14}  ({:5})  {:10}  {}",
             duration_to_string(record.duration(now)),
@@ -63,94 +121,95 @@ where
             record.project,
             record.task,
         )?;
+        for tag in &record.tags {
+            write!(writer, " +{tag}")?;
+        }
+        writeln!(writer)?;
+
+        if let Some(notes) = record.notes.as_ref().filter(|_| show_notes) {
+            for line in notes.lines() {
+                writeln!(writer, "               | {line}")?;
+            }
+        }
     }
     Ok(())
 }
 
-fn print_granularity_daily<Tz>(
+fn print_granularity_aggregated<Tz>(
     writer: &mut impl Write,
     now: DateTime<Utc>,
+    granularity: Granularity,
     records: Vec<Record>,
     tz: &Tz,
-    rounding_minutes: u32,
+    rounding: &Rounding,
 ) -> Result<()>
 where
     Tz: TimeZone,
-    Tz::Offset: Display,
 {
-    writeln!(writer, "Date               Duration  Project     Task")?;
-
-    let mut records = records.into_iter().peekable();
-    while let Some(record) = records.next() {
-        let started_at = record.started_at.with_timezone(tz);
-        let mut printing_date = Some(&started_at);
-        let date = started_at.date_naive();
-        let mut records_vec = vec![record];
-        while let Some(record) = records.peek() {
-            let started_at = record.started_at.with_timezone(tz);
-            if started_at.date_naive() != date {
-                break;
+    let heading = match granularity {
+        Granularity::Weekly => "Week",
+        Granularity::Monthly => "Month",
+        _ => "Date",
+    };
+    writeln!(writer, "{heading:<13}      Duration  Project     Task")?;
+
+    // if rounding happens per day, then the per-project durations are not rounded,
+    // so the rounded total for each period needs to be shown separately.
+    let print_totals = rounding.scope == RoundingScope::Day;
+    let aggregates = aggregate_detailed(records, now, &granularity, tz, rounding)?;
+
+    let periods = aggregates
+        .projects
+        .into_iter()
+        .chunk_by(|aggregate| aggregate.period);
+    for (period, projects) in &periods {
+        write!(writer, "{:<13}", period.to_string())?;
+        for (index, aggregate) in projects.enumerate() {
+            if index > 0 {
+                write!(writer, "             ")?;
             }
-
-            records_vec.push(records.next().unwrap());
-        }
-
-        records_vec.sort_unstable_by(|a, b| a.project.cmp(&b.project).reverse());
-        let mut records = records_vec.into_iter().peekable();
-        while let Some(record) = records.next() {
-            let project = &record.project;
-            let mut tasks = HashSet::new();
-            tasks.insert(record.task.clone());
-            let mut duration = record.duration(now);
-            while let Some(record) = records.peek() {
-                if &record.project != project {
-                    break;
-                }
-
-                duration += record.duration(now);
-                tasks.insert(record.task.clone());
-                records.next();
+            write!(
+                writer,
+                "{:>14}  {:10}  {}",
+                duration_to_string(aggregate.duration),
+                aggregate.project,
+                aggregate.tasks.join(", "),
+            )?;
+            if aggregate.non_working {
+                write!(writer, " (non-working)")?;
             }
+            writeln!(writer)?;
+        }
 
-            let mut tasks = tasks.into_iter().collect::<Vec<_>>();
-            tasks.sort_unstable();
-            let tasks = tasks.join(", ");
+        for (project, duration) in aggregates.rollups.get(&period).into_iter().flatten() {
+            writeln!(
+                writer,
+                "             {:>14}  {project} (incl. sub-projects)",
+                duration_to_string(*duration),
+            )?;
+        }
 
-            print_daily_line(
+        for (tag, duration) in aggregates.tags.get(&period).into_iter().flatten() {
+            writeln!(
                 writer,
-                printing_date,
-                round_duration(duration, rounding_minutes),
-                record.project,
-                &tasks,
+                "             {:>14}  +{tag}",
+                duration_to_string(*duration),
             )?;
-            printing_date = None;
+        }
+
+        if print_totals {
+            print_total_line(writer, aggregates.totals[&period])?;
         }
     }
 
     Ok(())
 }
 
-fn print_daily_line<Tz>(
-    writer: &mut impl Write,
-    date: Option<&DateTime<Tz>>,
-    duration: Duration,
-    project: String,
-    task: &str,
-) -> Result<()>
-where
-    Tz: TimeZone,
-    Tz::Offset: Display,
-{
-    match date {
-        Some(date) => print_date(writer, date)?,
-        None => write!(writer, "             ")?,
-    }
+fn print_total_line(writer: &mut impl Write, duration: Duration) -> Result<()> {
     writeln!(
         writer,
-        "{:>14}  {:10}  {}",
+        "             {:>14}  (total)",
         duration_to_string(duration),
-        project,
-        task,
     )?;
     Ok(())
 }
@@ -167,23 +226,7 @@ where
     Ok(())
 }
 
-fn round_duration(duration: Duration, rounding_minutes: u32) -> Duration {
-    let duration_secs = duration.num_seconds();
-    let rounding_seconds = (rounding_minutes * 60) as i64;
-
-    Duration::seconds(round_to_next(duration_secs, rounding_seconds))
-}
-
-fn round_to_next(value: i64, unit: i64) -> i64 {
-    let remainder = value % unit;
-    if remainder == 0 {
-        value
-    } else {
-        value + unit - remainder
-    }
-}
-
-fn duration_to_string(mut duration: Duration) -> String {
+pub fn duration_to_string(mut duration: Duration) -> String {
     let mut buf = String::new();
     let days = duration.num_days();
     if days > 0 {
@@ -212,6 +255,20 @@ fn duration_to_string(mut duration: Duration) -> String {
     buf
 }
 
+/// Formats an amount given in hundredths of the currency, e.g. "1234.50 EUR"
+pub fn format_amount(amount: i64, currency: &str) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let amount = amount.abs();
+    format!("{sign}{}.{:02} {currency}", amount / 100, amount % 100)
+}
+
+/// Formats a duration as decimal hours, e.g. "1.50h", as used on invoices
+pub fn format_hours(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    let hundredths = (minutes * 100 + 30) / 60;
+    format!("{}.{:02}h", hundredths / 100, hundredths % 100)
+}
+
 fn print_times<Tz>(
     writer: &mut impl Write,
     started_at: &DateTime<Tz>,
@@ -259,8 +316,18 @@ where
 mod tests {
     use chrono::TimeZone as _;
 
+    use crate::rounding::RoundingMode;
+
     use super::*;
 
+    fn rounding() -> Rounding {
+        Rounding::new(
+            Duration::minutes(15),
+            RoundingMode::Up,
+            RoundingScope::ProjectDay,
+        )
+    }
+
     fn dt(time: &str) -> DateTime<Utc> {
         let mut parts = time.split(":");
         let hour = parts.next().unwrap().parse().unwrap();
@@ -275,8 +342,14 @@ mod tests {
             id: "hello".into(),
             task: "blub".into(),
             project: "blob".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
             started_at: dt("12:23:34"),
             ended_at: Some(dt("13:34:45")),
+            tags: Vec::new(),
+            notes: None,
         };
 
         let mut buffer = Vec::new();
@@ -284,9 +357,11 @@ mod tests {
             &mut buffer,
             dt("14:00:00"),
             Granularity::All,
+            OutputFormat::Text,
             vec![record],
             &Utc,
-            15,
+            &rounding(),
+            false,
         )
         .unwrap();
         let result = String::from_utf8(buffer).unwrap();
@@ -294,7 +369,7 @@ mod tests {
             result,
             "
 Date           Times                     Duration  ( id  )  Project     Task
-Su 12 May '24  12:23:34-13:34:45       1h 11m 11s  (hello)  blob        blub\n"
+Su 12 May '24  12:23:34-13:34:45           1h 11m  (hello)  blob        blub\n"
                 .trim_start()
         );
     }
@@ -305,8 +380,14 @@ Su 12 May '24  12:23:34-13:34:45       1h 11m 11s  (hello)  blob        blub\n"
             id: "hello".into(),
             task: "blub".into(),
             project: "blob".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
             started_at: dt("12:23:34"),
             ended_at: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         let mut buffer = Vec::new();
@@ -314,9 +395,11 @@ Su 12 May '24  12:23:34-13:34:45       1h 11m 11s  (hello)  blob        blub\n"
             &mut buffer,
             dt("14:00:00"),
             Granularity::All,
+            OutputFormat::Text,
             vec![record],
             &Utc,
-            15,
+            &rounding(),
+            false,
         )
         .unwrap();
         let result = String::from_utf8(buffer).unwrap();
@@ -324,7 +407,7 @@ Su 12 May '24  12:23:34-13:34:45       1h 11m 11s  (hello)  blob        blub\n"
             result,
             "
 Date           Times                     Duration  ( id  )  Project     Task
-Su 12 May '24  12:23:34-               1h 36m 26s  (hello)  blob        blub\n"
+Su 12 May '24  12:23:34-                   1h 36m  (hello)  blob        blub\n"
                 .trim_start()
         );
     }
@@ -336,15 +419,216 @@ Su 12 May '24  12:23:34-               1h 36m 26s  (hello)  blob        blub\n"
                 id: "hello".into(),
                 task: "blub".into(),
                 project: "blob".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
                 started_at: dt("12:23:34"),
                 ended_at: Some(dt("13:34:45")),
+                tags: Vec::new(),
+                notes: None,
             },
             Record {
                 id: "hello".into(),
                 task: "blub".into(),
                 project: "blob".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
                 started_at: dt("14:45:56"),
                 ended_at: None,
+                tags: Vec::new(),
+                notes: None,
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        print(
+            &mut buffer,
+            dt("15:00:00"),
+            Granularity::All,
+            OutputFormat::Text,
+            records,
+            &Utc,
+            &rounding(),
+            false,
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "
+Date           Times                     Duration  ( id  )  Project     Task
+Su 12 May '24  12:23:34-13:34:45           1h 11m  (hello)  blob        blub
+               14:45:56-                      14m  (hello)  blob        blub\n"
+                .trim_start()
+        );
+    }
+
+    #[test]
+    fn prints_records_with_granularity_weekly() {
+        let records = vec![
+            Record {
+                id: "hello".into(),
+                task: "blub".into(),
+                project: "blob".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: Utc.with_ymd_and_hms(2024, 12, 30, 9, 0, 0).unwrap(),
+                ended_at: Some(Utc.with_ymd_and_hms(2024, 12, 30, 10, 5, 0).unwrap()),
+                tags: Vec::new(),
+                notes: None,
+            },
+            Record {
+                id: "hello".into(),
+                task: "blab".into(),
+                project: "blob".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: Utc.with_ymd_and_hms(2025, 1, 2, 9, 0, 0).unwrap(),
+                ended_at: Some(Utc.with_ymd_and_hms(2025, 1, 2, 10, 0, 0).unwrap()),
+                tags: Vec::new(),
+                notes: None,
+            },
+            Record {
+                id: "hello".into(),
+                task: "other".into(),
+                project: "abc".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: Utc.with_ymd_and_hms(2025, 1, 6, 9, 0, 0).unwrap(),
+                ended_at: Some(Utc.with_ymd_and_hms(2025, 1, 6, 9, 30, 0).unwrap()),
+                tags: Vec::new(),
+                notes: None,
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        print(
+            &mut buffer,
+            dt("15:00:00"),
+            Granularity::Weekly,
+            OutputFormat::Text,
+            records,
+            &Utc,
+            &rounding(),
+            false,
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "
+Week               Duration  Project     Task
+2025-W01             2h 15m  blob        blab, blub
+2025-W02                30m  abc         other\n"
+                .trim_start()
+        );
+    }
+
+    #[test]
+    fn prints_records_with_granularity_monthly() {
+        let records = vec![
+            Record {
+                id: "hello".into(),
+                task: "blub".into(),
+                project: "blob".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: Utc.with_ymd_and_hms(2024, 12, 31, 9, 0, 0).unwrap(),
+                ended_at: Some(Utc.with_ymd_and_hms(2024, 12, 31, 10, 0, 0).unwrap()),
+                tags: Vec::new(),
+                notes: None,
+            },
+            Record {
+                id: "hello".into(),
+                task: "blub".into(),
+                project: "blob".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: Utc.with_ymd_and_hms(2025, 1, 1, 9, 0, 0).unwrap(),
+                ended_at: Some(Utc.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap()),
+                tags: Vec::new(),
+                notes: None,
+            },
+            Record {
+                id: "hello".into(),
+                task: "other".into(),
+                project: "abc".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: Utc.with_ymd_and_hms(2025, 1, 6, 9, 0, 0).unwrap(),
+                ended_at: Some(Utc.with_ymd_and_hms(2025, 1, 6, 9, 30, 0).unwrap()),
+                tags: Vec::new(),
+                notes: None,
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        print(
+            &mut buffer,
+            dt("15:00:00"),
+            Granularity::Monthly,
+            OutputFormat::Text,
+            records,
+            &Utc,
+            &rounding(),
+            false,
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "
+Month              Duration  Project     Task
+Dec 2024              1h 0m  blob        blub
+Jan 2025              1h 0m  blob        blub
+                        30m  abc         other\n"
+                .trim_start()
+        );
+    }
+
+    #[test]
+    fn prints_total_lines_when_rounding_per_day() {
+        let records = vec![
+            Record {
+                id: "hello".into(),
+                task: "blub".into(),
+                project: "blob".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: dt("09:00:00"),
+                ended_at: Some(dt("09:20:00")),
+                tags: Vec::new(),
+                notes: None,
+            },
+            Record {
+                id: "hello".into(),
+                task: "other".into(),
+                project: "abc".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: dt("10:00:00"),
+                ended_at: Some(dt("10:20:00")),
+                tags: Vec::new(),
+                notes: None,
             },
         ];
 
@@ -352,10 +636,183 @@ Su 12 May '24  12:23:34-               1h 36m 26s  (hello)  blob        blub\n"
         print(
             &mut buffer,
             dt("15:00:00"),
+            Granularity::Daily,
+            OutputFormat::Text,
+            records,
+            &Utc,
+            &Rounding::new(Duration::minutes(15), RoundingMode::Up, RoundingScope::Day),
+            false,
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "
+Date               Duration  Project     Task
+Su 12 May '24           20m  blob        blub
+                        20m  abc         other
+                        45m  (total)\n"
+                .trim_start()
+        );
+    }
+
+    #[test]
+    fn prints_tags_with_granularity_all() {
+        let record = Record {
+            id: "hello".into(),
+            task: "blub".into(),
+            project: "blob".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: dt("12:23:34"),
+            ended_at: Some(dt("13:34:45")),
+            tags: vec!["billable".into(), "meeting".into()],
+            notes: None,
+        };
+
+        let mut buffer = Vec::new();
+        print(
+            &mut buffer,
+            dt("14:00:00"),
             Granularity::All,
+            OutputFormat::Text,
+            vec![record],
+            &Utc,
+            &rounding(),
+            false,
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "
+Date           Times                     Duration  ( id  )  Project     Task
+Su 12 May '24  12:23:34-13:34:45           1h 11m  (hello)  blob        blub +billable +meeting\n"
+                .trim_start()
+        );
+    }
+
+    #[test]
+    fn prints_tag_totals_with_granularity_daily() {
+        let records = vec![
+            Record {
+                id: "hello".into(),
+                task: "blub".into(),
+                project: "blob".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: dt("09:00:00"),
+                ended_at: Some(dt("10:00:00")),
+                tags: vec!["meeting".into()],
+                notes: None,
+            },
+            Record {
+                id: "hello".into(),
+                task: "other".into(),
+                project: "abc".into(),
+                non_working: false,
+                paused: false,
+                billable: true,
+                invoiced: false,
+                started_at: dt("10:00:00"),
+                ended_at: Some(dt("10:20:00")),
+                tags: vec![],
+                notes: None,
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        print(
+            &mut buffer,
+            dt("15:00:00"),
+            Granularity::Daily,
+            OutputFormat::Text,
             records,
             &Utc,
-            15,
+            &rounding(),
+            false,
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "
+Date               Duration  Project     Task
+Su 12 May '24         1h 0m  blob        blub
+                        30m  abc         other
+                      1h 0m  +meeting\n"
+                .trim_start()
+        );
+    }
+
+    #[test]
+    fn marks_non_working_projects_with_granularity_daily() {
+        let record = Record {
+            id: "hello".into(),
+            task: "drive".into(),
+            project: "commute".into(),
+            non_working: true,
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: dt("08:00:00"),
+            ended_at: Some(dt("08:30:00")),
+            tags: Vec::new(),
+            notes: None,
+        };
+
+        let mut buffer = Vec::new();
+        print(
+            &mut buffer,
+            dt("15:00:00"),
+            Granularity::Daily,
+            OutputFormat::Text,
+            vec![record],
+            &Utc,
+            &rounding(),
+            false,
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "
+Date               Duration  Project     Task
+Su 12 May '24           30m  commute     drive (non-working)\n"
+                .trim_start()
+        );
+    }
+
+    #[test]
+    fn prints_notes_with_granularity_all_if_requested() {
+        let record = Record {
+            id: "hello".into(),
+            task: "blub".into(),
+            project: "blob".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: dt("12:23:34"),
+            ended_at: Some(dt("13:34:45")),
+            tags: Vec::new(),
+            notes: Some("first line\nsecond line".into()),
+        };
+
+        let mut buffer = Vec::new();
+        print(
+            &mut buffer,
+            dt("14:00:00"),
+            Granularity::All,
+            OutputFormat::Text,
+            vec![record],
+            &Utc,
+            &rounding(),
+            true,
         )
         .unwrap();
         let result = String::from_utf8(buffer).unwrap();
@@ -363,9 +820,55 @@ Su 12 May '24  12:23:34-               1h 36m 26s  (hello)  blob        blub\n"
             result,
             "
 Date           Times                     Duration  ( id  )  Project     Task
-Su 12 May '24  12:23:34-13:34:45       1h 11m 11s  (hello)  blob        blub
-               14:45:56-                   14m 4s  (hello)  blob        blub\n"
+Su 12 May '24  12:23:34-13:34:45           1h 11m  (hello)  blob        blub
+               | first line
+               | second line\n"
                 .trim_start()
         );
     }
+
+    #[test]
+    fn writes_records_with_granularity_all_as_csv() {
+        let record = Record {
+            id: "hello".into(),
+            task: "blub".into(),
+            project: "blob".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: dt("12:23:34"),
+            ended_at: Some(dt("13:34:45")),
+            tags: vec!["billable".into(), "meeting".into()],
+            notes: None,
+        };
+
+        let mut buffer = Vec::new();
+        print(
+            &mut buffer,
+            dt("14:00:00"),
+            Granularity::All,
+            OutputFormat::Csv,
+            vec![record],
+            &Utc,
+            &rounding(),
+            false,
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "id,project,task,started_at,ended_at,duration_seconds,tags,notes,non_working\r\n\
+            hello,blob,blub,2024-05-12T12:23:34Z,2024-05-12T13:34:45Z,4271,\"billable, meeting\",,false\r\n"
+        );
+    }
+
+    #[test]
+    fn formats_amounts_and_hours() {
+        assert_eq!(format_amount(123450, "EUR"), "1234.50 EUR");
+        assert_eq!(format_amount(5, "USD"), "0.05 USD");
+        assert_eq!(format_amount(-250, "EUR"), "-2.50 EUR");
+        assert_eq!(format_hours(Duration::minutes(90)), "1.50h");
+        assert_eq!(format_hours(Duration::minutes(20)), "0.33h");
+    }
 }
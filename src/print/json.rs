@@ -0,0 +1,699 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use itertools::Itertools;
+use serde::Serialize;
+
+use super::table::Row;
+use crate::{
+    aggregate::{Aggregate, Period},
+    billing::BillingLine,
+    breaks::{Gap, GapKind, Violation, ViolationKind},
+    check::Problem,
+    commands::OutputFormat,
+    overtime::{OvertimeEntry, OvertimeSummary},
+    records::{Absence, Invoice, ProjectUsage, Record},
+};
+
+/// Writes the rows to the writer as either a JSON array or as
+/// newline-delimited JSON, depending on the format.
+pub fn write_json<T: Serialize>(
+    writer: &mut impl Write,
+    format: OutputFormat,
+    rows: impl IntoIterator<Item = T>,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => {
+            let rows = rows.into_iter().collect::<Vec<_>>();
+            serde_json::to_writer_pretty(&mut *writer, &rows)?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Ndjson => {
+            for row in rows {
+                serde_json::to_writer(&mut *writer, &row)?;
+                writeln!(writer)?;
+            }
+        }
+        other => bail!("cannot write {other:?} output as JSON"),
+    }
+    Ok(())
+}
+
+/// The JSON representation of a single record
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RecordRow {
+    pub id: String,
+    pub project: String,
+    pub task: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_seconds: i64,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    pub non_working: bool,
+}
+
+impl Row for RecordRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "project",
+            "task",
+            "started_at",
+            "ended_at",
+            "duration_seconds",
+            "tags",
+            "notes",
+            "non_working",
+        ]
+    }
+}
+
+impl RecordRow {
+    pub fn new(record: Record, now: DateTime<Utc>) -> Self {
+        Self {
+            duration_seconds: record.duration(now).num_seconds(),
+            id: record.id,
+            project: record.project,
+            non_working: record.non_working,
+            task: record.task,
+            started_at: record.started_at,
+            ended_at: record.ended_at,
+            tags: record.tags,
+            notes: record.notes,
+        }
+    }
+}
+
+/// The JSON representation of the time spent on a project in a given period
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AggregateRow {
+    pub period: &'static str,
+    pub label: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub project: String,
+    pub tasks: Vec<String>,
+    pub duration_seconds: i64,
+    pub non_working: bool,
+}
+
+impl Row for AggregateRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "period",
+            "label",
+            "start",
+            "end",
+            "project",
+            "tasks",
+            "duration_seconds",
+            "non_working",
+        ]
+    }
+}
+
+impl From<Aggregate> for AggregateRow {
+    fn from(aggregate: Aggregate) -> Self {
+        Self {
+            period: period_kind(&aggregate.period),
+            label: aggregate.period.to_string(),
+            start: aggregate.period.start(),
+            end: aggregate.period.end(),
+            project: aggregate.project,
+            non_working: aggregate.non_working,
+            tasks: aggregate.tasks,
+            duration_seconds: aggregate.duration.num_seconds(),
+        }
+    }
+}
+
+/// The JSON representation of the overtime balance for a single day
+#[derive(Serialize, Debug, PartialEq)]
+pub struct OvertimeRow {
+    pub date: NaiveDate,
+    pub kind: &'static str,
+    pub worked_seconds: i64,
+    pub difference_seconds: i64,
+    pub balance_seconds: i64,
+    pub description: Option<String>,
+}
+
+impl Row for OvertimeRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "date",
+            "kind",
+            "worked_seconds",
+            "difference_seconds",
+            "balance_seconds",
+            "description",
+        ]
+    }
+}
+
+impl From<OvertimeEntry> for OvertimeRow {
+    fn from(entry: OvertimeEntry) -> Self {
+        Self {
+            date: entry.date,
+            kind: entry.kind.as_str(),
+            worked_seconds: hours_to_seconds(entry.hours_worked),
+            difference_seconds: hours_to_seconds(entry.hours_difference),
+            balance_seconds: hours_to_seconds(entry.hours_balance),
+            description: entry.description,
+        }
+    }
+}
+
+/// The JSON representation of the overtime worked in a given period
+#[derive(Serialize, Debug, PartialEq)]
+pub struct OvertimePeriodRow {
+    pub period: &'static str,
+    pub label: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub worked_seconds: i64,
+    pub expected_seconds: i64,
+    pub adjustment_seconds: i64,
+    pub difference_seconds: i64,
+    pub balance_seconds: i64,
+}
+
+impl Row for OvertimePeriodRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "period",
+            "label",
+            "start",
+            "end",
+            "worked_seconds",
+            "expected_seconds",
+            "adjustment_seconds",
+            "difference_seconds",
+            "balance_seconds",
+        ]
+    }
+}
+
+impl From<OvertimeSummary> for OvertimePeriodRow {
+    fn from(summary: OvertimeSummary) -> Self {
+        Self {
+            period: period_kind(&summary.period),
+            label: summary.period.to_string(),
+            start: summary.period.start(),
+            end: summary.period.end(),
+            worked_seconds: hours_to_seconds(summary.hours_worked),
+            expected_seconds: hours_to_seconds(summary.hours_expected),
+            adjustment_seconds: hours_to_seconds(summary.hours_adjusted),
+            difference_seconds: hours_to_seconds(summary.hours_difference()),
+            balance_seconds: hours_to_seconds(summary.hours_balance),
+        }
+    }
+}
+
+fn period_kind(period: &Period) -> &'static str {
+    match period {
+        Period::Day(_) => "day",
+        Period::Week(_) => "week",
+        Period::Month(_) => "month",
+    }
+}
+
+fn hours_to_seconds(hours: f64) -> i64 {
+    (hours * 60.0 * 60.0).round() as i64
+}
+
+/// The JSON representation of the start and end of a single working day
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TimesRow {
+    pub date: NaiveDate,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_seconds: i64,
+    pub breaks: Vec<BreakRow>,
+    pub absences: Vec<AbsenceRow>,
+    pub violations: Vec<ViolationRow>,
+}
+
+impl Row for TimesRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "date",
+            "started_at",
+            "ended_at",
+            "duration_seconds",
+            "breaks",
+            "absences",
+            "violations",
+        ]
+    }
+
+    /// Breaks are written as `start/end` intervals, and absences and
+    /// violations by their kinds, each separated by semicolons.
+    fn field(&self, column: &str) -> Option<String> {
+        let field = match column {
+            "breaks" => self
+                .breaks
+                .iter()
+                .map(|pause| {
+                    let format =
+                        |time: DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Secs, true);
+                    format!("{}/{}", format(pause.started_at), format(pause.ended_at))
+                })
+                .join(";"),
+            "absences" => self.absences.iter().map(|absence| &absence.kind).join(";"),
+            "violations" => self
+                .violations
+                .iter()
+                .map(|violation| violation.kind.as_str())
+                .join(";"),
+            _ => return None,
+        };
+        Some(field)
+    }
+}
+
+/// The JSON representation of a break within a working day
+#[derive(Serialize, Debug, PartialEq)]
+pub struct BreakRow {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub duration_seconds: i64,
+    /// Whether this is a gap between records, rather than a break that was
+    /// started explicitly
+    pub untracked: bool,
+    /// Whether the time was spent on a non-working project
+    pub non_working: bool,
+}
+
+impl Row for BreakRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "started_at",
+            "ended_at",
+            "duration_seconds",
+            "untracked",
+            "non_working",
+        ]
+    }
+}
+
+impl From<Gap> for BreakRow {
+    fn from(gap: Gap) -> Self {
+        Self {
+            started_at: gap.started_at,
+            ended_at: gap.ended_at,
+            duration_seconds: (gap.ended_at - gap.started_at).num_seconds(),
+            untracked: gap.kind == GapKind::Untracked,
+            non_working: gap.kind == GapKind::NonWorking,
+        }
+    }
+}
+
+/// The JSON representation of a breach of the break policy
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ViolationRow {
+    pub kind: ViolationKind,
+    pub actual_seconds: i64,
+    pub limit_seconds: i64,
+}
+
+impl Row for ViolationRow {
+    fn columns() -> &'static [&'static str] {
+        &["kind", "actual_seconds", "limit_seconds"]
+    }
+}
+
+impl From<Violation> for ViolationRow {
+    fn from(violation: Violation) -> Self {
+        Self {
+            kind: violation.kind,
+            actual_seconds: violation.actual.num_seconds(),
+            limit_seconds: violation.limit.num_seconds(),
+        }
+    }
+}
+
+/// The JSON representation of a holiday, vacation day, or other absence
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AbsenceRow {
+    pub id: String,
+    pub date: NaiveDate,
+    pub kind: String,
+    pub half_day: bool,
+    pub description: Option<String>,
+}
+
+impl Row for AbsenceRow {
+    fn columns() -> &'static [&'static str] {
+        &["id", "date", "kind", "half_day", "description"]
+    }
+}
+
+impl From<Absence> for AbsenceRow {
+    fn from(absence: Absence) -> Self {
+        Self {
+            id: absence.id,
+            date: absence.date,
+            kind: absence.kind.to_string(),
+            half_day: absence.half_day,
+            description: absence.description,
+        }
+    }
+}
+
+/// The JSON representation of the vacation days taken in a year
+#[derive(Serialize, Debug, PartialEq)]
+pub struct VacationRow {
+    pub year: i32,
+    pub allowance_days: f64,
+    pub taken_days: f64,
+    pub remaining_days: f64,
+}
+
+impl Row for VacationRow {
+    fn columns() -> &'static [&'static str] {
+        &["year", "allowance_days", "taken_days", "remaining_days"]
+    }
+}
+
+/// The JSON representation of the currently running record
+#[derive(Serialize, Debug, PartialEq)]
+pub struct StatusRow {
+    pub id: String,
+    pub project: String,
+    pub task: String,
+    pub started_at: DateTime<Utc>,
+    pub elapsed_seconds: i64,
+    pub today_seconds: i64,
+    pub tags: Vec<String>,
+}
+
+impl Row for StatusRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "project",
+            "task",
+            "started_at",
+            "elapsed_seconds",
+            "today_seconds",
+            "tags",
+        ]
+    }
+}
+
+/// The JSON representation of a project and the time spent on it
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ProjectRow {
+    pub name: String,
+    pub non_working: bool,
+    pub archived: bool,
+    pub total_seconds: i64,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl Row for ProjectRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "name",
+            "non_working",
+            "archived",
+            "total_seconds",
+            "last_used",
+        ]
+    }
+}
+
+impl From<ProjectUsage> for ProjectRow {
+    fn from(usage: ProjectUsage) -> Self {
+        Self {
+            name: usage.project.name,
+            non_working: usage.project.non_working,
+            archived: usage.project.archived,
+            total_seconds: usage.total.num_seconds(),
+            last_used: usage.last_used,
+        }
+    }
+}
+
+/// The JSON representation of a problem found by the check command
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ProblemRow {
+    pub kind: &'static str,
+    pub record_id: String,
+    pub other_record_id: Option<String>,
+    pub fixed: bool,
+}
+
+impl Row for ProblemRow {
+    fn columns() -> &'static [&'static str] {
+        &["kind", "record_id", "other_record_id", "fixed"]
+    }
+}
+
+impl ProblemRow {
+    pub fn new(problem: &Problem, fixed: bool) -> Self {
+        Self {
+            kind: problem.kind(),
+            record_id: problem.record().id.clone(),
+            other_record_id: problem.other_record().map(|record| record.id.clone()),
+            fixed,
+        }
+    }
+}
+
+/// The JSON representation of a client and its current default rate
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ClientRow {
+    pub name: String,
+    pub currency: String,
+    /// The hourly rate in hundredths of the currency
+    pub hourly_rate: Option<i64>,
+}
+
+impl Row for ClientRow {
+    fn columns() -> &'static [&'static str] {
+        &["name", "currency", "hourly_rate"]
+    }
+}
+
+/// The JSON representation of the time billed for a project at one rate
+#[derive(Serialize, Debug, PartialEq)]
+pub struct BillingRow {
+    pub client: String,
+    pub currency: String,
+    pub project: String,
+    /// The hourly rate in hundredths of the currency
+    pub hourly_rate: i64,
+    pub duration_seconds: i64,
+    /// The amount in hundredths of the currency
+    pub amount: i64,
+}
+
+impl Row for BillingRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "client",
+            "currency",
+            "project",
+            "hourly_rate",
+            "duration_seconds",
+            "amount",
+        ]
+    }
+}
+
+impl From<BillingLine> for BillingRow {
+    fn from(line: BillingLine) -> Self {
+        Self {
+            client: line.client,
+            currency: line.currency,
+            project: line.project,
+            hourly_rate: line.hourly_rate,
+            duration_seconds: line.duration.num_seconds(),
+            amount: line.amount,
+        }
+    }
+}
+
+/// The JSON representation of an invoice, without its line items
+#[derive(Serialize, Debug, PartialEq)]
+pub struct InvoiceRow {
+    pub number: String,
+    pub client: String,
+    pub issued_on: NaiveDate,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub currency: String,
+    pub duration_seconds: i64,
+    /// The total amount in hundredths of the currency
+    pub total: i64,
+}
+
+impl Row for InvoiceRow {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "number",
+            "client",
+            "issued_on",
+            "period_start",
+            "period_end",
+            "currency",
+            "duration_seconds",
+            "total",
+        ]
+    }
+}
+
+impl From<Invoice> for InvoiceRow {
+    fn from(invoice: Invoice) -> Self {
+        Self {
+            duration_seconds: invoice.duration().num_seconds(),
+            total: invoice.total(),
+            number: invoice.number,
+            client: invoice.client,
+            issued_on: invoice.issued_on,
+            period_start: invoice.period_start,
+            period_end: invoice.period_end,
+            currency: invoice.currency,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn record() -> Record {
+        Record {
+            id: "hello".into(),
+            task: "blub".into(),
+            project: "blob".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
+            started_at: Utc.with_ymd_and_hms(2024, 5, 12, 12, 0, 0).unwrap(),
+            ended_at: None,
+            tags: vec!["meeting".into()],
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn writes_records_as_json_array() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 12, 13, 0, 0).unwrap();
+        let mut buffer = Vec::new();
+        write_json(
+            &mut buffer,
+            OutputFormat::Json,
+            [RecordRow::new(record(), now)],
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            r#"[
+  {
+    "id": "hello",
+    "project": "blob",
+    "task": "blub",
+    "started_at": "2024-05-12T12:00:00Z",
+    "ended_at": null,
+    "duration_seconds": 3600,
+    "tags": [
+      "meeting"
+    ],
+    "notes": null,
+    "non_working": false
+  }
+]
+"#
+        );
+    }
+
+    #[test]
+    fn writes_records_as_ndjson() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 12, 13, 0, 0).unwrap();
+        let mut buffer = Vec::new();
+        write_json(
+            &mut buffer,
+            OutputFormat::Ndjson,
+            [RecordRow::new(record(), now), RecordRow::new(record(), now)],
+        )
+        .unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        let line = r#"{"id":"hello","project":"blob","task":"blub","started_at":"2024-05-12T12:00:00Z","ended_at":null,"duration_seconds":3600,"tags":["meeting"],"notes":null,"non_working":false}"#;
+        assert_eq!(result, format!("{line}\n{line}\n"));
+    }
+
+    #[test]
+    fn flattens_times_rows_in_tables() {
+        let at = |hour, min| Utc.with_ymd_and_hms(2024, 5, 12, hour, min, 0).unwrap();
+        let row = TimesRow {
+            date: NaiveDate::from_ymd_opt(2024, 5, 12).unwrap(),
+            started_at: Some(at(8, 0)),
+            ended_at: Some(at(17, 0)),
+            duration_seconds: 8 * 60 * 60,
+            breaks: vec![
+                BreakRow {
+                    started_at: at(12, 0),
+                    ended_at: at(12, 30),
+                    duration_seconds: 30 * 60,
+                    untracked: false,
+                    non_working: false,
+                },
+                BreakRow {
+                    started_at: at(15, 0),
+                    ended_at: at(15, 30),
+                    duration_seconds: 30 * 60,
+                    untracked: true,
+                    non_working: false,
+                },
+            ],
+            absences: vec![AbsenceRow {
+                id: "abcde".into(),
+                date: NaiveDate::from_ymd_opt(2024, 5, 12).unwrap(),
+                kind: "vacation".into(),
+                half_day: true,
+                description: None,
+            }],
+            violations: vec![ViolationRow {
+                kind: ViolationKind::MaxDailyExceeded,
+                actual_seconds: 11 * 60 * 60,
+                limit_seconds: 10 * 60 * 60,
+            }],
+        };
+        let mut buffer = Vec::new();
+        let columns = ["breaks", "absences", "violations"].map(String::from);
+        crate::print::write_table(&mut buffer, b'\t', &columns, [row]).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "breaks\tabsences\tviolations\n\
+            2024-05-12T12:00:00Z/2024-05-12T12:30:00Z;2024-05-12T15:00:00Z/2024-05-12T15:30:00Z\tvacation\tmax-daily-exceeded\n"
+        );
+    }
+
+    #[test]
+    fn aggregate_rows_include_period_boundaries() {
+        let aggregate = Aggregate {
+            period: Period::Week(NaiveDate::from_ymd_opt(2024, 12, 30).unwrap()),
+            project: "blob".into(),
+            non_working: false,
+            tasks: vec!["blub".into()],
+            duration: chrono::Duration::minutes(90),
+        };
+        let row = AggregateRow::from(aggregate);
+        assert_eq!(
+            serde_json::to_string(&row).unwrap(),
+            r#"{"period":"week","label":"2025-W01","start":"2024-12-30","end":"2025-01-06","project":"blob","tasks":["blub"],"duration_seconds":5400,"non_working":false}"#
+        );
+    }
+}
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+/// A row that can be written as part of a table, where each column
+/// corresponds to one of the serialized fields of the row.
+pub trait Row: Serialize {
+    /// The names of all of the available columns, in their default order
+    fn columns() -> &'static [&'static str];
+
+    /// Formats a single column of the row as a table field, for columns that
+    /// are nested structures in JSON.  Returns `None` to use the serialized
+    /// value of the column.
+    fn field(&self, _column: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Writes the rows as delimiter-separated values, with a header row.
+///
+/// If no columns are given, all of the row's columns are written.  Fields are
+/// quoted according to RFC 4180 where necessary, and lines are terminated with
+/// CRLF when the delimiter is a comma.
+pub fn write_table<T: Row>(
+    writer: &mut impl Write,
+    delimiter: u8,
+    columns: &[String],
+    rows: impl IntoIterator<Item = T>,
+) -> Result<()> {
+    let columns = if columns.is_empty() {
+        T::columns().iter().map(|&c| c.to_owned()).collect()
+    } else {
+        columns.to_vec()
+    };
+    for column in &columns {
+        if !T::columns().contains(&column.as_str()) {
+            bail!(
+                "unknown column {column:?} (available columns: {})",
+                T::columns().join(", ")
+            );
+        }
+    }
+
+    let terminator = match delimiter {
+        b',' => csv::Terminator::CRLF,
+        _ => csv::Terminator::Any(b'\n'),
+    };
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .terminator(terminator)
+        .from_writer(writer);
+
+    writer.write_record(&columns)?;
+    for row in rows {
+        let value = serde_json::to_value(&row)?;
+        writer.write_record(columns.iter().map(|column| {
+            row.field(column)
+                .unwrap_or_else(|| field_to_string(&value[column]))
+        }))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn field_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        Value::Array(values) if values.iter().all(Value::is_string) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct TestRow {
+        name: &'static str,
+        count: i64,
+        tags: Vec<&'static str>,
+        missing: Option<i64>,
+    }
+
+    impl Row for TestRow {
+        fn columns() -> &'static [&'static str] {
+            &["name", "count", "tags", "missing"]
+        }
+    }
+
+    fn rows() -> Vec<TestRow> {
+        vec![
+            TestRow {
+                name: "plain",
+                count: 1,
+                tags: vec!["a", "b"],
+                missing: None,
+            },
+            TestRow {
+                name: "with \"quotes\", and commas",
+                count: 2,
+                tags: vec![],
+                missing: Some(3),
+            },
+        ]
+    }
+
+    #[test]
+    fn writes_csv_with_rfc_4180_quoting() {
+        let mut buffer = Vec::new();
+        write_table(&mut buffer, b',', &[], rows()).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "name,count,tags,missing\r\nplain,1,\"a, b\",\r\n\"with \"\"quotes\"\", and commas\",2,,3\r\n"
+        );
+    }
+
+    #[test]
+    fn writes_tsv_with_selected_columns() {
+        let mut buffer = Vec::new();
+        write_table(&mut buffer, b'\t', &["count".into(), "name".into()], rows()).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            "count\tname\n1\tplain\n2\t\"with \"\"quotes\"\", and commas\"\n"
+        );
+    }
+
+    #[test]
+    fn writes_header_even_without_rows() {
+        let mut buffer = Vec::new();
+        write_table(&mut buffer, b',', &[], Vec::<TestRow>::new()).unwrap();
+        let result = String::from_utf8(buffer).unwrap();
+        assert_eq!(result, "name,count,tags,missing\r\n");
+    }
+
+    #[test]
+    fn rejects_unknown_columns() {
+        let mut buffer = Vec::new();
+        let result = write_table(&mut buffer, b',', &["nope".into()], rows());
+        assert!(result.is_err());
+    }
+}
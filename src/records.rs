@@ -1,20 +1,42 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::sync::LazyLock;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::LazyLock,
+};
 
 use anyhow::{anyhow, bail, Result};
 use chrono::{DateTime, Duration, Utc};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use sqids::{Sqids, SqidsBuilder};
 
 use db::{
-    delete_record, get_most_recent_record, get_project_for_record, insert_record, query_records,
-    query_records_all, set_record_end_timestamp, update_record, upsert_project, Conn,
+    add_record_tag, begin_transaction, commit_transaction, copy_record_tags, delete_project,
+    delete_record, delete_record_if_exists, delete_undone_journal_entries, find_project,
+    find_project_by_id, find_record, get_breaks, get_invoice_for_record, get_journal_entries,
+    get_journal_records, get_most_recent_record, get_open_record, get_project_for_record,
+    get_project_record_ids, get_projects, get_record, get_record_breaks, get_record_tags,
+    insert_break, insert_journal_entry, insert_journal_record, insert_record,
+    insert_record_with_id, move_record_breaks, query_records, query_records_all, remove_record_tag,
+    rollback_transaction, set_journal_entry_undone, set_record_billable, set_record_end_timestamp,
+    set_record_paused, update_record, upsert_project, upsert_tag,
 };
 
+mod absences;
+mod adjustments;
+mod clients;
 mod db;
+mod invoices;
+mod projects;
 mod schema;
 
-pub use db::establish_connection;
+pub use absences::{Absence, AbsenceKind, Absences, ImportSummary};
+pub use adjustments::{Adjustment, Adjustments};
+pub use clients::{Client, Clients, Rate, RateCard};
+pub use db::{establish_connection, Conn};
+pub use invoices::{Invoice, InvoiceLine, Invoices};
+pub use projects::{is_in_project, parent_projects, Project, ProjectUsage, Projects};
 
 static SQIDS: LazyLock<Sqids> = LazyLock::new(|| {
     SqidsBuilder::new()
@@ -26,11 +48,93 @@ static SQIDS: LazyLock<Sqids> = LazyLock::new(|| {
 
 pub struct Records<'a> {
     db: &'a mut Conn,
+    // while a journalled operation is running, this contains the state of
+    // each record touched by that operation from before it was first changed.
+    journal: Option<BTreeMap<i32, Option<Snapshot>>>,
 }
 
 impl<'a> Records<'a> {
     pub fn new(db: &'a mut Conn) -> Self {
-        Self { db }
+        Self { db, journal: None }
+    }
+
+    /// Runs the given changes in a single transaction, and records them in the
+    /// journal so that they can later be undone.  If the changes fail, nothing
+    /// is written to the database.
+    pub fn journalled<T>(
+        &mut self,
+        command: &str,
+        changes: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        begin_transaction(self.db)?;
+        self.journal = Some(BTreeMap::new());
+        let result = changes(self).and_then(|value| {
+            self.write_journal(command)?;
+            Ok(value)
+        });
+        self.journal = None;
+
+        match result {
+            Ok(value) => {
+                commit_transaction(self.db)?;
+                Ok(value)
+            }
+            Err(err) => {
+                rollback_transaction(self.db)?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Reverts the last `count` journalled operations, returning the
+    /// operations that were undone, most recent first.
+    pub fn undo(&mut self, count: usize) -> Result<Vec<Operation>> {
+        self.replay(count, false)
+    }
+
+    /// Reapplies the last `count` undone operations, returning the operations
+    /// that were redone, in the order they were redone.
+    pub fn redo(&mut self, count: usize) -> Result<Vec<Operation>> {
+        self.replay(count, true)
+    }
+
+    fn replay(&mut self, count: usize, redo: bool) -> Result<Vec<Operation>> {
+        begin_transaction(self.db)?;
+        let result = self.replay_entries(count, redo);
+        match result {
+            Ok(operations) => {
+                commit_transaction(self.db)?;
+                Ok(operations)
+            }
+            Err(err) => {
+                rollback_transaction(self.db)?;
+                Err(err)
+            }
+        }
+    }
+
+    fn replay_entries(&mut self, count: usize, redo: bool) -> Result<Vec<Operation>> {
+        let entries = get_journal_entries(self.db, redo, count.try_into()?)?;
+        let mut operations = Vec::new();
+        for entry in entries {
+            for record in get_journal_records(self.db, entry.id)? {
+                let state = match redo {
+                    true => record.after_state,
+                    false => record.before_state,
+                };
+                let snapshot = state
+                    .map(|state| serde_json::from_str::<Snapshot>(&state))
+                    .transpose()?;
+                self.restore_snapshot(record.record_id, snapshot)?;
+            }
+            set_journal_entry_undone(self.db, entry.id, !redo)?;
+            operations.push(Operation {
+                command: entry.command,
+                created_at: entry.created_at,
+            });
+        }
+
+        Ok(operations)
     }
 
     pub fn complete_last_record(
@@ -38,45 +142,104 @@ impl<'a> Records<'a> {
         end_date: DateTime<Utc>,
         start_date: Option<DateTime<Utc>>,
     ) -> Result<Vec<Record>> {
-        let last_record = get_most_recent_record(self.db, end_date)?;
+        match get_most_recent_record(self.db, end_date)? {
+            None => Ok(Vec::new()),
+            Some((record, project)) => self.interrupt_record(record, project, end_date, start_date),
+        }
+    }
+
+    /// Ends the record at `end_date` if it is still running at that point.
+    /// If `start_date` is given and the record was still running at that
+    /// point, a copy of the record is created that starts at `start_date` and
+    /// ends when the original record ended.  Returns all updated and created
+    /// records.
+    pub fn split_record(
+        &mut self,
+        record_id: &str,
+        end_date: DateTime<Utc>,
+        start_date: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Record>> {
+        let id = desqid(record_id)?;
+        let record = get_record(self.db, id)?;
+        let project = get_project_for_record(self.db, id)?;
+        self.interrupt_record(record, project, end_date, start_date)
+    }
+
+    fn interrupt_record(
+        &mut self,
+        record: db::Record,
+        project: db::Project,
+        end_date: DateTime<Utc>,
+        start_date: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Record>> {
         let mut records = Vec::new();
-        match last_record {
-            None => {}
-            Some((record, project)) => {
-                match record.ended_at.filter(|date| date <= &end_date) {
-                    Some(_) => {}
-                    None => {
-                        set_record_end_timestamp(self.db, record.id, end_date)?;
-                        records.push(Record {
-                            id: sqid(record.id),
-                            task: record.task.clone(),
-                            project: project.name.clone(),
-                            started_at: record.started_at,
-                            ended_at: Some(end_date),
-                        })
+        let tags = self.tags_for_record(record.id)?;
+        match record.ended_at.filter(|date| date <= &end_date) {
+            Some(_) => {}
+            None => {
+                self.touch(record.id)?;
+                set_record_end_timestamp(self.db, record.id, end_date)?;
+                records.push(Record {
+                    id: sqid(record.id),
+                    task: record.task.clone(),
+                    project: project.name.clone(),
+                    non_working: project.non_working,
+                    paused: record.paused,
+                    billable: record.billable.unwrap_or(project.billable),
+                    invoiced: record.invoice_id.is_some(),
+                    started_at: record.started_at,
+                    ended_at: Some(end_date),
+                    tags: tags.clone(),
+                    notes: record.notes.clone(),
+                })
+            }
+        }
+
+        if let Some(start_date) = start_date {
+            match record.ended_at.filter(|date| date <= &start_date) {
+                Some(_) => {}
+                None => {
+                    let new_record = insert_record(
+                        self.db,
+                        &record.task,
+                        project.id,
+                        start_date,
+                        record.ended_at,
+                        record.notes.as_deref(),
+                    )?;
+                    self.touch_new(new_record.id);
+                    copy_record_tags(self.db, record.id, new_record.id)?;
+                    if record.billable.is_some() {
+                        set_record_billable(self.db, new_record.id, record.billable)?;
                     }
-                }
+                    // breaks taken after the interruption now belong to the
+                    // second half of the record.
+                    move_record_breaks(self.db, record.id, new_record.id, start_date)?;
 
-                if let Some(start_date) = start_date {
-                    match record.ended_at.filter(|date| date <= &start_date) {
-                        Some(_) => {}
-                        None => {
-                            let record = insert_record(
-                                self.db,
-                                &record.task,
-                                project.id,
-                                start_date,
-                                record.ended_at,
-                            )?;
-                            records.push(Record {
-                                id: sqid(record.id),
-                                task: record.task,
-                                project: project.name,
-                                started_at: start_date,
-                                ended_at: record.ended_at,
-                            })
+                    // if the record was paused, the break comes after the
+                    // second half of the record, not after the first.
+                    if record.paused {
+                        self.touch(record.id)?;
+                        set_record_paused(self.db, record.id, false)?;
+                        set_record_paused(self.db, new_record.id, true)?;
+                        if let Some(first) = records.first_mut() {
+                            first.paused = false;
                         }
                     }
+
+                    records.push(Record {
+                        id: sqid(new_record.id),
+                        task: new_record.task,
+                        project: project.name,
+                        non_working: project.non_working,
+                        paused: record.paused,
+                        billable: record.billable.unwrap_or(project.billable),
+                        invoiced: false,
+                        started_at: start_date,
+                        ended_at: new_record.ended_at,
+                        tags,
+                        notes: new_record.notes,
+                    })
                 }
             }
         }
@@ -92,90 +255,539 @@ impl<'a> Records<'a> {
         end_date: Option<DateTime<Utc>>,
     ) -> Result<Record> {
         let project = upsert_project(self.db, project_name)?;
-        let record = insert_record(self.db, task_name, project.id, start_date, end_date)?;
+        let record = insert_record(self.db, task_name, project.id, start_date, end_date, None)?;
+        self.touch_new(record.id);
 
         Ok(Record {
             id: sqid(record.id),
             task: record.task,
             project: project.name,
+            non_working: project.non_working,
+            paused: record.paused,
+            billable: record.billable.unwrap_or(project.billable),
+            invoiced: record.invoice_id.is_some(),
             started_at: record.started_at,
             ended_at: record.ended_at,
+            tags: Vec::new(),
+            notes: record.notes,
         })
     }
 
+    /// Lists all records that overlap the given date range.  If any tags are
+    /// given, only records that have all of those tags are returned.
     pub fn list_records(
         &mut self,
         start_date: DateTime<Utc>,
         end_date: DateTime<Utc>,
+        tags: &[&str],
     ) -> Result<Vec<Record>> {
-        let records = query_records(self.db, start_date, end_date)?
-            .map(|row| {
-                row.map(|(record, project)| Record {
-                    id: sqid(record.id),
-                    task: record.task,
-                    project: project.name,
-                    started_at: record.started_at,
-                    ended_at: record.ended_at,
-                })
-                .map_err(|err| anyhow::anyhow!(err))
+        let rows =
+            query_records(self.db, start_date, end_date, tags)?.collect::<Result<Vec<_>, _>>()?;
+        let ids = rows.iter().map(|(record, _)| record.id).collect::<Vec<_>>();
+        let mut tags = tag_map(get_record_tags(self.db, Some(&ids))?);
+
+        Ok(rows
+            .into_iter()
+            .map(|(record, project)| Record {
+                id: sqid(record.id),
+                tags: tags.remove(&record.id).unwrap_or_default(),
+                task: record.task,
+                project: project.name,
+                non_working: project.non_working,
+                paused: record.paused,
+                billable: record.billable.unwrap_or(project.billable),
+                invoiced: record.invoice_id.is_some(),
+                started_at: record.started_at,
+                ended_at: record.ended_at,
+                notes: record.notes,
             })
-            .collect::<Result<Vec<Record>>>()?;
+            .collect())
+    }
 
-        Ok(records)
+    /// Returns the most recent record that started before the given date.
+    pub fn most_recent_record(&mut self, before: DateTime<Utc>) -> Result<Option<Record>> {
+        let Some((record, project)) = get_most_recent_record(self.db, before)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Record {
+            id: sqid(record.id),
+            tags: self.tags_for_record(record.id)?,
+            task: record.task,
+            project: project.name,
+            non_working: project.non_working,
+            paused: record.paused,
+            billable: record.billable.unwrap_or(project.billable),
+            invoiced: record.invoice_id.is_some(),
+            started_at: record.started_at,
+            ended_at: record.ended_at,
+            notes: record.notes,
+        }))
     }
 
+    /// Returns the currently running record (i.e. the most recently started
+    /// record without an end date), if there is one.
+    pub fn current_record(&mut self) -> Result<Option<Record>> {
+        let Some((record, project)) = get_open_record(self.db)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Record {
+            id: sqid(record.id),
+            tags: self.tags_for_record(record.id)?,
+            task: record.task,
+            project: project.name,
+            non_working: project.non_working,
+            paused: record.paused,
+            billable: record.billable.unwrap_or(project.billable),
+            invoiced: record.invoice_id.is_some(),
+            started_at: record.started_at,
+            ended_at: record.ended_at,
+            notes: record.notes,
+        }))
+    }
+
+    pub fn get_record(&mut self, record_id: &str) -> Result<Record> {
+        let id = desqid(record_id)?;
+        let record = get_record(self.db, id)?;
+        let project = get_project_for_record(self.db, record.id)?;
+
+        Ok(Record {
+            id: record_id.into(),
+            project: project.name,
+            non_working: project.non_working,
+            paused: record.paused,
+            billable: record.billable.unwrap_or(project.billable),
+            invoiced: record.invoice_id.is_some(),
+            tags: self.tags_for_record(record.id)?,
+            task: record.task,
+            started_at: record.started_at,
+            ended_at: record.ended_at,
+            notes: record.notes,
+        })
+    }
+
+    /// Updates the given fields of an existing record.  Fields that are `None`
+    /// are left unchanged, and `ended_at` can be set to `Some(None)` to reopen
+    /// a record that has already been stopped.
     pub fn update_record(
         &mut self,
         record_id: &str,
         started_at: Option<DateTime<Utc>>,
-        ended_at: Option<DateTime<Utc>>,
+        ended_at: Option<Option<DateTime<Utc>>>,
         task: Option<&str>,
         project_name: Option<&str>,
     ) -> Result<Record> {
         let id = desqid(record_id)?;
+        self.touch(id)?;
 
         let project = project_name
             .map(|project_name| upsert_project(self.db, project_name))
             .transpose()?;
 
-        let record = update_record(
-            self.db,
-            id,
-            started_at,
-            ended_at,
-            task,
-            project.map(|project| project.id),
-        )?;
+        let record =
+            if started_at.is_none() && ended_at.is_none() && task.is_none() && project.is_none() {
+                // diesel refuses to run an update without any changes
+                get_record(self.db, id)?
+            } else {
+                update_record(
+                    self.db,
+                    id,
+                    started_at,
+                    ended_at,
+                    task,
+                    project.map(|project| project.id),
+                    None,
+                )?
+            };
+        let project = get_project_for_record(self.db, record.id)?;
 
         Ok(Record {
             id: record_id.into(),
             started_at: record.started_at,
             ended_at: record.ended_at,
             task: record.task,
-            project: get_project_for_record(self.db, record.id)?.name,
+            project: project.name,
+            non_working: project.non_working,
+            paused: record.paused,
+            billable: record.billable.unwrap_or(project.billable),
+            invoiced: record.invoice_id.is_some(),
+            tags: self.tags_for_record(record.id)?,
+            notes: record.notes,
         })
     }
+
+    /// Lists the records, other than the given record, that would overlap it
+    /// if it ran from `started_at` until `ended_at`.  Open records are treated
+    /// as running indefinitely.
+    pub fn overlapping_records(
+        &mut self,
+        record_id: &str,
+        started_at: DateTime<Utc>,
+        ended_at: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Record>> {
+        self.all_records()?
+            .filter_ok(|record| {
+                record.id != record_id
+                    && ended_at.is_none_or(|end| record.started_at < end)
+                    && record.ended_at.is_none_or(|end| started_at < end)
+            })
+            .collect()
+    }
+
+    /// Trims the records that overlap the given record if it ran from
+    /// `started_at` until `ended_at`.  Earlier records are ended when the
+    /// record starts (and restarted when it ends, if they ran on after it),
+    /// and later records are started when the record ends.  Fails if a
+    /// record would be covered completely.  Returns all updated and created
+    /// records.
+    pub fn shift_neighbours(
+        &mut self,
+        record_id: &str,
+        started_at: DateTime<Utc>,
+        ended_at: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Record>> {
+        let mut updated = Vec::new();
+        for neighbour in self.overlapping_records(record_id, started_at, ended_at)? {
+            if neighbour.started_at < started_at {
+                updated.extend(self.split_record(&neighbour.id, started_at, ended_at)?);
+                continue;
+            }
+            match ended_at {
+                Some(end)
+                    if neighbour
+                        .ended_at
+                        .is_none_or(|neighbour_end| neighbour_end > end) =>
+                {
+                    updated.push(self.update_record(&neighbour.id, Some(end), None, None, None)?);
+                }
+                _ => bail!(
+                    "record {} ({}) would be completely covered by record {}",
+                    neighbour.id,
+                    neighbour.task,
+                    record_id
+                ),
+            }
+        }
+
+        Ok(updated)
+    }
+
+    /// Provides access to the projects, e.g. to check a project before adding
+    /// records to it.
+    pub fn projects(&mut self) -> Projects<'_> {
+        Projects::new(self.db)
+    }
+
+    /// Provides access to the clients, e.g. to look up rates for records.
+    pub fn clients(&mut self) -> Clients<'_> {
+        Clients::new(self.db)
+    }
+
+    /// Moves all records from one project into another, and then deletes the
+    /// first project.  Returns the number of records that were moved.
+    pub fn merge_projects(&mut self, project_name: &str, into_name: &str) -> Result<usize> {
+        if project_name == into_name {
+            bail!("cannot merge project {project_name} into itself");
+        }
+        let project = find_project(self.db, project_name)?
+            .ok_or(anyhow!("No project found with name {project_name}"))?;
+        let into = find_project(self.db, into_name)?
+            .ok_or(anyhow!("No project found with name {into_name}"))?;
+
+        if get_projects(self.db)?
+            .iter()
+            .any(|child| child.parent_id == Some(project.id))
+        {
+            bail!("project {project_name} has sub-projects, rename or merge those first");
+        }
+
+        let ids = get_project_record_ids(self.db, project.id)?;
+        for &id in &ids {
+            self.touch(id)?;
+            update_record(self.db, id, None, None, None, Some(into.id), None)?;
+        }
+        delete_project(self.db, project.id)?;
+
+        Ok(ids.len())
+    }
+
+    /// Replaces the notes of an existing record.  Passing `None` removes the
+    /// notes entirely.
+    pub fn set_notes(&mut self, record_id: &str, notes: Option<&str>) -> Result<()> {
+        let id = desqid(record_id)?;
+        self.touch(id)?;
+        update_record(self.db, id, None, None, None, None, Some(notes))?;
+        Ok(())
+    }
+
+    /// Overrides whether the record is billable.  Passing `None` uses the
+    /// project's setting again.
+    pub fn set_billable(&mut self, record_id: &str, billable: Option<bool>) -> Result<()> {
+        let id = desqid(record_id)?;
+        self.touch(id)?;
+        set_record_billable(self.db, id, billable)
+    }
+
+    /// Marks whether the record was stopped for a break.  If it was, the time
+    /// between the end of this record and the start of the next one is a
+    /// break, rather than time that wasn't tracked.
+    pub fn set_paused(&mut self, record_id: &str, paused: bool) -> Result<()> {
+        let id = desqid(record_id)?;
+        self.touch(id)?;
+        set_record_paused(self.db, id, paused)
+    }
+
+    /// Adds a break between the two dates, splitting the record that was
+    /// running at `start_date` around it.  The break is stored with the record
+    /// before it, so only the time between the two dates counts as a break.
+    /// Fails if nothing was being worked on when the break started.  Returns
+    /// the record before the break.
+    pub fn add_break(
+        &mut self,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Result<Record> {
+        let updated = self.complete_last_record(start_date, Some(end_date))?;
+        let record = match updated.into_iter().next() {
+            Some(record) => record,
+            // gaps of up to a minute aren't counted as breaks in `times`
+            // either, so those records still count as running.
+            None => self
+                .most_recent_record(start_date)?
+                .filter(|record| {
+                    record
+                        .ended_at
+                        .is_some_and(|ended_at| start_date - ended_at <= Duration::seconds(60))
+                })
+                .ok_or(anyhow!("no record is running at {start_date}"))?,
+        };
+        let id = desqid(&record.id)?;
+        self.touch(id)?;
+        insert_break(self.db, id, start_date, end_date)?;
+        Ok(record)
+    }
+
+    /// Lists the breaks that overlap the given date range, ordered by their
+    /// start.
+    pub fn list_breaks(
+        &mut self,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Result<Vec<Break>> {
+        Ok(get_breaks(self.db, start_date, end_date)?
+            .into_iter()
+            .map(Break::from)
+            .collect())
+    }
+
+    /// Adds tags to an existing record, creating the tags if necessary.  Tags
+    /// that are already attached to the record are ignored.
+    pub fn add_tags(&mut self, record_id: &str, tags: &[&str]) -> Result<()> {
+        let id = desqid(record_id)?;
+        self.touch(id)?;
+        for tag in tags {
+            let tag = upsert_tag(self.db, tag)?;
+            add_record_tag(self.db, id, tag.id)?;
+        }
+        Ok(())
+    }
+
+    /// Removes tags from an existing record.  Tags that are not attached to
+    /// the record are ignored.
+    pub fn remove_tags(&mut self, record_id: &str, tags: &[&str]) -> Result<()> {
+        let id = desqid(record_id)?;
+        self.touch(id)?;
+        for tag in tags {
+            remove_record_tag(self.db, id, tag)?;
+        }
+        Ok(())
+    }
+
     pub fn delete_record(&mut self, record_id: &str) -> Result<()> {
         let id = desqid(record_id)?;
+        self.touch(id)?;
 
         delete_record(self.db, id)?;
         Ok(())
     }
 
     pub fn all_records(&mut self) -> Result<impl Iterator<Item = Result<Record>> + '_> {
-        let records = query_records_all(self.db)?.map(|row| {
+        // the tags need to be loaded up front, as the connection is borrowed
+        // by the records iterator until it is finished.
+        let mut tags = tag_map(get_record_tags(self.db, None)?);
+        let records = query_records_all(self.db)?.map(move |row| {
             row.map(|(record, project)| Record {
                 id: sqid(record.id),
+                tags: tags.remove(&record.id).unwrap_or_default(),
                 task: record.task,
                 project: project.name,
+                non_working: project.non_working,
+                paused: record.paused,
+                billable: record.billable.unwrap_or(project.billable),
+                invoiced: record.invoice_id.is_some(),
                 started_at: record.started_at,
                 ended_at: record.ended_at,
+                notes: record.notes,
             })
             .map_err(|err| anyhow::anyhow!(err))
         });
 
         Ok(records)
     }
+
+    /// Stores the current state of the record in the journal before it gets
+    /// changed, if a journalled operation is running and the record has not
+    /// already been changed by that operation.  Fails if the record has been
+    /// invoiced, as it can no longer be changed.
+    fn touch(&mut self, record_id: i32) -> Result<()> {
+        self.ensure_not_invoiced(record_id)?;
+        if self
+            .journal
+            .as_ref()
+            .is_some_and(|journal| !journal.contains_key(&record_id))
+        {
+            let snapshot = self.snapshot(record_id)?;
+            if let Some(journal) = &mut self.journal {
+                journal.insert(record_id, snapshot);
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the record as having been created by the running journalled
+    /// operation, if there is one.
+    fn touch_new(&mut self, record_id: i32) {
+        if let Some(journal) = &mut self.journal {
+            journal.entry(record_id).or_insert(None);
+        }
+    }
+
+    fn write_journal(&mut self, command: &str) -> Result<()> {
+        let Some(journal) = self.journal.take() else {
+            return Ok(());
+        };
+
+        let mut changes = Vec::new();
+        for (record_id, before) in journal {
+            let after = self.snapshot(record_id)?;
+            if before != after {
+                changes.push((record_id, before, after));
+            }
+        }
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        // anything that was undone can't be redone once the records have
+        // been changed again.
+        delete_undone_journal_entries(self.db)?;
+        let entry = insert_journal_entry(self.db, command, Utc::now())?;
+        for (record_id, before, after) in changes {
+            insert_journal_record(
+                self.db,
+                entry.id,
+                record_id,
+                before
+                    .map(|s| serde_json::to_string(&s))
+                    .transpose()?
+                    .as_deref(),
+                after
+                    .map(|s| serde_json::to_string(&s))
+                    .transpose()?
+                    .as_deref(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn snapshot(&mut self, record_id: i32) -> Result<Option<Snapshot>> {
+        let Some(record) = find_record(self.db, record_id)? else {
+            return Ok(None);
+        };
+
+        let project = get_project_for_record(self.db, record.id)?;
+        Ok(Some(Snapshot {
+            project: project.name,
+            project_id: Some(project.id),
+            tags: self.tags_for_record(record.id)?,
+            task: record.task,
+            started_at: record.started_at,
+            ended_at: record.ended_at,
+            notes: record.notes,
+            paused: record.paused,
+            billable: record.billable,
+            breaks: get_record_breaks(self.db, record.id)?
+                .into_iter()
+                .map(Break::from)
+                .collect(),
+        }))
+    }
+
+    fn restore_snapshot(&mut self, record_id: i32, snapshot: Option<Snapshot>) -> Result<()> {
+        self.ensure_not_invoiced(record_id)?;
+        delete_record_if_exists(self.db, record_id)?;
+        if let Some(snapshot) = snapshot {
+            // merged projects are deleted, so those have to be recreated
+            let existing = match snapshot.project_id {
+                Some(project_id) => find_project_by_id(self.db, project_id)?,
+                None => None,
+            };
+            let project = match existing {
+                Some(project) => project,
+                None => upsert_project(self.db, &snapshot.project)?,
+            };
+            insert_record_with_id(
+                self.db,
+                record_id,
+                &snapshot.task,
+                project.id,
+                snapshot.started_at,
+                snapshot.ended_at,
+                snapshot.notes.as_deref(),
+            )?;
+            if snapshot.paused {
+                set_record_paused(self.db, record_id, true)?;
+            }
+            if snapshot.billable.is_some() {
+                set_record_billable(self.db, record_id, snapshot.billable)?;
+            }
+            for tag in &snapshot.tags {
+                let tag = upsert_tag(self.db, tag)?;
+                add_record_tag(self.db, record_id, tag.id)?;
+            }
+            for pause in &snapshot.breaks {
+                insert_break(self.db, record_id, pause.started_at, pause.ended_at)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ensure_not_invoiced(&mut self, record_id: i32) -> Result<()> {
+        if let Some(invoice) = get_invoice_for_record(self.db, record_id)? {
+            bail!(
+                "record {} has been billed on invoice {} and can no longer be changed",
+                sqid(record_id),
+                invoice.number
+            );
+        }
+        Ok(())
+    }
+
+    fn tags_for_record(&mut self, record_id: i32) -> Result<Vec<String>> {
+        Ok(get_record_tags(self.db, Some(&[record_id]))?
+            .into_iter()
+            .map(|(_, tag)| tag)
+            .collect())
+    }
+}
+
+fn tag_map(record_tags: Vec<(i32, String)>) -> HashMap<i32, Vec<String>> {
+    let mut tags = HashMap::<i32, Vec<String>>::new();
+    for (record_id, tag) in record_tags {
+        tags.entry(record_id).or_default().push(tag);
+    }
+    tags
 }
 
 fn sqid(record_id: i32) -> String {
@@ -200,19 +812,82 @@ fn desqid(sqid: &str) -> Result<i32> {
     Ok(as_i32)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Record {
     pub id: String,
     pub task: String,
     pub project: String,
+    /// Whether the record's project is marked as not counting as working time
+    pub non_working: bool,
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    /// Whether the record was stopped for a break, see [`Records::set_paused`]
+    pub paused: bool,
+    /// Whether the record should be billed, either because its project is
+    /// billable or because it was overridden for this record
+    pub billable: bool,
+    /// Whether the record has been billed on an invoice, in which case it can
+    /// no longer be changed
+    pub invoiced: bool,
 }
 
 impl Record {
     pub fn duration(&self, now: DateTime<Utc>) -> Duration {
         self.ended_at.unwrap_or(now) - self.started_at
     }
+
+    /// Whether the record belongs to the given project or one of its
+    /// sub-projects.
+    pub fn is_in_project(&self, project: &str) -> bool {
+        is_in_project(&self.project, project)
+    }
+}
+
+/// A break taken while working on a record, see [`Records::add_break`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Break {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+}
+
+impl From<db::Break> for Break {
+    fn from(value: db::Break) -> Self {
+        Self {
+            started_at: value.started_at,
+            ended_at: value.ended_at,
+        }
+    }
+}
+
+/// A journalled operation that has been undone or redone
+#[derive(Debug, PartialEq, Clone)]
+pub struct Operation {
+    pub command: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The full state of a record at a point in time, as stored in the journal
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Snapshot {
+    task: String,
+    project: String,
+    // the project may have been renamed since the snapshot was taken, so it
+    // is looked up by ID first.  Older journal entries only have the name.
+    #[serde(default)]
+    project_id: Option<i32>,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    notes: Option<String>,
+    tags: Vec<String>,
+    // journal entries written before breaks were tracked won't have this field
+    #[serde(default)]
+    paused: bool,
+    #[serde(default)]
+    billable: Option<bool>,
+    #[serde(default)]
+    breaks: Vec<Break>,
 }
 
 #[cfg(test)]
@@ -231,6 +906,23 @@ mod tests {
         Utc.with_ymd_and_hms(2024, 5, 12, hour, min, sec).unwrap()
     }
 
+    #[test]
+    fn record_tags_can_be_queried_for_many_records() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("hello, world", "proj", dt("10:00:00"), None)
+            .unwrap();
+        records.add_tags(&record.id, &["meeting"]).unwrap();
+
+        let record_id = desqid(&record.id).unwrap();
+        let ids = (record_id..record_id + 300_000).collect::<Vec<_>>();
+        assert_eq!(
+            get_record_tags(&mut conn, Some(&ids)).unwrap(),
+            [(record_id, "meeting".to_owned())]
+        );
+    }
+
     #[test]
     fn add_record_adds_a_new_record_and_task() {
         let mut conn = establish_connection(":memory:").unwrap();
@@ -243,7 +935,7 @@ mod tests {
         assert_eq!(record.ended_at, None);
 
         let record_list = records
-            .list_records(dt("00:00:00"), dt("23:59:59"))
+            .list_records(dt("00:00:00"), dt("23:59:59"), &[])
             .unwrap();
         assert_eq!(record_list.len(), 1);
         assert_eq!(record_list[0].task, "hello, world");
@@ -261,57 +953,148 @@ mod tests {
         assert_eq!(record.ended_at, Some(dt("11:00:00")));
 
         let record_list = records
-            .list_records(dt("00:00:00"), dt("23:59:59"))
+            .list_records(dt("00:00:00"), dt("23:59:59"), &[])
             .unwrap();
         assert_eq!(record_list.len(), 1);
         assert_eq!(record_list[0].task, "hello, world");
     }
 
     #[test]
-    fn complete_last_record_updates_most_recent_unfinished_record() {
+    fn shift_neighbours_trims_overlapping_records() {
         let mut conn = establish_connection(":memory:").unwrap();
         let mut records = Records::new(&mut conn);
         records
-            .add_record("hello, world", "proj", dt("10:00:00"), None)
+            .add_record("before", "proj", dt("09:00:00"), Some(dt("10:00:00")))
+            .unwrap();
+        let record = records
+            .add_record("edited", "proj", dt("10:00:00"), Some(dt("11:00:00")))
+            .unwrap();
+        records
+            .add_record("after", "proj", dt("11:00:00"), Some(dt("12:00:00")))
             .unwrap();
 
-        let recs = &records.complete_last_record(dt("11:00:00"), None).unwrap();
-        assert_eq!(recs[0].task, "hello, world");
-        assert_eq!(recs[0].ended_at, Some(dt("11:00:00")));
-        assert_eq!(recs.len(), 1);
+        let overlapping = records
+            .overlapping_records(&record.id, dt("09:30:00"), Some(dt("11:15:00")))
+            .unwrap();
+        assert_eq!(
+            overlapping.iter().map(|r| r.task.as_str()).collect_vec(),
+            vec!["before", "after"]
+        );
+
+        records
+            .shift_neighbours(&record.id, dt("09:30:00"), Some(dt("11:15:00")))
+            .unwrap();
+        let times = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &[])
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.task, r.started_at, r.ended_at))
+            .collect_vec();
+        assert_eq!(
+            times,
+            vec![
+                ("before".into(), dt("09:00:00"), Some(dt("09:30:00"))),
+                ("edited".into(), dt("10:00:00"), Some(dt("11:00:00"))),
+                ("after".into(), dt("11:15:00"), Some(dt("12:00:00"))),
+            ]
+        );
+
+        assert!(records
+            .shift_neighbours(&record.id, dt("09:30:00"), None)
+            .is_err());
     }
 
     #[test]
-    fn complete_last_record_does_not_update_records_after_the_given_date() {
+    fn merge_projects_moves_records_and_can_be_undone() {
         let mut conn = establish_connection(":memory:").unwrap();
         let mut records = Records::new(&mut conn);
-        records
-            .add_record("abc", "proj", dt("10:00:00"), None)
+        let record = records
+            .add_record("a", "clientx", dt("10:00:00"), Some(dt("11:00:00")))
             .unwrap();
         records
-            .add_record("def", "proj", dt("12:00:00"), None)
+            .add_record("b", "client-x", dt("11:00:00"), None)
             .unwrap();
 
-        let recs = &records.complete_last_record(dt("11:00:00"), None).unwrap();
-        assert_eq!(recs[0].task, "abc");
-        assert_eq!(recs[0].ended_at, Some(dt("11:00:00")));
-        assert_eq!(recs.len(), 1);
+        let moved = records
+            .journalled("project merge", |recs| {
+                recs.merge_projects("clientx", "client-x")
+            })
+            .unwrap();
+        assert_eq!(moved, 1);
+        assert_eq!(records.get_record(&record.id).unwrap().project, "client-x");
+        assert_eq!(records.projects().get("clientx").unwrap(), None);
+        assert!(records.merge_projects("client-x", "client-x").is_err());
+
+        records.undo(1).unwrap();
+        assert_eq!(records.get_record(&record.id).unwrap().project, "clientx");
     }
 
     #[test]
-    fn complete_last_record_ignores_dates_that_have_finished_before_the_given_date() {
+    fn undo_keeps_records_in_renamed_projects() {
         let mut conn = establish_connection(":memory:").unwrap();
         let mut records = Records::new(&mut conn);
+        let record = records
+            .journalled("go", |recs| {
+                recs.add_record("a", "clientx", dt("10:00:00"), Some(dt("11:00:00")))
+            })
+            .unwrap();
         records
-            .add_record("abc", "proj", dt("10:00:00"), Some(dt("11:00:00")))
+            .journalled("note", |recs| recs.set_notes(&record.id, Some("hi")))
             .unwrap();
+        records.projects().rename("clientx", "client-x").unwrap();
 
-        let record = records.complete_last_record(dt("11:30:00"), None).unwrap();
-        assert!(record.is_empty());
+        records.undo(1).unwrap();
+        let record = records.get_record(&record.id).unwrap();
+        assert_eq!(record.notes, None);
+        assert_eq!(record.project, "client-x");
+        assert_eq!(records.projects().get("clientx").unwrap(), None);
     }
 
     #[test]
-    fn complete_last_record_truncates_records_that_finish_after_the_given_date() {
+    fn complete_last_record_updates_most_recent_unfinished_record() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("hello, world", "proj", dt("10:00:00"), None)
+            .unwrap();
+
+        let recs = &records.complete_last_record(dt("11:00:00"), None).unwrap();
+        assert_eq!(recs[0].task, "hello, world");
+        assert_eq!(recs[0].ended_at, Some(dt("11:00:00")));
+        assert_eq!(recs.len(), 1);
+    }
+
+    #[test]
+    fn complete_last_record_does_not_update_records_after_the_given_date() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("abc", "proj", dt("10:00:00"), None)
+            .unwrap();
+        records
+            .add_record("def", "proj", dt("12:00:00"), None)
+            .unwrap();
+
+        let recs = &records.complete_last_record(dt("11:00:00"), None).unwrap();
+        assert_eq!(recs[0].task, "abc");
+        assert_eq!(recs[0].ended_at, Some(dt("11:00:00")));
+        assert_eq!(recs.len(), 1);
+    }
+
+    #[test]
+    fn complete_last_record_ignores_dates_that_have_finished_before_the_given_date() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("11:00:00")))
+            .unwrap();
+
+        let record = records.complete_last_record(dt("11:30:00"), None).unwrap();
+        assert!(record.is_empty());
+    }
+
+    #[test]
+    fn complete_last_record_truncates_records_that_finish_after_the_given_date() {
         let mut conn = establish_connection(":memory:").unwrap();
         let mut records = Records::new(&mut conn);
         records
@@ -391,14 +1174,281 @@ mod tests {
         assert_eq!(updated.project, "new proj");
     }
 
+    #[test]
+    fn update_record_can_move_record_to_existing_project() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("abc", "proj a", dt("09:00:00"), Some(dt("10:00:00")))
+            .unwrap();
+        let record = records
+            .add_record("def", "proj b", dt("10:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+
+        let updated = records
+            .update_record(&record.id, None, None, None, Some("proj a"))
+            .unwrap();
+
+        assert_eq!(updated.task, "def");
+        assert_eq!(updated.project, "proj a");
+        assert_eq!(updated.started_at, dt("10:00:00"));
+        assert_eq!(updated.ended_at, Some(dt("12:00:00")));
+
+        let record_list = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &[])
+            .unwrap();
+        assert_eq!(record_list[0].project, "proj a");
+        assert_eq!(record_list[1].project, "proj a");
+    }
+
+    #[test]
+    fn update_record_can_remove_end_time() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+
+        let updated = records
+            .update_record(&record.id, None, Some(None), None, None)
+            .unwrap();
+
+        assert_eq!(updated.started_at, dt("10:00:00"));
+        assert_eq!(updated.ended_at, None);
+    }
+
+    #[test]
+    fn update_record_leaves_end_time_alone_if_not_given() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+
+        let updated = records
+            .update_record(&record.id, None, None, Some("def"), None)
+            .unwrap();
+
+        assert_eq!(updated.task, "def");
+        assert_eq!(updated.ended_at, Some(dt("12:00:00")));
+    }
+
+    #[test]
+    fn tags_can_be_added_to_records() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+
+        records
+            .add_tags(&record.id, &["meeting", "billable", "meeting"])
+            .unwrap();
+
+        let record_list = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &[])
+            .unwrap();
+        assert_eq!(record_list[0].tags, vec!["billable", "meeting"]);
+    }
+
+    #[test]
+    fn tags_can_be_removed_from_records() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+        records
+            .add_tags(&record.id, &["meeting", "billable"])
+            .unwrap();
+
+        records
+            .remove_tags(&record.id, &["meeting", "unknown"])
+            .unwrap();
+
+        let updated = records
+            .update_record(&record.id, None, None, None, None)
+            .unwrap();
+        assert_eq!(updated.tags, vec!["billable"]);
+    }
+
+    #[test]
+    fn list_records_can_filter_by_tags() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let first = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("11:00:00")))
+            .unwrap();
+        let second = records
+            .add_record("def", "proj", dt("11:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+        records
+            .add_record("ghi", "proj", dt("12:00:00"), Some(dt("13:00:00")))
+            .unwrap();
+        records.add_tags(&first.id, &["meeting"]).unwrap();
+        records
+            .add_tags(&second.id, &["meeting", "billable"])
+            .unwrap();
+
+        let meetings = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &["meeting"])
+            .unwrap();
+        assert_eq!(meetings.len(), 2);
+
+        let billable_meetings = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &["meeting", "billable"])
+            .unwrap();
+        assert_eq!(billable_meetings.len(), 1);
+        assert_eq!(billable_meetings[0].task, "def");
+    }
+
+    #[test]
+    fn complete_last_record_copies_tags_when_splitting_records() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("15:00:00")))
+            .unwrap();
+        records.add_tags(&record.id, &["meeting"]).unwrap();
+
+        let split = records
+            .complete_last_record(dt("11:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+        assert_eq!(split[0].tags, vec!["meeting"]);
+        assert_eq!(split[1].tags, vec!["meeting"]);
+
+        let record_list = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &["meeting"])
+            .unwrap();
+        assert_eq!(record_list.len(), 2);
+    }
+
+    #[test]
+    fn deleting_a_record_removes_its_tags() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("15:00:00")))
+            .unwrap();
+        records.add_tags(&record.id, &["meeting"]).unwrap();
+
+        records.delete_record(&record.id).unwrap();
+
+        let record_list = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &["meeting"])
+            .unwrap();
+        assert!(record_list.is_empty());
+    }
+
+    #[test]
+    fn notes_can_be_set_and_removed() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+        assert_eq!(record.notes, None);
+
+        records
+            .set_notes(&record.id, Some("fixed the thing\nand another"))
+            .unwrap();
+        let record_list = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &[])
+            .unwrap();
+        assert_eq!(
+            record_list[0].notes.as_deref(),
+            Some("fixed the thing\nand another")
+        );
+
+        records.set_notes(&record.id, None).unwrap();
+        let record_list = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &[])
+            .unwrap();
+        assert_eq!(record_list[0].notes, None);
+    }
+
+    #[test]
+    fn complete_last_record_copies_notes_when_splitting_records() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("10:00:00"), Some(dt("15:00:00")))
+            .unwrap();
+        records.set_notes(&record.id, Some("notes")).unwrap();
+
+        let split = records
+            .complete_last_record(dt("11:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+        assert_eq!(split[0].notes.as_deref(), Some("notes"));
+        assert_eq!(split[1].notes.as_deref(), Some("notes"));
+    }
+
+    #[test]
+    fn current_record_returns_the_latest_open_record() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("abc", "proj", dt("09:00:00"), None)
+            .unwrap();
+        records
+            .add_record("def", "proj", dt("10:00:00"), None)
+            .unwrap();
+        records
+            .add_record("ghi", "proj", dt("11:00:00"), Some(dt("12:00:00")))
+            .unwrap();
+
+        let current = records.current_record().unwrap().unwrap();
+        assert_eq!(current.task, "def");
+        assert_eq!(current.ended_at, None);
+    }
+
+    #[test]
+    fn current_record_returns_none_if_all_records_are_finished() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("abc", "proj", dt("09:00:00"), Some(dt("10:00:00")))
+            .unwrap();
+
+        assert!(records.current_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn most_recent_record_ignores_records_after_the_given_date() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("09:00:00"), Some(dt("10:00:00")))
+            .unwrap();
+        records.add_tags(&record.id, &["meeting"]).unwrap();
+        records
+            .add_record("def", "proj", dt("12:00:00"), Some(dt("13:00:00")))
+            .unwrap();
+
+        let recent = records.most_recent_record(dt("11:00:00")).unwrap().unwrap();
+        assert_eq!(recent.id, record.id);
+        assert_eq!(recent.tags, vec!["meeting"]);
+
+        assert!(records
+            .most_recent_record(dt("08:00:00"))
+            .unwrap()
+            .is_none());
+    }
+
     #[test]
     fn duration_returns_duration_of_two_records() {
         let record = Record {
             task: "task".into(),
             project: "project".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
             id: "12345".into(),
             started_at: dt("10:00:00"),
             ended_at: Some(dt("12:00:00")),
+            tags: Vec::new(),
+            notes: None,
         };
 
         assert_eq!(
@@ -412,9 +1462,15 @@ mod tests {
         let record = Record {
             task: "task".into(),
             project: "project".into(),
+            non_working: false,
+            paused: false,
+            billable: true,
+            invoiced: false,
             id: "12345".into(),
             started_at: dt("10:00:00"),
             ended_at: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         assert_eq!(
@@ -422,4 +1478,249 @@ mod tests {
             Duration::seconds(5 * 60 * 60)
         );
     }
+
+    #[test]
+    fn undo_reverts_split_records() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let first = records
+            .journalled("go", |records| {
+                let record =
+                    records.add_record("abc", "proj", dt("09:00:00"), Some(dt("12:00:00")))?;
+                records.add_tags(&record.id, &["meeting"])?;
+                Ok(record)
+            })
+            .unwrap();
+        records
+            .journalled("go", |records| {
+                records.complete_last_record(dt("10:00:00"), Some(dt("11:00:00")))?;
+                records.add_record("def", "proj", dt("10:00:00"), Some(dt("11:00:00")))
+            })
+            .unwrap();
+        assert_eq!(
+            records
+                .list_records(dt("00:00:00"), dt("23:00:00"), &[])
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let undone = records.undo(1).unwrap();
+        assert_eq!(undone.len(), 1);
+        assert_eq!(undone[0].command, "go");
+
+        let listed = records
+            .list_records(dt("00:00:00"), dt("23:00:00"), &[])
+            .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, first.id);
+        assert_eq!(listed[0].ended_at, Some(dt("12:00:00")));
+        assert_eq!(listed[0].tags, vec!["meeting"]);
+    }
+
+    #[test]
+    fn undo_restores_deleted_records_with_the_same_id() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .journalled("go", |records| {
+                let record = records.add_record("abc", "proj", dt("09:00:00"), None)?;
+                records.set_notes(&record.id, Some("a note"))?;
+                Ok(record)
+            })
+            .unwrap();
+        records
+            .journalled("delete", |records| records.delete_record(&record.id))
+            .unwrap();
+        assert!(records.get_record(&record.id).is_err());
+
+        records.undo(1).unwrap();
+        let restored = records.get_record(&record.id).unwrap();
+        assert_eq!(restored.notes.as_deref(), Some("a note"));
+
+        records.undo(1).unwrap();
+        assert!(records.get_record(&record.id).is_err());
+        assert!(records.undo(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn redo_reapplies_undone_operations_until_something_changes() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .journalled("go", |records| {
+                records.add_record("abc", "proj", dt("09:00:00"), None)
+            })
+            .unwrap();
+        records
+            .journalled("edit", |records| {
+                records.update_record(&record.id, None, None, Some("def"), None)
+            })
+            .unwrap();
+
+        assert_eq!(records.undo(5).unwrap().len(), 2);
+        assert_eq!(records.redo(1).unwrap()[0].command, "go");
+        assert_eq!(records.get_record(&record.id).unwrap().task, "abc");
+
+        records
+            .journalled("edit", |records| {
+                records.update_record(&record.id, None, None, Some("ghi"), None)
+            })
+            .unwrap();
+        assert!(records.redo(1).unwrap().is_empty());
+        assert_eq!(records.get_record(&record.id).unwrap().task, "ghi");
+    }
+
+    #[test]
+    fn undo_and_redo_restore_paused_records() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .journalled("go", |records| {
+                records.add_record("abc", "proj", dt("09:00:00"), None)
+            })
+            .unwrap();
+        records
+            .journalled("pause", |records| {
+                records.complete_last_record(dt("12:00:00"), None)?;
+                records.set_paused(&record.id, true)
+            })
+            .unwrap();
+        let paused = records.get_record(&record.id).unwrap();
+        assert!(paused.paused);
+        assert_eq!(paused.ended_at, Some(dt("12:00:00")));
+
+        records.undo(1).unwrap();
+        let running = records.get_record(&record.id).unwrap();
+        assert!(!running.paused);
+        assert_eq!(running.ended_at, None);
+
+        records.redo(1).unwrap();
+        assert!(records.get_record(&record.id).unwrap().paused);
+    }
+
+    #[test]
+    fn add_break_only_applies_to_records_running_at_its_start() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let morning = records
+            .add_record("abc", "proj", dt("09:00:00"), Some(dt("11:59:30")))
+            .unwrap();
+        let afternoon = records
+            .add_record("def", "proj", dt("14:00:00"), Some(dt("16:00:00")))
+            .unwrap();
+
+        let paused = records.add_break(dt("12:00:00"), dt("12:30:00")).unwrap();
+        assert_eq!(paused.id, morning.id);
+        // the record isn't paused, or `unpause` would continue it
+        assert!(!records.get_record(&morning.id).unwrap().paused);
+
+        // nothing was tracked between the end of the break and 14:00
+        assert!(records.add_break(dt("13:00:00"), dt("13:30:00")).is_err());
+
+        let paused = records.add_break(dt("15:00:00"), dt("15:15:00")).unwrap();
+        assert_eq!(paused.id, afternoon.id);
+        assert_eq!(paused.ended_at, Some(dt("15:00:00")));
+
+        assert_eq!(
+            records.list_breaks(dt("00:00:00"), dt("23:59:59")).unwrap(),
+            [
+                Break {
+                    started_at: dt("12:00:00"),
+                    ended_at: dt("12:30:00"),
+                },
+                Break {
+                    started_at: dt("15:00:00"),
+                    ended_at: dt("15:15:00"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_break_itself_is_counted_as_intentional() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("abc", "proj", dt("09:00:00"), Some(dt("11:59:30")))
+            .unwrap();
+        records
+            .add_record("def", "proj", dt("14:00:00"), Some(dt("16:00:00")))
+            .unwrap();
+        records.add_break(dt("12:00:00"), dt("12:30:00")).unwrap();
+
+        let list = records
+            .list_records(dt("00:00:00"), dt("23:59:59"), &[])
+            .unwrap();
+        let breaks = records.list_breaks(dt("00:00:00"), dt("23:59:59")).unwrap();
+        let (_, gaps) = crate::breaks::find_gaps(&list, &breaks);
+        let rows = gaps
+            .into_iter()
+            .map(|gap| {
+                let row = crate::print::BreakRow::from(gap);
+                (row.started_at, row.ended_at, row.untracked)
+            })
+            .collect_vec();
+        assert_eq!(
+            rows,
+            [
+                (dt("12:00:00"), dt("12:30:00"), false),
+                (dt("12:30:00"), dt("14:00:00"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn breaks_follow_split_records_and_are_restored_by_undo() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .add_record("abc", "proj", dt("09:00:00"), Some(dt("17:00:00")))
+            .unwrap();
+        records.add_break(dt("15:00:00"), dt("15:30:00")).unwrap();
+
+        // the break comes after the second half of the split record
+        let split = records
+            .split_record(&record.id, dt("11:00:00"), Some(dt("11:30:00")))
+            .unwrap();
+        let second = &split[1];
+        records.delete_record(&record.id).unwrap();
+        assert_eq!(
+            records
+                .list_breaks(dt("00:00:00"), dt("23:59:59"))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        records
+            .journalled("delete", |records| records.delete_record(&second.id))
+            .unwrap();
+        assert!(records
+            .list_breaks(dt("00:00:00"), dt("23:59:59"))
+            .unwrap()
+            .is_empty());
+
+        records.undo(1).unwrap();
+        assert_eq!(
+            records.list_breaks(dt("00:00:00"), dt("23:59:59")).unwrap(),
+            [Break {
+                started_at: dt("15:00:00"),
+                ended_at: dt("15:30:00"),
+            }]
+        );
+    }
+
+    #[test]
+    fn failed_journalled_operations_are_rolled_back() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let result = records.journalled("go", |records| {
+            records.add_record("abc", "proj", dt("09:00:00"), None)?;
+            records.delete_record("zzzzz")
+        });
+        assert!(result.is_err());
+        assert!(records.current_record().unwrap().is_none());
+        assert!(records.undo(1).unwrap().is_empty());
+    }
 }
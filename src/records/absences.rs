@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{collections::BTreeSet, fmt::Display};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+
+use super::{
+    db::{
+        self, begin_transaction, commit_transaction, delete_absence, delete_absences_by_uid,
+        insert_absence, query_absences, rollback_transaction, Conn,
+    },
+    desqid, sqid,
+};
+use crate::parse::CalendarEvent;
+
+/// The reason for not working on a given day
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbsenceKind {
+    /// a public holiday
+    Holiday,
+    /// a vacation day, which counts towards the yearly allowance
+    Vacation,
+    /// a sick day
+    Sick,
+    /// a day taken off using overtime
+    CompTime,
+}
+
+impl AbsenceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AbsenceKind::Holiday => "holiday",
+            AbsenceKind::Vacation => "vacation",
+            AbsenceKind::Sick => "sick",
+            AbsenceKind::CompTime => "comp-time",
+        }
+    }
+
+    /// Whether the expected hours for the day are credited to the worker.
+    /// Comp-time is paid for out of the existing overtime balance, so the
+    /// hours are not credited.
+    pub fn is_credited(&self) -> bool {
+        !matches!(self, AbsenceKind::CompTime)
+    }
+
+    fn from_db(kind: &str) -> Result<Self> {
+        Ok(match kind {
+            "holiday" => AbsenceKind::Holiday,
+            "vacation" => AbsenceKind::Vacation,
+            "sick" => AbsenceKind::Sick,
+            "comp-time" => AbsenceKind::CompTime,
+            other => bail!("unknown absence kind {other}"),
+        })
+    }
+}
+
+impl Display for AbsenceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Absence {
+    pub id: String,
+    pub date: NaiveDate,
+    pub kind: AbsenceKind,
+    pub half_day: bool,
+    pub description: Option<String>,
+}
+
+impl Absence {
+    /// The number of days taken off
+    pub fn days(&self) -> f64 {
+        match self.half_day {
+            true => 0.5,
+            false => 1.0,
+        }
+    }
+
+    fn from_db(absence: db::Absence) -> Result<Self> {
+        Ok(Self {
+            id: sqid(absence.id),
+            date: absence.date,
+            kind: AbsenceKind::from_db(&absence.kind)?,
+            half_day: absence.half_day,
+            description: absence.description,
+        })
+    }
+}
+
+/// The number of holidays that were added, removed, or left alone by an import
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+pub struct Absences<'a> {
+    db: &'a mut Conn,
+}
+
+impl<'a> Absences<'a> {
+    pub fn new(db: &'a mut Conn) -> Self {
+        Self { db }
+    }
+
+    pub fn add_absence(
+        &mut self,
+        date: NaiveDate,
+        kind: AbsenceKind,
+        half_day: bool,
+        description: Option<&str>,
+    ) -> Result<Absence> {
+        Absence::from_db(insert_absence(
+            self.db,
+            date,
+            kind.as_str(),
+            half_day,
+            description,
+            None,
+        )?)
+    }
+
+    /// Imports the calendar events as holidays.  Each event replaces any
+    /// holidays previously imported from an event with the same UID, so
+    /// importing the same calendar twice leaves the absences unchanged.
+    pub fn import_holidays(&mut self, events: &[CalendarEvent]) -> Result<ImportSummary> {
+        begin_transaction(self.db)?;
+        match self.replace_holidays(events) {
+            Ok(summary) => {
+                commit_transaction(self.db)?;
+                Ok(summary)
+            }
+            Err(err) => {
+                rollback_transaction(self.db)?;
+                Err(err)
+            }
+        }
+    }
+
+    fn replace_holidays(&mut self, events: &[CalendarEvent]) -> Result<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        for event in events {
+            let previous = delete_absences_by_uid(self.db, &event.uid)?
+                .into_iter()
+                .collect::<BTreeSet<_>>();
+            let days = event.days().collect::<BTreeSet<_>>();
+            for day in &days {
+                insert_absence(
+                    self.db,
+                    *day,
+                    AbsenceKind::Holiday.as_str(),
+                    false,
+                    event.summary.as_deref(),
+                    Some(&event.uid),
+                )?;
+            }
+
+            summary.added += days.difference(&previous).count();
+            summary.removed += previous.difference(&days).count();
+            summary.unchanged += days.intersection(&previous).count();
+        }
+
+        Ok(summary)
+    }
+
+    /// Lists all absences between the two dates (inclusive), ordered by date.
+    pub fn list_absences(&mut self, from: NaiveDate, until: NaiveDate) -> Result<Vec<Absence>> {
+        query_absences(self.db, from, until)?
+            .into_iter()
+            .map(Absence::from_db)
+            .collect()
+    }
+
+    pub fn delete_absence(&mut self, absence_id: &str) -> Result<Absence> {
+        let id = desqid(absence_id)?;
+        Absence::from_db(delete_absence(self.db, id)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::establish_connection;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn absences_can_be_added_listed_and_deleted() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut absences = Absences::new(&mut conn);
+        let sick = absences
+            .add_absence(date(14), AbsenceKind::Sick, false, None)
+            .unwrap();
+        let comp_time = absences
+            .add_absence(date(13), AbsenceKind::CompTime, true, Some("dentist"))
+            .unwrap();
+        absences
+            .add_absence(date(20), AbsenceKind::Holiday, false, None)
+            .unwrap();
+
+        let listed = absences.list_absences(date(13), date(14)).unwrap();
+        assert_eq!(listed, vec![comp_time.clone(), sick.clone()]);
+        assert_eq!(listed[0].days(), 0.5);
+
+        assert_eq!(absences.delete_absence(&sick.id).unwrap(), sick);
+        assert_eq!(absences.list_absences(date(1), date(31)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn importing_holidays_twice_is_idempotent() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut absences = Absences::new(&mut conn);
+        let mut events = vec![CalendarEvent {
+            uid: "whitsun@example.com".into(),
+            summary: Some("Whitsun".into()),
+            start: date(19),
+            end: date(21),
+        }];
+
+        let summary = absences.import_holidays(&events).unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                added: 2,
+                removed: 0,
+                unchanged: 0
+            }
+        );
+        let summary = absences.import_holidays(&events).unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                added: 0,
+                removed: 0,
+                unchanged: 2
+            }
+        );
+
+        events[0].end = date(20);
+        let summary = absences.import_holidays(&events).unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                added: 0,
+                removed: 1,
+                unchanged: 1
+            }
+        );
+
+        let listed = absences.list_absences(date(1), date(31)).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].kind, AbsenceKind::Holiday);
+        assert_eq!(listed[0].description.as_deref(), Some("Whitsun"));
+    }
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+
+use super::{
+    db::{self, insert_adjustment, query_adjustments, Conn},
+    sqid,
+};
+
+/// A manual change to the overtime balance, e.g. when overtime is paid out
+#[derive(Debug, PartialEq, Clone)]
+pub struct Adjustment {
+    pub id: String,
+    pub date: NaiveDate,
+    pub duration: Duration,
+    pub description: Option<String>,
+}
+
+impl From<db::Adjustment> for Adjustment {
+    fn from(adjustment: db::Adjustment) -> Self {
+        Self {
+            id: sqid(adjustment.id),
+            date: adjustment.date,
+            duration: Duration::seconds(adjustment.duration_seconds.into()),
+            description: adjustment.description,
+        }
+    }
+}
+
+pub struct Adjustments<'a> {
+    db: &'a mut Conn,
+}
+
+impl<'a> Adjustments<'a> {
+    pub fn new(db: &'a mut Conn) -> Self {
+        Self { db }
+    }
+
+    pub fn add_adjustment(
+        &mut self,
+        date: NaiveDate,
+        duration: Duration,
+        description: Option<&str>,
+    ) -> Result<Adjustment> {
+        let seconds = duration.num_seconds().try_into()?;
+        Ok(insert_adjustment(self.db, date, seconds, description)?.into())
+    }
+
+    /// Lists all adjustments, ordered by date.
+    pub fn list_adjustments(&mut self) -> Result<Vec<Adjustment>> {
+        Ok(query_adjustments(self.db)?
+            .into_iter()
+            .map(Adjustment::from)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::establish_connection;
+
+    #[test]
+    fn adjustments_are_listed_by_date() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut adjustments = Adjustments::new(&mut conn);
+        let later = adjustments
+            .add_adjustment(
+                NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+                Duration::hours(-10),
+                Some("paid out"),
+            )
+            .unwrap();
+        let earlier = adjustments
+            .add_adjustment(
+                NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+                Duration::minutes(90),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(
+            adjustments.list_adjustments().unwrap(),
+            vec![earlier, later]
+        );
+    }
+}
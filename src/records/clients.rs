@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+
+use super::{
+    db::{
+        self, find_client, get_clients, get_projects, get_rates, insert_client, upsert_rate, Conn,
+    },
+    projects::parent_projects,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Client {
+    pub name: String,
+    /// The currency that this client is billed in, e.g. "EUR"
+    pub currency: String,
+}
+
+impl From<db::Client> for Client {
+    fn from(client: db::Client) -> Self {
+        Self {
+            name: client.name,
+            currency: client.currency,
+        }
+    }
+}
+
+/// An hourly rate that applies from a given date until the next rate starts
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Rate {
+    pub effective_from: NaiveDate,
+    /// The rate in hundredths of the client's currency (e.g. cents)
+    pub hourly_rate: i64,
+}
+
+impl From<db::Rate> for Rate {
+    fn from(rate: db::Rate) -> Self {
+        Self {
+            effective_from: rate.effective_from,
+            hourly_rate: rate.hourly_rate.into(),
+        }
+    }
+}
+
+/// The clients that each project is billed to, and the rates that they are
+/// billed at.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RateCard {
+    pub clients: HashMap<String, Client>,
+    /// The client for each project that has been assigned to one directly
+    pub project_clients: HashMap<String, String>,
+    /// The default rates for each client, sorted by date
+    pub client_rates: HashMap<String, Vec<Rate>>,
+    /// The rates for individual projects, sorted by date
+    pub project_rates: HashMap<String, Vec<Rate>>,
+}
+
+impl RateCard {
+    /// Returns the client that the project is billed to.  Sub-projects are
+    /// billed to the same client as their parent, unless they have been
+    /// assigned to a different client.
+    pub fn client(&self, project: &str) -> Option<&Client> {
+        std::iter::once(project)
+            .chain(parent_projects(project))
+            .find_map(|project| self.project_clients.get(project))
+            .and_then(|client| self.clients.get(client))
+    }
+
+    /// Returns the hourly rate for the project on the given date.  This is the
+    /// rate for the project itself, or for the closest parent project with a
+    /// rate, or otherwise the default rate of the project's client.
+    pub fn hourly_rate(&self, project: &str, date: NaiveDate) -> Option<i64> {
+        std::iter::once(project)
+            .chain(parent_projects(project))
+            .find_map(|project| effective_rate(self.project_rates.get(project)?, date))
+            .or_else(|| self.client_rate(&self.client(project)?.name, date))
+    }
+
+    /// Returns the client's default hourly rate on the given date.
+    pub fn client_rate(&self, client: &str, date: NaiveDate) -> Option<i64> {
+        effective_rate(self.client_rates.get(client)?, date)
+    }
+}
+
+/// Finds the rate that applies on the given date, if the rates are sorted by
+/// the date that they start from.
+fn effective_rate(rates: &[Rate], date: NaiveDate) -> Option<i64> {
+    rates
+        .iter()
+        .rev()
+        .find(|rate| rate.effective_from <= date)
+        .map(|rate| rate.hourly_rate)
+}
+
+pub struct Clients<'a> {
+    db: &'a mut Conn,
+}
+
+impl<'a> Clients<'a> {
+    pub fn new(db: &'a mut Conn) -> Self {
+        Self { db }
+    }
+
+    pub fn add(&mut self, client_name: &str, currency: &str) -> Result<Client> {
+        if find_client(self.db, client_name)?.is_some() {
+            bail!("A client with the name {client_name} already exists");
+        }
+        Ok(insert_client(self.db, client_name, currency)?.into())
+    }
+
+    /// Lists all clients in alphabetical order.
+    pub fn list(&mut self) -> Result<Vec<Client>> {
+        Ok(get_clients(self.db)?
+            .into_iter()
+            .map(Client::from)
+            .collect())
+    }
+
+    /// Sets the default hourly rate for all of the client's projects, from
+    /// the given date onwards.
+    pub fn set_rate(
+        &mut self,
+        client_name: &str,
+        hourly_rate: i64,
+        effective_from: NaiveDate,
+    ) -> Result<Rate> {
+        let client = find_client(self.db, client_name)?
+            .ok_or(anyhow!("No client found with name {client_name}"))?;
+        let hourly_rate = hourly_rate.try_into()?;
+        Ok(upsert_rate(self.db, Some(client.id), None, effective_from, hourly_rate)?.into())
+    }
+
+    pub fn rate_card(&mut self) -> Result<RateCard> {
+        let clients = get_clients(self.db)?;
+        let projects = get_projects(self.db)?;
+        let client_names = clients
+            .iter()
+            .map(|client| (client.id, client.name.clone()))
+            .collect::<HashMap<_, _>>();
+        let project_names = projects
+            .iter()
+            .map(|project| (project.id, project.name.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut card = RateCard {
+            project_clients: projects
+                .iter()
+                .filter_map(|project| {
+                    let client = client_names.get(&project.client_id?)?;
+                    Some((project.name.clone(), client.clone()))
+                })
+                .collect(),
+            clients: clients
+                .into_iter()
+                .map(|client| (client.name.clone(), client.into()))
+                .collect(),
+            ..RateCard::default()
+        };
+        // the rates are loaded in date order, so each list stays sorted
+        for rate in get_rates(self.db)? {
+            let rates = match (rate.client_id, rate.project_id) {
+                (Some(client_id), _) => client_names
+                    .get(&client_id)
+                    .map(|name| card.client_rates.entry(name.clone()).or_default()),
+                (_, Some(project_id)) => project_names
+                    .get(&project_id)
+                    .map(|name| card.project_rates.entry(name.clone()).or_default()),
+                (None, None) => None,
+            };
+            if let Some(rates) = rates {
+                rates.push(rate.into());
+            }
+        }
+
+        Ok(card)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{establish_connection, Projects, Records};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn rates_are_inherited_from_parent_projects_and_clients() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let start = "2024-05-12T10:00:00Z".parse().unwrap();
+        let mut records = Records::new(&mut conn);
+        records
+            .add_record("task", "acme/website/frontend", start, None)
+            .unwrap();
+        records
+            .add_record("task", "acme/support", start, None)
+            .unwrap();
+        records.add_record("task", "internal", start, None).unwrap();
+
+        let mut clients = Clients::new(&mut conn);
+        clients.add("ACME Corp", "EUR").unwrap();
+        assert!(clients.add("ACME Corp", "USD").is_err());
+        clients.set_rate("ACME Corp", 8000, date(1, 1)).unwrap();
+        clients.set_rate("ACME Corp", 9000, date(6, 1)).unwrap();
+
+        let mut projects = Projects::new(&mut conn);
+        projects.set_client("acme", Some("ACME Corp")).unwrap();
+        projects
+            .set_rate("acme/website", 12000, date(1, 1))
+            .unwrap();
+
+        let card = Clients::new(&mut conn).rate_card().unwrap();
+        assert_eq!(
+            card.client("acme/website/frontend")
+                .map(|c| c.name.as_str()),
+            Some("ACME Corp")
+        );
+        assert_eq!(card.client("internal"), None);
+        assert_eq!(
+            card.hourly_rate("acme/website/frontend", date(5, 1)),
+            Some(12000)
+        );
+        assert_eq!(card.hourly_rate("acme/support", date(5, 31)), Some(8000));
+        assert_eq!(card.hourly_rate("acme/support", date(6, 1)), Some(9000));
+        assert_eq!(card.hourly_rate("internal", date(6, 1)), None);
+        assert_eq!(card.client_rate("ACME Corp", date(5, 31)), Some(8000));
+    }
+}
@@ -4,6 +4,8 @@ use std::fs::create_dir_all;
 use std::path::Path;
 
 use anyhow::{bail, Result};
+use diesel::connection::SimpleConnection;
+use diesel::connection::TransactionManager;
 use diesel::upsert::excluded;
 use diesel::{prelude::*, sql_query};
 use diesel::{Connection, SqliteConnection};
@@ -40,12 +42,17 @@ pub fn establish_connection(database_url: impl AsRef<Path>) -> Result<Conn> {
 
     log::trace!("Connecting to SQLite DB at {database_url}");
     let mut conn = SqliteConnection::establish(&database_url)?;
-    sql_query(
+    // sql_query only runs the first statement it is given, so batch_execute is
+    // needed here to make sure all of the pragmas are applied.  Before that,
+    // only the application ID was set and foreign keys were never enforced, so
+    // existing databases may still contain rows that break them.  SQLite only
+    // checks foreign keys on changes, so those can still be opened, and the
+    // leftover record tags are cleaned up by a migration.
+    conn.batch_execute(
         "PRAGMA application_id = 0x9b34493a;
         PRAGMA foreign_keys = TRUE;
         PRAGMA ignore_check_constraints = FALSE;",
-    )
-    .execute(&mut conn)?;
+    )?;
     log::trace!("Connection to SQLite DB successful");
     run_migrations(&mut conn)?;
     Ok(Conn(conn))
@@ -53,6 +60,11 @@ pub fn establish_connection(database_url: impl AsRef<Path>) -> Result<Conn> {
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
+/// The maximum number of parameters that can be bound in a single statement
+/// in SQLite versions before 3.32.0.  Longer lists of IDs are split up into
+/// several queries.
+const MAX_BOUND_PARAMETERS: usize = 999;
+
 fn run_migrations(db: &mut SqliteConnection) -> Result<()> {
     let migrated = match db.run_pending_migrations(MIGRATIONS) {
         Ok(migrations) => migrations.len(),
@@ -76,6 +88,66 @@ fn run_migrations(db: &mut SqliteConnection) -> Result<()> {
 pub struct Project {
     pub id: i32,
     pub name: String,
+    pub non_working: bool,
+    pub archived: bool,
+    pub parent_id: Option<i32>,
+    pub client_id: Option<i32>,
+    pub billable: bool,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq, Clone)]
+#[diesel(table_name=super::schema::clients)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Client {
+    pub id: i32,
+    pub name: String,
+    pub currency: String,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq, Clone)]
+#[diesel(table_name=super::schema::rates)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Rate {
+    pub id: i32,
+    pub client_id: Option<i32>,
+    pub project_id: Option<i32>,
+    pub effective_from: chrono::NaiveDate,
+    pub hourly_rate: i32,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq, Clone)]
+#[diesel(table_name=super::schema::invoices)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Invoice {
+    pub id: i32,
+    pub number: String,
+    pub client_id: i32,
+    pub currency: String,
+    pub issued_on: chrono::NaiveDate,
+    pub period_start: chrono::NaiveDate,
+    pub period_end: chrono::NaiveDate,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq, Clone)]
+#[diesel(table_name=super::schema::invoice_lines)]
+#[diesel(belongs_to(Invoice))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct InvoiceLine {
+    pub id: i32,
+    pub invoice_id: i32,
+    pub project: String,
+    pub task: String,
+    pub hourly_rate: i32,
+    pub duration_seconds: i32,
+    pub amount: i32,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq, Clone)]
+#[diesel(table_name=super::schema::tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
 }
 
 #[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
@@ -88,6 +160,21 @@ pub struct Record {
     pub project_id: i32,
     pub started_at: chrono::DateTime<chrono::Utc>,
     pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub notes: Option<String>,
+    pub paused: bool,
+    pub billable: Option<bool>,
+    pub invoice_id: Option<i32>,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Associations, Debug, PartialEq)]
+#[diesel(table_name = super::schema::breaks)]
+#[diesel(belongs_to(Record))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Break {
+    pub id: i32,
+    pub record_id: i32,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(AsChangeset)]
@@ -97,24 +184,539 @@ struct RecordUpdate<'a> {
     pub task: Option<&'a str>,
     pub project_id: Option<i32>,
     pub started_at: Option<chrono::DateTime<chrono::Utc>>,
-    pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    // the outer option indicates whether the value should be updated, the inner
+    // option allows the value to be set to NULL.
+    pub ended_at: Option<Option<chrono::DateTime<chrono::Utc>>>,
+    pub notes: Option<Option<&'a str>>,
+}
+
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
+#[diesel(table_name = super::schema::journal)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct JournalEntry {
+    pub id: i32,
+    pub command: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub undone: bool,
+}
+
+#[derive(Queryable, Selectable, Associations, Debug, PartialEq)]
+#[diesel(table_name = super::schema::journal_records)]
+#[diesel(belongs_to(JournalEntry, foreign_key = journal_id))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct JournalRecord {
+    pub journal_id: i32,
+    pub record_id: i32,
+    pub before_state: Option<String>,
+    pub after_state: Option<String>,
+}
+
+type TransactionManagerFor = <SqliteConnection as Connection>::TransactionManager;
+
+pub fn begin_transaction(conn: &mut Conn) -> Result<()> {
+    TransactionManagerFor::begin_transaction(&mut conn.0)?;
+    Ok(())
+}
+
+pub fn commit_transaction(conn: &mut Conn) -> Result<()> {
+    TransactionManagerFor::commit_transaction(&mut conn.0)?;
+    Ok(())
+}
+
+pub fn rollback_transaction(conn: &mut Conn) -> Result<()> {
+    TransactionManagerFor::rollback_transaction(&mut conn.0)?;
+    Ok(())
 }
 
 pub fn upsert_project(conn: &mut Conn, project_name: &str) -> Result<Project> {
     use super::schema::projects;
 
+    let parent_id = upsert_parent_project(conn, project_name)?;
     let project = diesel::insert_into(projects::table)
-        .values(projects::name.eq(project_name))
+        .values((
+            projects::name.eq(project_name),
+            projects::parent_id.eq(parent_id),
+        ))
         .on_conflict(projects::name)
         .do_update()
-        // "updates" the project name to itself - this should be a no-op, but allows us to use
-        // the returning clause to fetch the project ID and other details.
-        .set(projects::name.eq(excluded(projects::name)))
+        // "updates" the project's parent to itself - this should be a no-op (unless the project
+        // was created before parents were tracked), but allows us to use the returning clause to
+        // fetch the project ID and other details.
+        .set(projects::parent_id.eq(excluded(projects::parent_id)))
         .returning(Project::as_returning())
         .get_result(&mut conn.0)?;
     Ok(project)
 }
 
+/// Creates the parent of a sub-project (e.g. "client/project" for
+/// "client/project/frontend") if it doesn't exist yet, and returns its ID.
+fn upsert_parent_project(conn: &mut Conn, project_name: &str) -> Result<Option<i32>> {
+    if project_name.split('/').any(|part| part.trim().is_empty()) {
+        bail!("Invalid project name {project_name:?}");
+    }
+    match project_name.rsplit_once('/') {
+        Some((parent, _)) => Ok(Some(upsert_project(conn, parent)?.id)),
+        None => Ok(None),
+    }
+}
+
+pub fn set_project_non_working(
+    conn: &mut Conn,
+    project_name: &str,
+    non_working: bool,
+) -> Result<Project> {
+    use super::schema::projects;
+
+    let project = diesel::update(projects::table.filter(projects::name.eq(project_name)))
+        .set(projects::non_working.eq(non_working))
+        .returning(Project::as_returning())
+        .get_result(&mut conn.0)
+        .optional()?;
+    match project {
+        Some(project) => Ok(project),
+        None => bail!("No project found with name {project_name}"),
+    }
+}
+
+pub fn set_project_archived(
+    conn: &mut Conn,
+    project_name: &str,
+    archived: bool,
+) -> Result<Project> {
+    use super::schema::projects;
+
+    let project = diesel::update(projects::table.filter(projects::name.eq(project_name)))
+        .set(projects::archived.eq(archived))
+        .returning(Project::as_returning())
+        .get_result(&mut conn.0)
+        .optional()?;
+    match project {
+        Some(project) => Ok(project),
+        None => bail!("No project found with name {project_name}"),
+    }
+}
+
+pub fn rename_project(conn: &mut Conn, project_name: &str, new_name: &str) -> Result<Project> {
+    use super::schema::projects;
+
+    if find_project(conn, new_name)?.is_some() {
+        bail!("A project with the name {new_name} already exists");
+    }
+    let prefix = format!("{project_name}/");
+    if new_name.starts_with(&prefix) {
+        bail!("Cannot move project {project_name} into its own sub-project");
+    }
+    let Some(project) = find_project(conn, project_name)? else {
+        bail!("No project found with name {project_name}");
+    };
+
+    let parent_id = upsert_parent_project(conn, new_name)?;
+    let renamed = diesel::update(projects::table.filter(projects::id.eq(project.id)))
+        .set((
+            projects::name.eq(new_name),
+            projects::parent_id.eq(parent_id),
+        ))
+        .returning(Project::as_returning())
+        .get_result(&mut conn.0)?;
+
+    // sub-projects keep their parents, but their names need to be updated
+    for child in get_projects(conn)? {
+        if let Some(rest) = child.name.strip_prefix(&prefix) {
+            diesel::update(projects::table.filter(projects::id.eq(child.id)))
+                .set(projects::name.eq(format!("{new_name}/{rest}")))
+                .execute(&mut conn.0)?;
+        }
+    }
+
+    Ok(renamed)
+}
+
+pub fn find_project(conn: &mut Conn, project_name: &str) -> Result<Option<Project>> {
+    use super::schema::projects;
+
+    let project = projects::table
+        .filter(projects::name.eq(project_name))
+        .select(Project::as_select())
+        .get_result(&mut conn.0)
+        .optional()?;
+    Ok(project)
+}
+
+pub fn find_project_by_id(conn: &mut Conn, project_id: i32) -> Result<Option<Project>> {
+    use super::schema::projects;
+
+    let project = projects::table
+        .filter(projects::id.eq(project_id))
+        .select(Project::as_select())
+        .get_result(&mut conn.0)
+        .optional()?;
+    Ok(project)
+}
+
+pub fn get_projects(conn: &mut Conn) -> Result<Vec<Project>> {
+    use super::schema::projects;
+
+    let projects = projects::table
+        .order(projects::name)
+        .select(Project::as_select())
+        .load(&mut conn.0)?;
+    Ok(projects)
+}
+
+pub fn delete_project(conn: &mut Conn, project_id: i32) -> Result<()> {
+    use super::schema::projects;
+    use super::schema::rates;
+
+    diesel::delete(rates::table.filter(rates::project_id.eq(project_id))).execute(&mut conn.0)?;
+    diesel::delete(projects::table.filter(projects::id.eq(project_id))).execute(&mut conn.0)?;
+    Ok(())
+}
+
+pub fn get_project_record_ids(conn: &mut Conn, project_id: i32) -> Result<Vec<i32>> {
+    use super::schema::records;
+
+    let ids = records::table
+        .filter(records::project_id.eq(project_id))
+        .order(records::started_at)
+        .select(records::id)
+        .load(&mut conn.0)?;
+    Ok(ids)
+}
+
+pub fn set_project_billable(
+    conn: &mut Conn,
+    project_name: &str,
+    billable: bool,
+) -> Result<Project> {
+    use super::schema::projects;
+
+    let project = diesel::update(projects::table.filter(projects::name.eq(project_name)))
+        .set(projects::billable.eq(billable))
+        .returning(Project::as_returning())
+        .get_result(&mut conn.0)
+        .optional()?;
+    match project {
+        Some(project) => Ok(project),
+        None => bail!("No project found with name {project_name}"),
+    }
+}
+
+pub fn set_project_client(
+    conn: &mut Conn,
+    project_name: &str,
+    client_id: Option<i32>,
+) -> Result<Project> {
+    use super::schema::projects;
+
+    let project = diesel::update(projects::table.filter(projects::name.eq(project_name)))
+        .set(projects::client_id.eq(client_id))
+        .returning(Project::as_returning())
+        .get_result(&mut conn.0)
+        .optional()?;
+    match project {
+        Some(project) => Ok(project),
+        None => bail!("No project found with name {project_name}"),
+    }
+}
+
+pub fn insert_client(conn: &mut Conn, client_name: &str, currency: &str) -> Result<Client> {
+    use super::schema::clients;
+
+    let client = diesel::insert_into(clients::table)
+        .values((
+            clients::name.eq(client_name),
+            clients::currency.eq(currency),
+        ))
+        .returning(Client::as_returning())
+        .get_result(&mut conn.0)?;
+    Ok(client)
+}
+
+pub fn find_client(conn: &mut Conn, client_name: &str) -> Result<Option<Client>> {
+    use super::schema::clients;
+
+    let client = clients::table
+        .filter(clients::name.eq(client_name))
+        .select(Client::as_select())
+        .get_result(&mut conn.0)
+        .optional()?;
+    Ok(client)
+}
+
+pub fn get_clients(conn: &mut Conn) -> Result<Vec<Client>> {
+    use super::schema::clients;
+
+    let clients = clients::table
+        .order(clients::name)
+        .select(Client::as_select())
+        .load(&mut conn.0)?;
+    Ok(clients)
+}
+
+/// Sets the rate for either a client or a project from the given date,
+/// replacing any rate that was previously set for the same date.
+pub fn upsert_rate(
+    conn: &mut Conn,
+    client_id: Option<i32>,
+    project_id: Option<i32>,
+    effective_from: chrono::NaiveDate,
+    hourly_rate: i32,
+) -> Result<Rate> {
+    use super::schema::rates;
+
+    diesel::delete(
+        rates::table
+            .filter(rates::client_id.is(client_id))
+            .filter(rates::project_id.is(project_id))
+            .filter(rates::effective_from.eq(effective_from)),
+    )
+    .execute(&mut conn.0)?;
+    let rate = diesel::insert_into(rates::table)
+        .values((
+            rates::client_id.eq(client_id),
+            rates::project_id.eq(project_id),
+            rates::effective_from.eq(effective_from),
+            rates::hourly_rate.eq(hourly_rate),
+        ))
+        .returning(Rate::as_returning())
+        .get_result(&mut conn.0)?;
+    Ok(rate)
+}
+
+pub fn get_rates(conn: &mut Conn) -> Result<Vec<Rate>> {
+    use super::schema::rates;
+
+    let rates = rates::table
+        .order(rates::effective_from)
+        .select(Rate::as_select())
+        .load(&mut conn.0)?;
+    Ok(rates)
+}
+
+pub fn insert_invoice(
+    conn: &mut Conn,
+    number: &str,
+    client: &Client,
+    issued_on: chrono::NaiveDate,
+    period_start: chrono::NaiveDate,
+    period_end: chrono::NaiveDate,
+) -> Result<Invoice> {
+    use super::schema::invoices;
+
+    let invoice = diesel::insert_into(invoices::table)
+        .values((
+            invoices::number.eq(number),
+            invoices::client_id.eq(client.id),
+            invoices::currency.eq(&client.currency),
+            invoices::issued_on.eq(issued_on),
+            invoices::period_start.eq(period_start),
+            invoices::period_end.eq(period_end),
+        ))
+        .returning(Invoice::as_returning())
+        .get_result(&mut conn.0)?;
+    Ok(invoice)
+}
+
+pub fn insert_invoice_line(
+    conn: &mut Conn,
+    invoice_id: i32,
+    project: &str,
+    task: &str,
+    hourly_rate: i32,
+    duration_seconds: i32,
+    amount: i32,
+) -> Result<InvoiceLine> {
+    use super::schema::invoice_lines;
+
+    let line = diesel::insert_into(invoice_lines::table)
+        .values((
+            invoice_lines::invoice_id.eq(invoice_id),
+            invoice_lines::project.eq(project),
+            invoice_lines::task.eq(task),
+            invoice_lines::hourly_rate.eq(hourly_rate),
+            invoice_lines::duration_seconds.eq(duration_seconds),
+            invoice_lines::amount.eq(amount),
+        ))
+        .returning(InvoiceLine::as_returning())
+        .get_result(&mut conn.0)?;
+    Ok(line)
+}
+
+/// Returns all invoices along with their clients, oldest first.
+pub fn get_invoices(conn: &mut Conn) -> Result<Vec<(Invoice, Client)>> {
+    use super::schema::clients;
+    use super::schema::invoices;
+
+    let invoices = invoices::table
+        .inner_join(clients::table)
+        .order(invoices::id)
+        .select((Invoice::as_select(), Client::as_select()))
+        .load(&mut conn.0)?;
+    Ok(invoices)
+}
+
+pub fn find_invoice(conn: &mut Conn, number: &str) -> Result<Option<(Invoice, Client)>> {
+    use super::schema::clients;
+    use super::schema::invoices;
+
+    let invoice = invoices::table
+        .inner_join(clients::table)
+        .filter(invoices::number.eq(number))
+        .select((Invoice::as_select(), Client::as_select()))
+        .get_result(&mut conn.0)
+        .optional()?;
+    Ok(invoice)
+}
+
+pub fn get_invoice_lines(conn: &mut Conn, invoice_id: i32) -> Result<Vec<InvoiceLine>> {
+    use super::schema::invoice_lines;
+
+    let lines = invoice_lines::table
+        .filter(invoice_lines::invoice_id.eq(invoice_id))
+        .order(invoice_lines::id)
+        .select(InvoiceLine::as_select())
+        .load(&mut conn.0)?;
+    Ok(lines)
+}
+
+/// Returns the invoice that the record has been billed on, if any.
+pub fn get_invoice_for_record(conn: &mut Conn, record_id: i32) -> Result<Option<Invoice>> {
+    use super::schema::invoices;
+    use super::schema::records;
+
+    let invoice = records::table
+        .inner_join(invoices::table)
+        .filter(records::id.eq(record_id))
+        .select(Invoice::as_select())
+        .get_result(&mut conn.0)
+        .optional()?;
+    Ok(invoice)
+}
+
+/// Marks the records as billed on the given invoice, returning the number of
+/// records that were updated.  Records that are already on an invoice are
+/// left unchanged.
+pub fn set_records_invoice(conn: &mut Conn, record_ids: &[i32], invoice_id: i32) -> Result<usize> {
+    use super::schema::records;
+
+    let mut count = 0;
+    for chunk in record_ids.chunks(MAX_BOUND_PARAMETERS) {
+        count += diesel::update(
+            records::table
+                .filter(records::id.eq_any(chunk))
+                .filter(records::invoice_id.is_null()),
+        )
+        .set(records::invoice_id.eq(invoice_id))
+        .execute(&mut conn.0)?;
+    }
+    Ok(count)
+}
+
+/// Deletes an invoice and its line items, and marks the records that were
+/// billed on it as not invoiced.  Returns the number of records that were
+/// released.
+pub fn delete_invoice(conn: &mut Conn, invoice_id: i32) -> Result<usize> {
+    use super::schema::invoice_lines;
+    use super::schema::invoices;
+    use super::schema::records;
+
+    let count = diesel::update(records::table.filter(records::invoice_id.eq(invoice_id)))
+        .set(records::invoice_id.eq(None::<i32>))
+        .execute(&mut conn.0)?;
+    diesel::delete(invoice_lines::table.filter(invoice_lines::invoice_id.eq(invoice_id)))
+        .execute(&mut conn.0)?;
+    diesel::delete(invoices::table.filter(invoices::id.eq(invoice_id))).execute(&mut conn.0)?;
+    Ok(count)
+}
+
+pub fn upsert_tag(conn: &mut Conn, tag_name: &str) -> Result<Tag> {
+    use super::schema::tags;
+
+    let tag = diesel::insert_into(tags::table)
+        .values(tags::name.eq(tag_name))
+        .on_conflict(tags::name)
+        .do_update()
+        // see upsert_project for why this no-op update is needed
+        .set(tags::name.eq(excluded(tags::name)))
+        .returning(Tag::as_returning())
+        .get_result(&mut conn.0)?;
+    Ok(tag)
+}
+
+pub fn add_record_tag(conn: &mut Conn, record_id: i32, tag_id: i32) -> Result<()> {
+    use super::schema::records_tags;
+
+    diesel::insert_into(records_tags::table)
+        .values((
+            records_tags::record_id.eq(record_id),
+            records_tags::tag_id.eq(tag_id),
+        ))
+        .on_conflict_do_nothing()
+        .execute(&mut conn.0)?;
+    Ok(())
+}
+
+pub fn remove_record_tag(conn: &mut Conn, record_id: i32, tag_name: &str) -> Result<()> {
+    use super::schema::records_tags;
+    use super::schema::tags;
+
+    diesel::delete(
+        records_tags::table
+            .filter(records_tags::record_id.eq(record_id))
+            .filter(
+                records_tags::tag_id
+                    .eq_any(tags::table.filter(tags::name.eq(tag_name)).select(tags::id)),
+            ),
+    )
+    .execute(&mut conn.0)?;
+    Ok(())
+}
+
+pub fn copy_record_tags(conn: &mut Conn, from_record_id: i32, to_record_id: i32) -> Result<()> {
+    use super::schema::records_tags;
+
+    diesel::insert_into(records_tags::table)
+        .values(
+            records_tags::table
+                .filter(records_tags::record_id.eq(from_record_id))
+                .select((
+                    to_record_id.into_sql::<diesel::sql_types::Integer>(),
+                    records_tags::tag_id,
+                )),
+        )
+        .into_columns((records_tags::record_id, records_tags::tag_id))
+        .execute(&mut conn.0)?;
+    Ok(())
+}
+
+/// Returns pairs of record IDs and tag names for the given records.  If no
+/// record IDs are given, all tagged records are returned.
+pub fn get_record_tags(conn: &mut Conn, record_ids: Option<&[i32]>) -> Result<Vec<(i32, String)>> {
+    use super::schema::records_tags;
+    use super::schema::tags;
+
+    let query = || {
+        records_tags::table
+            .inner_join(tags::table)
+            .select((records_tags::record_id, tags::name))
+            .order(tags::name)
+            .into_boxed()
+    };
+    let Some(record_ids) = record_ids else {
+        return Ok(query().load(&mut conn.0)?);
+    };
+
+    let mut record_tags = Vec::new();
+    for chunk in record_ids.chunks(MAX_BOUND_PARAMETERS) {
+        record_tags.extend(
+            query()
+                .filter(records_tags::record_id.eq_any(chunk))
+                .load::<(i32, String)>(&mut conn.0)?,
+        );
+    }
+    Ok(record_tags)
+}
+
 pub fn get_most_recent_record(
     conn: &mut Conn,
     before: chrono::DateTime<chrono::Utc>,
@@ -130,6 +732,21 @@ pub fn get_most_recent_record(
         .optional()?)
 }
 
+/// Returns the most recently started record that has no end date.  This uses
+/// the partial index on open records, so it should be fast regardless of the
+/// number of records in the database.
+pub fn get_open_record(conn: &mut Conn) -> Result<Option<RecordTuple>> {
+    use super::schema::projects;
+    use super::schema::records;
+
+    Ok(records::table
+        .inner_join(projects::table)
+        .filter(records::ended_at.is_null())
+        .order(records::started_at.desc())
+        .first(&mut conn.0)
+        .optional()?)
+}
+
 pub fn set_record_end_timestamp(
     conn: &mut Conn,
     record_id: i32,
@@ -151,6 +768,7 @@ pub fn insert_record(
     project_id: i32,
     start_date: chrono::DateTime<chrono::Utc>,
     end_date: Option<chrono::DateTime<chrono::Utc>>,
+    notes: Option<&str>,
 ) -> Result<Record> {
     use super::schema::records;
     let record = diesel::insert_into(records::table)
@@ -159,19 +777,46 @@ pub fn insert_record(
             records::task.eq(task),
             records::started_at.eq(start_date),
             records::ended_at.eq(end_date),
+            records::notes.eq(notes),
         ))
         .returning(Record::as_returning())
         .get_result(&mut conn.0)?;
     Ok(record)
 }
 
+/// Inserts a record with a fixed ID.  This is used to restore records that
+/// have previously been deleted, so that they keep their original ID.
+pub fn insert_record_with_id(
+    conn: &mut Conn,
+    record_id: i32,
+    task: &str,
+    project_id: i32,
+    start_date: chrono::DateTime<chrono::Utc>,
+    end_date: Option<chrono::DateTime<chrono::Utc>>,
+    notes: Option<&str>,
+) -> Result<()> {
+    use super::schema::records;
+    diesel::insert_into(records::table)
+        .values((
+            records::id.eq(record_id),
+            records::project_id.eq(project_id),
+            records::task.eq(task),
+            records::started_at.eq(start_date),
+            records::ended_at.eq(end_date),
+            records::notes.eq(notes),
+        ))
+        .execute(&mut conn.0)?;
+    Ok(())
+}
+
 pub fn update_record(
     conn: &mut Conn,
     record_id: i32,
     started_at: Option<chrono::DateTime<chrono::Utc>>,
-    ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    ended_at: Option<Option<chrono::DateTime<chrono::Utc>>>,
     task: Option<&str>,
     project_id: Option<i32>,
+    notes: Option<Option<&str>>,
 ) -> Result<Record> {
     use super::schema::records;
     let record = diesel::update(records::table)
@@ -181,6 +826,7 @@ pub fn update_record(
             ended_at,
             task,
             project_id,
+            notes,
         })
         .returning(Record::as_returning())
         .get_result(&mut conn.0)?;
@@ -188,6 +834,92 @@ pub fn update_record(
     Ok(record)
 }
 
+pub fn insert_break(
+    conn: &mut Conn,
+    record_id: i32,
+    started_at: chrono::DateTime<chrono::Utc>,
+    ended_at: chrono::DateTime<chrono::Utc>,
+) -> Result<Break> {
+    use super::schema::breaks;
+
+    Ok(diesel::insert_into(breaks::table)
+        .values((
+            breaks::record_id.eq(record_id),
+            breaks::started_at.eq(started_at),
+            breaks::ended_at.eq(ended_at),
+        ))
+        .returning(Break::as_returning())
+        .get_result(&mut conn.0)?)
+}
+
+/// Returns the breaks that overlap the given date range, ordered by their
+/// start.
+pub fn get_breaks(
+    conn: &mut Conn,
+    start_date: chrono::DateTime<chrono::Utc>,
+    end_date: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<Break>> {
+    use super::schema::breaks;
+
+    Ok(breaks::table
+        .filter(breaks::ended_at.gt(start_date))
+        .filter(breaks::started_at.lt(end_date))
+        .order(breaks::started_at)
+        .select(Break::as_select())
+        .load(&mut conn.0)?)
+}
+
+pub fn get_record_breaks(conn: &mut Conn, record_id: i32) -> Result<Vec<Break>> {
+    use super::schema::breaks;
+
+    Ok(breaks::table
+        .filter(breaks::record_id.eq(record_id))
+        .order(breaks::started_at)
+        .select(Break::as_select())
+        .load(&mut conn.0)?)
+}
+
+/// Moves the breaks that start at or after the given date to another record.
+pub fn move_record_breaks(
+    conn: &mut Conn,
+    from_record_id: i32,
+    to_record_id: i32,
+    after: chrono::DateTime<chrono::Utc>,
+) -> Result<()> {
+    use super::schema::breaks;
+
+    diesel::update(
+        breaks::table
+            .filter(breaks::record_id.eq(from_record_id))
+            .filter(breaks::started_at.ge(after)),
+    )
+    .set(breaks::record_id.eq(to_record_id))
+    .execute(&mut conn.0)?;
+    Ok(())
+}
+
+pub fn set_record_paused(conn: &mut Conn, record_id: i32, paused: bool) -> Result<()> {
+    use super::schema::records;
+    let count = diesel::update(records::table.filter(records::id.eq(record_id)))
+        .set(records::paused.eq(paused))
+        .execute(&mut conn.0)?;
+    if count < 1 {
+        bail!("No record found with id {record_id}")
+    }
+    Ok(())
+}
+
+pub fn set_record_billable(conn: &mut Conn, record_id: i32, billable: Option<bool>) -> Result<()> {
+    use super::schema::records;
+    let count = diesel::update(records::table.filter(records::id.eq(record_id)))
+        .set(records::billable.eq(billable))
+        .execute(&mut conn.0)?;
+    if count < 1 {
+        bail!("No record found with id {record_id}")
+    }
+    Ok(())
+}
+
 pub fn delete_record(conn: &mut Conn, record_id: i32) -> Result<Record> {
     use super::schema::records;
     let record = diesel::delete(records::table.filter(records::id.eq(record_id)))
@@ -197,6 +929,36 @@ pub fn delete_record(conn: &mut Conn, record_id: i32) -> Result<Record> {
     Ok(record)
 }
 
+/// Deletes a record if it exists, returning whether anything was deleted.
+pub fn delete_record_if_exists(conn: &mut Conn, record_id: i32) -> Result<bool> {
+    use super::schema::records;
+    let count =
+        diesel::delete(records::table.filter(records::id.eq(record_id))).execute(&mut conn.0)?;
+
+    Ok(count > 0)
+}
+
+pub fn find_record(conn: &mut Conn, record_id: i32) -> Result<Option<Record>> {
+    use super::schema::records;
+
+    Ok(records::table
+        .filter(records::id.eq(record_id))
+        .select(Record::as_select())
+        .get_result(&mut conn.0)
+        .optional()?)
+}
+
+pub fn get_record(conn: &mut Conn, record_id: i32) -> Result<Record> {
+    use super::schema::records;
+
+    let record = records::table
+        .filter(records::id.eq(record_id))
+        .select(Record::as_select())
+        .get_result(&mut conn.0)?;
+
+    Ok(record)
+}
+
 pub fn get_project_for_record(conn: &mut Conn, record_id: i32) -> Result<Project> {
     use super::schema::projects;
     use super::schema::records;
@@ -211,15 +973,21 @@ pub fn get_project_for_record(conn: &mut Conn, record_id: i32) -> Result<Project
 }
 
 pub type RecordTuple = (Record, Project);
-pub fn query_records(
-    conn: &mut Conn,
+
+/// Returns all records that overlap the given date range.  If any tags are
+/// given, only records that have all of those tags will be returned.
+pub fn query_records<'a>(
+    conn: &'a mut Conn,
     start_date: chrono::DateTime<chrono::Utc>,
     end_date: chrono::DateTime<chrono::Utc>,
-) -> Result<impl Iterator<Item = QueryResult<RecordTuple>> + '_> {
+    tags: &[&str],
+) -> Result<impl Iterator<Item = QueryResult<RecordTuple>> + 'a> {
     use super::schema::projects;
     use super::schema::records;
+    use super::schema::records_tags;
+    use super::schema::tags as tags_table;
 
-    Ok(records::table
+    let mut query = records::table
         .inner_join(projects::table)
         .filter(
             records::ended_at
@@ -228,7 +996,19 @@ pub fn query_records(
         )
         .filter(records::started_at.lt(end_date))
         .order(records::started_at)
-        .load_iter(&mut conn.0)?)
+        .into_boxed();
+    for tag in tags {
+        query = query.filter(
+            records::id.eq_any(
+                records_tags::table
+                    .inner_join(tags_table::table)
+                    .filter(tags_table::name.eq(tag.to_string()))
+                    .select(records_tags::record_id),
+            ),
+        );
+    }
+
+    Ok(query.load_iter(&mut conn.0)?)
 }
 
 pub fn query_records_all(
@@ -242,3 +1022,233 @@ pub fn query_records_all(
         .order(records::started_at)
         .load_iter(&mut conn.0)?)
 }
+
+pub fn insert_journal_entry(
+    conn: &mut Conn,
+    command: &str,
+    created_at: chrono::DateTime<chrono::Utc>,
+) -> Result<JournalEntry> {
+    use super::schema::journal;
+    let entry = diesel::insert_into(journal::table)
+        .values((
+            journal::command.eq(command),
+            journal::created_at.eq(created_at),
+        ))
+        .returning(JournalEntry::as_returning())
+        .get_result(&mut conn.0)?;
+    Ok(entry)
+}
+
+pub fn insert_journal_record(
+    conn: &mut Conn,
+    journal_id: i32,
+    record_id: i32,
+    before_state: Option<&str>,
+    after_state: Option<&str>,
+) -> Result<()> {
+    use super::schema::journal_records;
+    diesel::insert_into(journal_records::table)
+        .values((
+            journal_records::journal_id.eq(journal_id),
+            journal_records::record_id.eq(record_id),
+            journal_records::before_state.eq(before_state),
+            journal_records::after_state.eq(after_state),
+        ))
+        .execute(&mut conn.0)?;
+    Ok(())
+}
+
+/// Returns up to `count` journal entries that have (or have not) been undone.
+/// Entries that have not been undone are returned newest first, so that they
+/// can be undone in order, and entries that have been undone are returned
+/// oldest first, so that they can be redone in order.
+pub fn get_journal_entries(conn: &mut Conn, undone: bool, count: i64) -> Result<Vec<JournalEntry>> {
+    use super::schema::journal;
+
+    let query = journal::table
+        .filter(journal::undone.eq(undone))
+        .select(JournalEntry::as_select())
+        .limit(count);
+    let entries = match undone {
+        false => query.order(journal::id.desc()).load(&mut conn.0)?,
+        true => query.order(journal::id.asc()).load(&mut conn.0)?,
+    };
+    Ok(entries)
+}
+
+pub fn get_journal_records(conn: &mut Conn, journal_id: i32) -> Result<Vec<JournalRecord>> {
+    use super::schema::journal_records;
+
+    Ok(journal_records::table
+        .filter(journal_records::journal_id.eq(journal_id))
+        .select(JournalRecord::as_select())
+        .load(&mut conn.0)?)
+}
+
+pub fn set_journal_entry_undone(conn: &mut Conn, journal_id: i32, undone: bool) -> Result<()> {
+    use super::schema::journal;
+    diesel::update(journal::table.filter(journal::id.eq(journal_id)))
+        .set(journal::undone.eq(undone))
+        .execute(&mut conn.0)?;
+    Ok(())
+}
+
+/// Removes all journal entries that have been undone.  This is needed whenever
+/// a new change is made, as the undone entries can no longer be redone safely.
+pub fn delete_undone_journal_entries(conn: &mut Conn) -> Result<()> {
+    use super::schema::journal;
+    diesel::delete(journal::table.filter(journal::undone.eq(true))).execute(&mut conn.0)?;
+    Ok(())
+}
+
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
+#[diesel(table_name = super::schema::absences)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Absence {
+    pub id: i32,
+    pub date: chrono::NaiveDate,
+    pub kind: String,
+    pub half_day: bool,
+    pub description: Option<String>,
+}
+
+pub fn insert_absence(
+    conn: &mut Conn,
+    date: chrono::NaiveDate,
+    kind: &str,
+    half_day: bool,
+    description: Option<&str>,
+    uid: Option<&str>,
+) -> Result<Absence> {
+    use super::schema::absences;
+    let absence = diesel::insert_into(absences::table)
+        .values((
+            absences::date.eq(date),
+            absences::kind.eq(kind),
+            absences::half_day.eq(half_day),
+            absences::description.eq(description),
+            absences::uid.eq(uid),
+        ))
+        .returning(Absence::as_returning())
+        .get_result(&mut conn.0)?;
+    Ok(absence)
+}
+
+/// Returns all absences between the two dates (inclusive), ordered by date.
+pub fn query_absences(
+    conn: &mut Conn,
+    from: chrono::NaiveDate,
+    until: chrono::NaiveDate,
+) -> Result<Vec<Absence>> {
+    use super::schema::absences;
+
+    Ok(absences::table
+        .filter(absences::date.between(from, until))
+        .order((absences::date, absences::id))
+        .select(Absence::as_select())
+        .load(&mut conn.0)?)
+}
+
+pub fn delete_absence(conn: &mut Conn, absence_id: i32) -> Result<Absence> {
+    use super::schema::absences;
+    let absence = diesel::delete(absences::table.filter(absences::id.eq(absence_id)))
+        .returning(Absence::as_returning())
+        .get_result(&mut conn.0)?;
+
+    Ok(absence)
+}
+
+/// Deletes all absences that were imported from the calendar event with the
+/// given UID, returning the dates of the deleted absences.
+pub fn delete_absences_by_uid(conn: &mut Conn, uid: &str) -> Result<Vec<chrono::NaiveDate>> {
+    use super::schema::absences;
+    let dates = diesel::delete(absences::table.filter(absences::uid.eq(uid)))
+        .returning(absences::date)
+        .get_results(&mut conn.0)?;
+
+    Ok(dates)
+}
+
+#[derive(Queryable, Identifiable, Selectable, Debug, PartialEq)]
+#[diesel(table_name = super::schema::overtime_adjustments)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Adjustment {
+    pub id: i32,
+    pub date: chrono::NaiveDate,
+    pub duration_seconds: i32,
+    pub description: Option<String>,
+}
+
+pub fn insert_adjustment(
+    conn: &mut Conn,
+    date: chrono::NaiveDate,
+    duration_seconds: i32,
+    description: Option<&str>,
+) -> Result<Adjustment> {
+    use super::schema::overtime_adjustments;
+    let adjustment = diesel::insert_into(overtime_adjustments::table)
+        .values((
+            overtime_adjustments::date.eq(date),
+            overtime_adjustments::duration_seconds.eq(duration_seconds),
+            overtime_adjustments::description.eq(description),
+        ))
+        .returning(Adjustment::as_returning())
+        .get_result(&mut conn.0)?;
+    Ok(adjustment)
+}
+
+pub fn query_adjustments(conn: &mut Conn) -> Result<Vec<Adjustment>> {
+    use super::schema::overtime_adjustments;
+
+    Ok(overtime_adjustments::table
+        .order((overtime_adjustments::date, overtime_adjustments::id))
+        .select(Adjustment::as_select())
+        .load(&mut conn.0)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::Records;
+
+    #[test]
+    fn opens_databases_with_tags_of_deleted_records() {
+        let path = std::env::temp_dir().join(format!("tst-orphans-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // set up the database as it was before foreign keys were enforced
+        let mut conn = SqliteConnection::establish(&path.to_string_lossy()).unwrap();
+        for migration in conn.pending_migrations(MIGRATIONS).unwrap() {
+            if migration.name().to_string() == "2026-10-16-220000_remove_orphaned_record_tags" {
+                break;
+            }
+            conn.run_migration(&migration).unwrap();
+        }
+        conn.batch_execute(
+            "INSERT INTO projects (id, name) VALUES (1, 'proj');
+            INSERT INTO records (id, task, project_id, started_at)
+            VALUES (1, 'task', 1, '2024-05-12T10:00:00Z');
+            INSERT INTO tags (id, name) VALUES (1, 'meeting');
+            INSERT INTO records_tags (record_id, tag_id) VALUES (1, 1);
+            DELETE FROM records WHERE id = 1;",
+        )
+        .unwrap();
+        drop(conn);
+
+        let mut conn = establish_connection(&path).unwrap();
+        let mut records = Records::new(&mut conn);
+        let started_at = "2024-05-12T11:00:00Z".parse().unwrap();
+        let record = records
+            .add_record("other", "proj", started_at, None)
+            .unwrap();
+        assert_eq!(record.id, crate::records::sqid(1));
+        assert_eq!(
+            records.get_record(&record.id).unwrap().tags,
+            Vec::<String>::new()
+        );
+        records.delete_record(&record.id).unwrap();
+
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
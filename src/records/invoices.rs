@@ -0,0 +1,274 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+
+use super::{
+    db::{
+        self, begin_transaction, commit_transaction, delete_invoice, find_client, find_invoice,
+        get_invoice_lines, get_invoices, insert_invoice, insert_invoice_line, rollback_transaction,
+        set_records_invoice, Conn,
+    },
+    desqid,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Invoice {
+    /// The invoice number, e.g. "2024-0001"
+    pub number: String,
+    pub client: String,
+    pub currency: String,
+    pub issued_on: NaiveDate,
+    /// The first day of the period that was billed
+    pub period_start: NaiveDate,
+    /// The last day of the period that was billed
+    pub period_end: NaiveDate,
+    pub lines: Vec<InvoiceLine>,
+}
+
+impl Invoice {
+    /// The total amount, in hundredths of the currency (e.g. cents)
+    pub fn total(&self) -> i64 {
+        self.lines.iter().map(|line| line.amount).sum()
+    }
+
+    /// The total time billed
+    pub fn duration(&self) -> Duration {
+        self.lines.iter().map(|line| line.duration).sum()
+    }
+}
+
+/// The time billed for a single task in a project
+#[derive(Debug, PartialEq, Clone)]
+pub struct InvoiceLine {
+    pub project: String,
+    pub task: String,
+    /// The hourly rate in hundredths of the currency (e.g. cents)
+    pub hourly_rate: i64,
+    pub duration: Duration,
+    /// The amount in hundredths of the currency (e.g. cents)
+    pub amount: i64,
+}
+
+impl From<db::InvoiceLine> for InvoiceLine {
+    fn from(line: db::InvoiceLine) -> Self {
+        Self {
+            project: line.project,
+            task: line.task,
+            hourly_rate: line.hourly_rate.into(),
+            duration: Duration::seconds(line.duration_seconds.into()),
+            amount: line.amount.into(),
+        }
+    }
+}
+
+pub struct Invoices<'a> {
+    db: &'a mut Conn,
+}
+
+impl<'a> Invoices<'a> {
+    pub fn new(db: &'a mut Conn) -> Self {
+        Self { db }
+    }
+
+    /// Creates a new invoice for the client, and marks the given records as
+    /// billed on it, so that they can't be billed again or changed.  Invoices
+    /// are numbered consecutively within each year.  Fails if any of the
+    /// records have already been invoiced.
+    pub fn create(
+        &mut self,
+        client_name: &str,
+        issued_on: NaiveDate,
+        (period_start, period_end): (NaiveDate, NaiveDate),
+        lines: Vec<InvoiceLine>,
+        record_ids: &[String],
+    ) -> Result<Invoice> {
+        if lines.is_empty() {
+            bail!("Nothing to invoice for {client_name}");
+        }
+        let client = find_client(self.db, client_name)?
+            .ok_or(anyhow!("No client found with name {client_name}"))?;
+        let record_ids = record_ids
+            .iter()
+            .map(|id| desqid(id))
+            .collect::<Result<Vec<_>>>()?;
+
+        begin_transaction(self.db)?;
+        let result = (|| {
+            let number = self.next_number(issued_on.year())?;
+            let invoice = insert_invoice(
+                self.db,
+                &number,
+                &client,
+                issued_on,
+                period_start,
+                period_end,
+            )?;
+            for line in &lines {
+                insert_invoice_line(
+                    self.db,
+                    invoice.id,
+                    &line.project,
+                    &line.task,
+                    line.hourly_rate.try_into()?,
+                    line.duration.num_seconds().try_into()?,
+                    line.amount.try_into()?,
+                )?;
+            }
+            if set_records_invoice(self.db, &record_ids, invoice.id)? != record_ids.len() {
+                bail!("Some of the records have already been invoiced");
+            }
+            Ok(number)
+        })();
+
+        match result {
+            Ok(number) => {
+                commit_transaction(self.db)?;
+                Ok(Invoice {
+                    number,
+                    client: client.name,
+                    currency: client.currency,
+                    issued_on,
+                    period_start,
+                    period_end,
+                    lines,
+                })
+            }
+            Err(err) => {
+                rollback_transaction(self.db)?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Lists all invoices, oldest first.
+    pub fn list(&mut self) -> Result<Vec<Invoice>> {
+        get_invoices(self.db)?
+            .into_iter()
+            .map(|(invoice, client)| self.load(invoice, client))
+            .collect()
+    }
+
+    pub fn get(&mut self, number: &str) -> Result<Invoice> {
+        let (invoice, client) = find_invoice(self.db, number)?
+            .ok_or(anyhow!("No invoice found with number {number}"))?;
+        self.load(invoice, client)
+    }
+
+    /// Deletes the invoice, so that its records can be changed and invoiced
+    /// again.  Only the most recent invoice can be cancelled, so that there
+    /// are no gaps in the invoice numbers.  Returns the number of records
+    /// that were released.
+    pub fn cancel(&mut self, number: &str) -> Result<usize> {
+        let (invoice, _) = find_invoice(self.db, number)?
+            .ok_or(anyhow!("No invoice found with number {number}"))?;
+        let latest = get_invoices(self.db)?.pop().map(|(invoice, _)| invoice);
+        if latest.as_ref() != Some(&invoice) {
+            bail!("Only the most recent invoice can be cancelled");
+        }
+
+        begin_transaction(self.db)?;
+        match delete_invoice(self.db, invoice.id) {
+            Ok(count) => {
+                commit_transaction(self.db)?;
+                Ok(count)
+            }
+            Err(err) => {
+                rollback_transaction(self.db)?;
+                Err(err)
+            }
+        }
+    }
+
+    fn load(&mut self, invoice: db::Invoice, client: db::Client) -> Result<Invoice> {
+        Ok(Invoice {
+            lines: get_invoice_lines(self.db, invoice.id)?
+                .into_iter()
+                .map(InvoiceLine::from)
+                .collect(),
+            number: invoice.number,
+            client: client.name,
+            currency: invoice.currency,
+            issued_on: invoice.issued_on,
+            period_start: invoice.period_start,
+            period_end: invoice.period_end,
+        })
+    }
+
+    fn next_number(&mut self, year: i32) -> Result<String> {
+        let prefix = format!("{year}-");
+        let last = get_invoices(self.db)?
+            .iter()
+            .filter_map(|(invoice, _)| invoice.number.strip_prefix(&prefix)?.parse::<u32>().ok())
+            .max()
+            .unwrap_or_default();
+        Ok(format!("{prefix}{:04}", last + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{establish_connection, Clients, Records};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    fn line(task: &str) -> InvoiceLine {
+        InvoiceLine {
+            project: "acme".into(),
+            task: task.into(),
+            hourly_rate: 8000,
+            duration: Duration::minutes(90),
+            amount: 12000,
+        }
+    }
+
+    #[test]
+    fn invoiced_records_are_locked_until_the_invoice_is_cancelled() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let start = "2024-05-12T10:00:00Z".parse().unwrap();
+        let end = "2024-05-12T11:30:00Z".parse().unwrap();
+        let mut records = Records::new(&mut conn);
+        let record = records
+            .journalled("go", |recs| {
+                recs.add_record("design", "acme", start, Some(end))
+            })
+            .unwrap();
+        Clients::new(&mut conn).add("ACME", "EUR").unwrap();
+
+        let mut invoices = Invoices::new(&mut conn);
+        let period = (date(1), date(31));
+        let ids = vec![record.id.clone()];
+        let invoice = invoices
+            .create("ACME", date(31), period, vec![line("design")], &ids)
+            .unwrap();
+        assert_eq!(invoice.number, "2024-0001");
+        assert_eq!(invoice.total(), 12000);
+        assert_eq!(invoices.get("2024-0001").unwrap(), invoice);
+        assert!(invoices
+            .create("ACME", date(31), period, vec![line("design")], &ids)
+            .is_err());
+
+        let mut records = Records::new(&mut conn);
+        assert!(records.get_record(&record.id).unwrap().invoiced);
+        assert!(records.set_notes(&record.id, Some("changed")).is_err());
+        assert!(records.delete_record(&record.id).is_err());
+        assert!(records.undo(1).is_err());
+
+        let mut invoices = Invoices::new(&mut conn);
+        let second = invoices
+            .create("ACME", date(31), period, vec![line("review")], &[])
+            .unwrap();
+        assert_eq!(second.number, "2024-0002");
+        assert!(invoices.cancel("2024-0001").is_err());
+        assert_eq!(invoices.cancel("2024-0002").unwrap(), 0);
+        assert_eq!(invoices.cancel("2024-0001").unwrap(), 1);
+        assert!(invoices.list().unwrap().is_empty());
+
+        let mut records = Records::new(&mut conn);
+        assert!(!records.get_record(&record.id).unwrap().invoiced);
+        records.set_notes(&record.id, Some("changed")).unwrap();
+    }
+}
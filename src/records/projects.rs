@@ -0,0 +1,354 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use super::{
+    clients::Rate,
+    db::{
+        self, begin_transaction, commit_transaction, find_client, find_project, get_projects,
+        query_records_all, rename_project, rollback_transaction, set_project_archived,
+        set_project_billable, set_project_client, set_project_non_working, upsert_rate, Conn,
+    },
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Project {
+    pub name: String,
+    /// Whether time spent on this project is excluded from the time worked,
+    /// e.g. for commuting or volunteering.
+    pub non_working: bool,
+    /// Whether the project is no longer in use.  Archived projects can still
+    /// be used, but a warning is shown.
+    pub archived: bool,
+    /// Whether time spent on this project is billed to its client.  This can
+    /// be overridden for individual records.
+    pub billable: bool,
+}
+
+impl From<db::Project> for Project {
+    fn from(project: db::Project) -> Self {
+        Self {
+            name: project.name,
+            non_working: project.non_working,
+            archived: project.archived,
+            billable: project.billable,
+        }
+    }
+}
+
+/// A project along with the time recorded against it
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProjectUsage {
+    pub project: Project,
+    pub total: Duration,
+    /// The start of the most recent record in this project
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+pub struct Projects<'a> {
+    db: &'a mut Conn,
+}
+
+impl<'a> Projects<'a> {
+    pub fn new(db: &'a mut Conn) -> Self {
+        Self { db }
+    }
+
+    pub fn get(&mut self, project_name: &str) -> Result<Option<Project>> {
+        Ok(find_project(self.db, project_name)?.map(Project::from))
+    }
+
+    /// Lists all projects in alphabetical order, along with how much time has
+    /// been spent on each one.  Open records are counted until `now`.
+    pub fn list(&mut self, now: DateTime<Utc>) -> Result<Vec<ProjectUsage>> {
+        let mut usage = HashMap::<i32, (Duration, DateTime<Utc>)>::new();
+        for row in query_records_all(self.db)? {
+            let (record, _) = row?;
+            let duration = record.ended_at.unwrap_or(now) - record.started_at;
+            let (total, last_used) = usage
+                .entry(record.project_id)
+                .or_insert((Duration::zero(), record.started_at));
+            *total += duration;
+            *last_used = record.started_at.max(*last_used);
+        }
+
+        Ok(get_projects(self.db)?
+            .into_iter()
+            .map(|project| {
+                let (total, last_used) = match usage.remove(&project.id) {
+                    Some((total, last_used)) => (total, Some(last_used)),
+                    None => (Duration::zero(), None),
+                };
+                ProjectUsage {
+                    project: project.into(),
+                    total,
+                    last_used,
+                }
+            })
+            .collect())
+    }
+
+    /// Finds existing projects whose names are similar to the given name, e.g.
+    /// because they only differ in case or by a typo.  Archived projects are
+    /// ignored.
+    pub fn similar_names(&mut self, project_name: &str) -> Result<Vec<String>> {
+        let name = project_name.to_lowercase();
+        Ok(get_projects(self.db)?
+            .into_iter()
+            .filter(|project| !project.archived && project.name != project_name)
+            .filter(|project| {
+                let other = project.name.to_lowercase();
+                // allow one typo for short names, and more for longer names
+                let max_distance = name.chars().count().max(other.chars().count()) / 5 + 1;
+                edit_distance(&name, &other) <= max_distance
+            })
+            .map(|project| project.name)
+            .collect())
+    }
+
+    /// Renames an existing project.  Fails if the new name is already used,
+    /// in which case the projects should be merged instead.
+    /// Sub-projects are renamed along with their parent.
+    pub fn rename(&mut self, project_name: &str, new_name: &str) -> Result<Project> {
+        begin_transaction(self.db)?;
+        match rename_project(self.db, project_name, new_name) {
+            Ok(project) => {
+                commit_transaction(self.db)?;
+                Ok(project.into())
+            }
+            Err(err) => {
+                rollback_transaction(self.db)?;
+                Err(err)
+            }
+        }
+    }
+
+    pub fn set_archived(&mut self, project_name: &str, archived: bool) -> Result<Project> {
+        Ok(set_project_archived(self.db, project_name, archived)?.into())
+    }
+
+    pub fn set_billable(&mut self, project_name: &str, billable: bool) -> Result<Project> {
+        Ok(set_project_billable(self.db, project_name, billable)?.into())
+    }
+
+    /// Assigns the project (and its sub-projects) to a client, or removes the
+    /// assignment if no client is given.
+    pub fn set_client(&mut self, project_name: &str, client_name: Option<&str>) -> Result<Project> {
+        let client_id = client_name
+            .map(|client_name| {
+                find_client(self.db, client_name)?
+                    .map(|client| client.id)
+                    .ok_or(anyhow!("No client found with name {client_name}"))
+            })
+            .transpose()?;
+        Ok(set_project_client(self.db, project_name, client_id)?.into())
+    }
+
+    /// Sets the hourly rate for the project (and its sub-projects) from the
+    /// given date onwards, overriding the client's default rate.
+    pub fn set_rate(
+        &mut self,
+        project_name: &str,
+        hourly_rate: i64,
+        effective_from: NaiveDate,
+    ) -> Result<Rate> {
+        let project = find_project(self.db, project_name)?
+            .ok_or(anyhow!("No project found with name {project_name}"))?;
+        let hourly_rate = hourly_rate.try_into()?;
+        Ok(upsert_rate(self.db, None, Some(project.id), effective_from, hourly_rate)?.into())
+    }
+
+    /// Marks an existing project as working or non-working time.
+    pub fn set_non_working(&mut self, project_name: &str, non_working: bool) -> Result<Project> {
+        Ok(set_project_non_working(self.db, project_name, non_working)?.into())
+    }
+}
+
+/// Whether the project is the same as the other project, or one of its
+/// sub-projects (e.g. "acme/website" is in "acme").
+pub fn is_in_project(project: &str, other: &str) -> bool {
+    project
+        .strip_prefix(other)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Lists the projects containing the given project, starting with its direct
+/// parent (e.g. "acme/website" and then "acme" for "acme/website/frontend").
+pub fn parent_projects(project: &str) -> impl Iterator<Item = &str> {
+    project
+        .char_indices()
+        .rev()
+        .filter(|(_, c)| *c == '/')
+        .map(|(index, _)| &project[..index])
+}
+
+/// The Levenshtein distance between two strings, i.e. the number of single
+/// character insertions, deletions, or substitutions needed to turn one into
+/// the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{establish_connection, Records};
+
+    #[test]
+    fn non_working_projects_are_marked_on_records() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let start = "2024-05-12T10:00:00Z".parse().unwrap();
+        let record = Records::new(&mut conn)
+            .add_record("drive", "commute", start, None)
+            .unwrap();
+        assert!(!record.non_working);
+
+        let project = Projects::new(&mut conn)
+            .set_non_working("commute", true)
+            .unwrap();
+        assert_eq!(
+            project,
+            Project {
+                name: "commute".into(),
+                non_working: true,
+                archived: false,
+                billable: true,
+            }
+        );
+        let record = Records::new(&mut conn).get_record(&record.id).unwrap();
+        assert!(record.non_working);
+
+        assert!(Projects::new(&mut conn)
+            .set_non_working("unknown", true)
+            .is_err());
+    }
+
+    #[test]
+    fn projects_can_be_listed_renamed_and_archived() {
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let start = "2024-05-12T10:00:00Z".parse().unwrap();
+        let end = "2024-05-12T11:30:00Z".parse().unwrap();
+        records
+            .add_record("a", "clientx", start, Some(end))
+            .unwrap();
+        records
+            .add_record("b", "other", end, Some(end + Duration::hours(1)))
+            .unwrap();
+
+        let mut projects = Projects::new(&mut conn);
+        projects.rename("clientx", "client-x").unwrap();
+        assert!(projects.rename("other", "client-x").is_err());
+        projects.set_archived("other", true).unwrap();
+
+        let list = projects.list(end).unwrap();
+        assert_eq!(
+            list,
+            vec![
+                ProjectUsage {
+                    project: Project {
+                        name: "client-x".into(),
+                        non_working: false,
+                        archived: false,
+                        billable: true,
+                    },
+                    total: Duration::minutes(90),
+                    last_used: Some(start),
+                },
+                ProjectUsage {
+                    project: Project {
+                        name: "other".into(),
+                        non_working: false,
+                        archived: true,
+                        billable: true,
+                    },
+                    total: Duration::hours(1),
+                    last_used: Some(end),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn similar_names_finds_typos_and_different_cases() {
+        assert_eq!(edit_distance("clientx", "client-x"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let start = "2024-05-12T10:00:00Z".parse().unwrap();
+        for project in ["client-x", "Website", "internal", "old-client"] {
+            records.add_record("task", project, start, None).unwrap();
+        }
+        let mut projects = Projects::new(&mut conn);
+        projects.set_archived("old-client", true).unwrap();
+
+        assert_eq!(projects.similar_names("clientx").unwrap(), vec!["client-x"]);
+        assert_eq!(projects.similar_names("website").unwrap(), vec!["Website"]);
+        assert!(projects.similar_names("oldclient").unwrap().is_empty());
+        assert!(projects.similar_names("holidays").unwrap().is_empty());
+    }
+    #[test]
+    fn sub_projects_are_renamed_with_their_parents() {
+        assert!(is_in_project("acme/website", "acme"));
+        assert!(is_in_project("acme", "acme"));
+        assert!(!is_in_project("acme-corp", "acme"));
+        assert_eq!(
+            parent_projects("acme/website/frontend").collect::<Vec<_>>(),
+            vec!["acme/website", "acme"]
+        );
+
+        let mut conn = establish_connection(":memory:").unwrap();
+        let mut records = Records::new(&mut conn);
+        let start = "2024-05-12T10:00:00Z".parse().unwrap();
+        let record = records
+            .add_record("task", "acme/website/frontend", start, None)
+            .unwrap();
+        assert!(records
+            .add_record("task", "acme//api", start, None)
+            .is_err());
+
+        let mut projects = Projects::new(&mut conn);
+        let names = |projects: &mut Projects| {
+            projects
+                .list(start)
+                .unwrap()
+                .into_iter()
+                .map(|usage| usage.project.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&mut projects),
+            vec!["acme", "acme/website", "acme/website/frontend"]
+        );
+        assert!(projects.rename("acme", "acme/other").is_err());
+
+        projects.rename("acme", "clients/acme").unwrap();
+        assert_eq!(
+            names(&mut projects),
+            vec![
+                "clients",
+                "clients/acme",
+                "clients/acme/website",
+                "clients/acme/website/frontend"
+            ]
+        );
+        let record = Records::new(&mut conn).get_record(&record.id).unwrap();
+        assert_eq!(record.project, "clients/acme/website/frontend");
+        assert!(Records::new(&mut conn)
+            .merge_projects("clients/acme", "clients")
+            .is_err());
+    }
+}
@@ -1,9 +1,103 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    absences (id) {
+        id -> Integer,
+        date -> Date,
+        kind -> Text,
+        half_day -> Bool,
+        description -> Nullable<Text>,
+        uid -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    breaks (id) {
+        id -> Integer,
+        record_id -> Integer,
+        started_at -> TimestamptzSqlite,
+        ended_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    clients (id) {
+        id -> Integer,
+        name -> Text,
+        currency -> Text,
+    }
+}
+
+diesel::table! {
+    invoice_lines (id) {
+        id -> Integer,
+        invoice_id -> Integer,
+        project -> Text,
+        task -> Text,
+        hourly_rate -> Integer,
+        duration_seconds -> Integer,
+        amount -> Integer,
+    }
+}
+
+diesel::table! {
+    invoices (id) {
+        id -> Integer,
+        number -> Text,
+        client_id -> Integer,
+        currency -> Text,
+        issued_on -> Date,
+        period_start -> Date,
+        period_end -> Date,
+    }
+}
+
+diesel::table! {
+    journal (id) {
+        id -> Integer,
+        command -> Text,
+        created_at -> TimestamptzSqlite,
+        undone -> Bool,
+    }
+}
+
+diesel::table! {
+    journal_records (journal_id, record_id) {
+        journal_id -> Integer,
+        record_id -> Integer,
+        before_state -> Nullable<Text>,
+        after_state -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    overtime_adjustments (id) {
+        id -> Integer,
+        date -> Date,
+        duration_seconds -> Integer,
+        description -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     projects (id) {
         id -> Integer,
         name -> Text,
+        non_working -> Bool,
+        archived -> Bool,
+        parent_id -> Nullable<Integer>,
+        client_id -> Nullable<Integer>,
+        billable -> Bool,
+    }
+}
+
+diesel::table! {
+    rates (id) {
+        id -> Integer,
+        client_id -> Nullable<Integer>,
+        project_id -> Nullable<Integer>,
+        effective_from -> Date,
+        hourly_rate -> Integer,
     }
 }
 
@@ -14,9 +108,51 @@ diesel::table! {
         project_id -> Integer,
         started_at -> TimestamptzSqlite,
         ended_at -> Nullable<TimestamptzSqlite>,
+        notes -> Nullable<Text>,
+        paused -> Bool,
+        billable -> Nullable<Bool>,
+        invoice_id -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    records_tags (record_id, tag_id) {
+        record_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Integer,
+        name -> Text,
     }
 }
 
+diesel::joinable!(breaks -> records (record_id));
+diesel::joinable!(invoice_lines -> invoices (invoice_id));
+diesel::joinable!(invoices -> clients (client_id));
+diesel::joinable!(journal_records -> journal (journal_id));
+diesel::joinable!(projects -> clients (client_id));
+diesel::joinable!(rates -> clients (client_id));
+diesel::joinable!(rates -> projects (project_id));
+diesel::joinable!(records -> invoices (invoice_id));
 diesel::joinable!(records -> projects (project_id));
+diesel::joinable!(records_tags -> records (record_id));
+diesel::joinable!(records_tags -> tags (tag_id));
 
-diesel::allow_tables_to_appear_in_same_query!(projects, records,);
+diesel::allow_tables_to_appear_in_same_query!(
+    absences,
+    breaks,
+    clients,
+    invoice_lines,
+    invoices,
+    journal,
+    journal_records,
+    overtime_adjustments,
+    projects,
+    rates,
+    records,
+    records_tags,
+    tags,
+);
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::BTreeMap;
+
+use chrono::Duration;
+use clap::ValueEnum;
+
+/// The direction in which durations should be rounded
+#[derive(ValueEnum, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingMode {
+    /// round up to the next multiple of the rounding unit
+    #[default]
+    Up,
+    /// round down to the previous multiple of the rounding unit
+    Down,
+    /// round to the closest multiple of the rounding unit
+    Nearest,
+    /// do not round at all
+    None,
+}
+
+/// Which durations should be rounded
+#[derive(ValueEnum, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingScope {
+    /// round each individual record
+    Record,
+    /// round the total time spent on each project per day
+    #[default]
+    ProjectDay,
+    /// round the total time worked per day
+    Day,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rounding {
+    pub unit: Duration,
+    pub mode: RoundingMode,
+    pub scope: RoundingScope,
+}
+
+/// The rounded time spent during a single day
+#[derive(Debug, PartialEq, Eq)]
+pub struct DayTotals {
+    /// The time spent per project.  If the rounding scope is `Day`, these
+    /// durations will not be rounded.
+    pub projects: BTreeMap<String, Duration>,
+    pub total: Duration,
+}
+
+impl Rounding {
+    pub fn new(unit: Duration, mode: RoundingMode, scope: RoundingScope) -> Self {
+        Self { unit, mode, scope }
+    }
+
+    /// Rounds a single duration to a multiple of the rounding unit according
+    /// to the rounding mode.
+    pub fn round(&self, duration: Duration) -> Duration {
+        let unit = self.unit.num_seconds();
+        if unit <= 0 {
+            return duration;
+        }
+
+        let value = duration.num_seconds();
+        let remainder = value.rem_euclid(unit);
+        let rounded = match self.mode {
+            RoundingMode::None => value,
+            _ if remainder == 0 => value,
+            RoundingMode::Up => value + unit - remainder,
+            RoundingMode::Down => value - remainder,
+            RoundingMode::Nearest if remainder * 2 >= unit => value + unit - remainder,
+            RoundingMode::Nearest => value - remainder,
+        };
+        Duration::seconds(rounded)
+    }
+
+    /// Sums up the durations of all the records in a single day, given as
+    /// pairs of project name and duration, rounding them according to the
+    /// rounding scope.
+    pub fn round_day(&self, records: impl IntoIterator<Item = (String, Duration)>) -> DayTotals {
+        let mut projects = BTreeMap::<String, Duration>::new();
+        for (project, duration) in records {
+            let duration = match self.scope {
+                RoundingScope::Record => self.round(duration),
+                _ => duration,
+            };
+            *projects.entry(project).or_default() += duration;
+        }
+
+        if self.scope == RoundingScope::ProjectDay {
+            for duration in projects.values_mut() {
+                *duration = self.round(*duration);
+            }
+        }
+
+        let total = projects.values().sum();
+        let total = match self.scope {
+            RoundingScope::Day => self.round(total),
+            _ => total,
+        };
+
+        DayTotals { projects, total }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rounding(mode: RoundingMode, scope: RoundingScope) -> Rounding {
+        Rounding::new(Duration::minutes(15), mode, scope)
+    }
+
+    fn day() -> Vec<(String, Duration)> {
+        vec![
+            ("a".into(), Duration::minutes(5)),
+            ("a".into(), Duration::minutes(5)),
+            ("b".into(), Duration::minutes(20)),
+        ]
+    }
+
+    #[test]
+    fn round_up_rounds_to_next_multiple() {
+        let rounding = rounding(RoundingMode::Up, RoundingScope::Record);
+        assert_eq!(rounding.round(Duration::minutes(1)), Duration::minutes(15));
+        assert_eq!(rounding.round(Duration::minutes(15)), Duration::minutes(15));
+        assert_eq!(rounding.round(Duration::minutes(16)), Duration::minutes(30));
+    }
+
+    #[test]
+    fn round_down_rounds_to_previous_multiple() {
+        let rounding = rounding(RoundingMode::Down, RoundingScope::Record);
+        assert_eq!(rounding.round(Duration::minutes(14)), Duration::zero());
+        assert_eq!(rounding.round(Duration::minutes(29)), Duration::minutes(15));
+    }
+
+    #[test]
+    fn round_nearest_rounds_half_up() {
+        let rounding = rounding(RoundingMode::Nearest, RoundingScope::Record);
+        assert_eq!(rounding.round(Duration::minutes(7)), Duration::zero());
+        assert_eq!(
+            rounding.round(Duration::seconds(7 * 60 + 30)),
+            Duration::minutes(15)
+        );
+        assert_eq!(rounding.round(Duration::minutes(22)), Duration::minutes(15));
+    }
+
+    #[test]
+    fn round_none_leaves_durations_unchanged() {
+        let rounding = rounding(RoundingMode::None, RoundingScope::Record);
+        assert_eq!(rounding.round(Duration::seconds(61)), Duration::seconds(61));
+    }
+
+    #[test]
+    fn zero_unit_leaves_durations_unchanged() {
+        let rounding = Rounding::new(
+            Duration::zero(),
+            RoundingMode::Up,
+            RoundingScope::ProjectDay,
+        );
+        assert_eq!(rounding.round(Duration::seconds(61)), Duration::seconds(61));
+    }
+
+    #[test]
+    fn round_day_with_record_scope_rounds_each_record() {
+        let totals = rounding(RoundingMode::Up, RoundingScope::Record).round_day(day());
+        assert_eq!(totals.projects["a"], Duration::minutes(30));
+        assert_eq!(totals.projects["b"], Duration::minutes(30));
+        assert_eq!(totals.total, Duration::minutes(60));
+    }
+
+    #[test]
+    fn round_day_with_project_day_scope_rounds_each_project() {
+        let totals = rounding(RoundingMode::Up, RoundingScope::ProjectDay).round_day(day());
+        assert_eq!(totals.projects["a"], Duration::minutes(15));
+        assert_eq!(totals.projects["b"], Duration::minutes(30));
+        assert_eq!(totals.total, Duration::minutes(45));
+    }
+
+    #[test]
+    fn round_day_with_day_scope_only_rounds_the_total() {
+        let totals = rounding(RoundingMode::Up, RoundingScope::Day).round_day(day());
+        assert_eq!(totals.projects["a"], Duration::minutes(10));
+        assert_eq!(totals.projects["b"], Duration::minutes(20));
+        assert_eq!(totals.total, Duration::minutes(30));
+    }
+}
@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use chrono::{Datelike, NaiveDate};
+
+/// The number of hours expected to be worked on each day of the week,
+/// starting with Monday.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeekHours(pub [f64; 7]);
+
+impl WeekHours {
+    /// The same number of hours on every weekday, and no hours at weekends.
+    pub fn weekdays(hours: f64) -> Self {
+        Self([hours, hours, hours, hours, hours, 0.0, 0.0])
+    }
+
+    pub fn for_date(&self, date: NaiveDate) -> f64 {
+        self.0[date.weekday().num_days_from_monday() as usize]
+    }
+}
+
+/// A change to the usual schedule that applies between two dates (inclusive).
+/// If there is no end date, the change applies indefinitely.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleChange {
+    pub from: NaiveDate,
+    pub until: Option<NaiveDate>,
+    pub hours: WeekHours,
+}
+
+impl ScheduleChange {
+    fn contains(&self, date: NaiveDate) -> bool {
+        self.from <= date && self.until.is_none_or(|until| date <= until)
+    }
+}
+
+/// The expected working hours for each day
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    usual: WeekHours,
+    changes: Vec<ScheduleChange>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new(WeekHours::weekdays(8.0), Vec::new())
+    }
+}
+
+impl Schedule {
+    pub fn new(usual: WeekHours, changes: Vec<ScheduleChange>) -> Self {
+        Self { usual, changes }
+    }
+
+    /// Returns the number of hours that should be worked on the given date.
+    /// If multiple schedule changes apply to the same date, the one that was
+    /// defined last is used.
+    pub fn expected_hours(&self, date: NaiveDate) -> f64 {
+        self.changes
+            .iter()
+            .rev()
+            .find(|change| change.contains(date))
+            .map(|change| &change.hours)
+            .unwrap_or(&self.usual)
+            .for_date(date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn default_schedule_expects_eight_hours_on_weekdays() {
+        let schedule = Schedule::default();
+        // 2024-05-10 is a Friday
+        assert_eq!(schedule.expected_hours(date(5, 10)), 8.0);
+        assert_eq!(schedule.expected_hours(date(5, 11)), 0.0);
+        assert_eq!(schedule.expected_hours(date(5, 12)), 0.0);
+    }
+
+    #[test]
+    fn schedule_changes_apply_within_their_date_range() {
+        let schedule = Schedule::new(
+            WeekHours::weekdays(8.0),
+            vec![ScheduleChange {
+                from: date(5, 1),
+                until: Some(date(5, 31)),
+                hours: WeekHours([8.0, 8.0, 8.0, 8.0, 6.0, 0.0, 0.0]),
+            }],
+        );
+        assert_eq!(schedule.expected_hours(date(4, 26)), 8.0);
+        assert_eq!(schedule.expected_hours(date(5, 3)), 6.0);
+        assert_eq!(schedule.expected_hours(date(5, 31)), 6.0);
+        assert_eq!(schedule.expected_hours(date(6, 7)), 8.0);
+    }
+
+    #[test]
+    fn later_schedule_changes_take_precedence() {
+        let schedule = Schedule::new(
+            WeekHours::weekdays(8.0),
+            vec![
+                ScheduleChange {
+                    from: date(1, 1),
+                    until: None,
+                    hours: WeekHours([8.0, 8.0, 8.0, 8.0, 0.0, 0.0, 0.0]),
+                },
+                ScheduleChange {
+                    from: date(5, 1),
+                    until: None,
+                    hours: WeekHours([0.0, 8.0, 8.0, 8.0, 8.0, 0.0, 4.0]),
+                },
+            ],
+        );
+        // 2024-04-29 is a Monday
+        assert_eq!(schedule.expected_hours(date(4, 29)), 8.0);
+        assert_eq!(schedule.expected_hours(date(5, 3)), 8.0);
+        assert_eq!(schedule.expected_hours(date(5, 6)), 0.0);
+        assert_eq!(schedule.expected_hours(date(5, 12)), 4.0);
+    }
+}